@@ -1,9 +1,16 @@
 #![no_main]
 use libfuzzer_sys::fuzz_target;
-use libterm::core::{Terminal, VtParser};
+use libterm::core::{ParserLimits, Terminal, VtParser};
 
 fuzz_target!(|data: &[u8]| {
     let mut terminal = Terminal::new(80, 24);
     let mut parser = VtParser::new();
     terminal.feed_bytes(&mut parser, data);
+
+    // Whatever the input, an unfinished sequence holds at most what the limits allow
+    let limits = ParserLimits::default();
+    let bound = limits.max_params * (std::mem::size_of::<u16>() + std::mem::size_of::<usize>())
+        + limits.max_intermediates
+        + limits.max_osc_len.max(limits.max_apc_len);
+    assert!(parser.buffered_len() <= bound, "parser buffered {} bytes", parser.buffered_len());
 });
@@ -68,8 +68,14 @@ class TerminalMetalView: NSView, CALayerDelegate {
 
     override func viewDidMoveToWindow() {
         super.viewDidMoveToWindow()
-        if window != nil {
-            window?.makeFirstResponder(self)
+        NotificationCenter.default.removeObserver(self, name: NSWindow.didBecomeKeyNotification, object: nil)
+        NotificationCenter.default.removeObserver(self, name: NSWindow.didResignKeyNotification, object: nil)
+        if let window = window {
+            window.makeFirstResponder(self)
+            NotificationCenter.default.addObserver(self, selector: #selector(windowKeyChanged),
+                                                   name: NSWindow.didBecomeKeyNotification, object: window)
+            NotificationCenter.default.addObserver(self, selector: #selector(windowKeyChanged),
+                                                   name: NSWindow.didResignKeyNotification, object: window)
             initGPU()
             if displayLink == nil { startDisplayLink() }
         } else {
@@ -77,6 +83,29 @@ class TerminalMetalView: NSView, CALayerDelegate {
         }
     }
 
+    // MARK: - Focus reporting (mode 1004)
+
+    override func becomeFirstResponder() -> Bool {
+        reportFocus(window?.isKeyWindow ?? false)
+        return super.becomeFirstResponder()
+    }
+
+    override func resignFirstResponder() -> Bool {
+        reportFocus(false)
+        return super.resignFirstResponder()
+    }
+
+    @objc private func windowKeyChanged(_ note: Notification) {
+        reportFocus(window?.isKeyWindow == true && window?.firstResponder === self)
+    }
+
+    /// The pane is focused while it is first responder in the key window.
+    /// The terminal only reports changes, so repeated calls are harmless.
+    private func reportFocus(_ focused: Bool) {
+        guard let session = session else { return }
+        term_session_set_focused(session, focused ? 1 : 0)
+    }
+
     private func initGPU() {
         guard let session = session else { return }
         gpuReady = false
@@ -113,7 +142,7 @@ class TerminalMetalView: NSView, CALayerDelegate {
             let h = UInt32(bounds.height * scale)
             metalLayer?.drawableSize = CGSize(width: CGFloat(w), height: CGFloat(h))
             term_session_render_gpu(session, w, h)
-        } else {
+        } else if term_session_sync_pending(session) == 0 {
             setNeedsDisplay(bounds)
         }
     }
@@ -243,6 +272,13 @@ class TerminalMetalView: NSView, CALayerDelegate {
         let delta = Int(-event.scrollingDeltaY.rounded())
         if delta == 0 { return }
 
+        // Mouse reporting or alternate scroll (mode 1007) takes the wheel
+        let button: Int32 = delta < 0 ? 4 : 5
+        if reportMouse(event, kind: 1, button: button) {
+            for _ in 1..<abs(delta) { _ = reportMouse(event, kind: 1, button: button) }
+            return
+        }
+
         let maxScroll = Int(term_session_scrollback_len(session))
         scrollOffset = max(0, min(scrollOffset + delta, maxScroll))
         setNeedsDisplay(bounds)
@@ -269,15 +305,25 @@ class TerminalMetalView: NSView, CALayerDelegate {
         return (row: max(0, row), col: max(0, col))
     }
 
+    /// Explicit OSC 8 hyperlink at the position, else a detected URL.
+    private func linkAt(_ session: OpaquePointer, _ pos: (row: Int, col: Int)) -> String? {
+        var start: UInt32 = 0, end: UInt32 = 0
+        var ptr = term_session_hyperlink_at(session, UInt32(pos.row), UInt32(pos.col), &start, &end)
+        if ptr == nil {
+            ptr = term_session_url_at(session, UInt32(pos.row), UInt32(pos.col))
+        }
+        guard let p = ptr else { return nil }
+        let url = String(cString: p)
+        term_string_free(p)
+        return url
+    }
+
     override func mouseDown(with event: NSEvent) {
         // Cmd+Click — open URL
         if event.modifierFlags.contains(.command) {
             if let session = self.session {
                 let pos = gridPosition(for: event)
-                let ptr = term_session_url_at(session, UInt32(pos.row), UInt32(pos.col))
-                if ptr != nil {
-                    let url = String(cString: ptr!)
-                    term_string_free(ptr!)
+                if let url = linkAt(session, pos) {
                     if let nsurl = URL(string: url) {
                         NSWorkspace.shared.open(nsurl)
                         return
@@ -286,6 +332,8 @@ class TerminalMetalView: NSView, CALayerDelegate {
             }
         }
 
+        if reportMouse(event, kind: 1, button: 1) { return }
+
         let pos = gridPosition(for: event)
         selectionStart = pos
         selectionEnd = pos
@@ -294,14 +342,13 @@ class TerminalMetalView: NSView, CALayerDelegate {
     }
 
     override func mouseMoved(with event: NSEvent) {
+        _ = reportMouse(event, kind: 3, button: 0)
         guard let session = self.session, event.modifierFlags.contains(.command) else {
             NSCursor.iBeam.set()
             return
         }
         let pos = gridPosition(for: event)
-        let ptr = term_session_url_at(session, UInt32(pos.row), UInt32(pos.col))
-        if ptr != nil {
-            term_string_free(ptr!)
+        if linkAt(session, pos) != nil {
             NSCursor.pointingHand.set()
         } else {
             NSCursor.iBeam.set()
@@ -319,15 +366,61 @@ class TerminalMetalView: NSView, CALayerDelegate {
     }
 
     override func mouseDragged(with event: NSEvent) {
-        guard isSelecting else { return }
+        guard isSelecting else {
+            _ = reportMouse(event, kind: 3, button: 1)
+            return
+        }
         selectionEnd = gridPosition(for: event)
         setNeedsDisplay(bounds)
     }
 
     override func mouseUp(with event: NSEvent) {
+        if !isSelecting { _ = reportMouse(event, kind: 2, button: 1) }
         isSelecting = false
     }
 
+    override func rightMouseDown(with event: NSEvent) {
+        if !reportMouse(event, kind: 1, button: 3) { super.rightMouseDown(with: event) }
+    }
+
+    override func rightMouseUp(with event: NSEvent) {
+        if !reportMouse(event, kind: 2, button: 3) { super.rightMouseUp(with: event) }
+    }
+
+    override func rightMouseDragged(with event: NSEvent) {
+        _ = reportMouse(event, kind: 3, button: 3)
+    }
+
+    override func otherMouseDown(with event: NSEvent) {
+        _ = reportMouse(event, kind: 1, button: 2)
+    }
+
+    override func otherMouseUp(with event: NSEvent) {
+        _ = reportMouse(event, kind: 2, button: 2)
+    }
+
+    override func otherMouseDragged(with event: NSEvent) {
+        _ = reportMouse(event, kind: 3, button: 2)
+    }
+
+    // MARK: - Mouse reporting
+
+    private var lastMouseCell: (row: Int, col: Int)?
+
+    /// Offer a mouse event to the application (modes 9/1000–1003, 1007).
+    /// Returns true when libterm took it; Shift-clicks always come back
+    /// for local selection. Motion is only sent when the cell changes.
+    private func reportMouse(_ event: NSEvent, kind: Int32, button: Int32) -> Bool {
+        guard let session = session else { return false }
+        let pos = gridPosition(for: event)
+        if kind == 3, let last = lastMouseCell, last == pos { return false }
+        lastMouseCell = pos
+        let loc = convert(event.locationInWindow, from: nil)
+        return term_session_send_mouse(session, kind, button, keyModifiers(event),
+                                       UInt32(pos.col), UInt32(pos.row),
+                                       UInt32(max(0, loc.x)), UInt32(max(0, loc.y))) == 1
+    }
+
     func copySelection() {
         guard let session = session,
               let start = selectionStart,
@@ -384,18 +477,13 @@ class TerminalMetalView: NSView, CALayerDelegate {
     private var imeMarkedRange: NSRange = NSRange(location: NSNotFound, length: 0)
 
     override func keyDown(with event: NSEvent) {
-        guard let session = session else { return }
+        guard session != nil else { return }
 
-        // Ctrl+key — handle directly, don't send to IME
-        if event.modifierFlags.contains(.control), let chars = event.charactersIgnoringModifiers {
-            if let scalar = chars.unicodeScalars.first,
-               scalar.value >= 0x61 && scalar.value <= 0x7a {
-                writePTY([UInt8(scalar.value - 0x60)])
-                return
-            } else if chars == "c" || chars == "C" {
-                writePTY([0x03])
-                return
-            }
+        // Ctrl+key — encoded by libterm, don't send to IME
+        if event.modifierFlags.contains(.control),
+           let scalar = event.charactersIgnoringModifiers?.lowercased().unicodeScalars.first {
+            sendKey(scalar.value, event)
+            return
         }
 
         // If IME is composing, let it handle everything (including Return)
@@ -435,25 +523,20 @@ class TerminalMetalView: NSView, CALayerDelegate {
             }
         }
 
-        // Special keys — handle directly (only when IME is NOT composing)
-        let appMode = term_session_cursor_keys_app(session) != 0
-        switch event.keyCode {
-        case 36:  writePTY([0x0d]); return
-        case 51:  writePTY([0x7f]); return
-        case 48:  writePTY([0x09]); return
-        case 53:
-            if searchActive { closeSearch(); return }
-            writePTY([0x1b]); return
-        case 126: writePTY(Array((appMode ? "\u{1b}OA" : "\u{1b}[A").utf8)); return
-        case 125: writePTY(Array((appMode ? "\u{1b}OB" : "\u{1b}[B").utf8)); return
-        case 124: writePTY(Array((appMode ? "\u{1b}OC" : "\u{1b}[C").utf8)); return
-        case 123: writePTY(Array((appMode ? "\u{1b}OD" : "\u{1b}[D").utf8)); return
-        case 115: writePTY(Array((appMode ? "\u{1b}OH" : "\u{1b}[H").utf8)); return
-        case 119: writePTY(Array((appMode ? "\u{1b}OF" : "\u{1b}[F").utf8)); return
-        case 116: writePTY(Array("\u{1b}[5~".utf8)); return
-        case 121: writePTY(Array("\u{1b}[6~".utf8)); return
-        case 117: writePTY(Array("\u{1b}[3~".utf8)); return
-        default: break
+        // Special keys — encoded by libterm (only when IME is NOT composing)
+        if event.keyCode == 53 && searchActive { closeSearch(); return }
+        if let key = Self.functionalKeys[event.keyCode] {
+            sendKey(key, event)
+            return
+        }
+
+        // Option as Alt: ESC + the key with Shift applied, not the composed
+        // character
+        if event.modifierFlags.contains(.option), let session = session,
+           term_session_option_as_alt(session) != 0,
+           let scalar = event.characters(byApplyingModifiers: [])?.unicodeScalars.first {
+            sendKey(scalar.value, event, text: event.charactersIgnoringModifiers)
+            return
         }
 
         // All other keys → go through IME via interpretKeyEvents
@@ -465,6 +548,36 @@ class TerminalMetalView: NSView, CALayerDelegate {
         // Intentionally empty
     }
 
+    /// Kitty key numbers, as term_session_send_key takes them, by keyCode.
+    private static let functionalKeys: [UInt16: UInt32] = [
+        36: 13, 48: 9, 51: 127, 53: 27, 76: 57414,           // Return Tab Delete Esc, keypad Enter
+        114: 57348, 117: 57349,                              // Help/Insert, Forward Delete
+        123: 57350, 124: 57351, 126: 57352, 125: 57353,      // arrows
+        116: 57354, 121: 57355, 115: 57356, 119: 57357,      // PgUp PgDn Home End
+        122: 57364, 120: 57365, 99: 57366, 118: 57367,       // F1–F4
+        96: 57368, 97: 57369, 98: 57370, 100: 57371,         // F5–F8
+        101: 57372, 109: 57373, 103: 57374, 111: 57375,      // F9–F12
+        105: 57376, 107: 57377, 113: 57378, 106: 57379,      // F13–F16
+        64: 57380, 79: 57381, 80: 57382, 90: 57383,          // F17–F20
+    ]
+
+    private func sendKey(_ key: UInt32, _ event: NSEvent, text: String? = nil) {
+        guard let session = session else { return }
+        _ = term_session_send_key(session, key, keyModifiers(event), event.isARepeat ? 2 : 1,
+                                  text ?? event.characters ?? "")
+    }
+
+    /// Kitty modifier bits for term_session_send_key / term_session_send_mouse.
+    private func keyModifiers(_ event: NSEvent) -> UInt32 {
+        let flags = event.modifierFlags
+        var mods: UInt32 = 0
+        if flags.contains(.shift) { mods |= 1 }
+        if flags.contains(.option) { mods |= 2 }
+        if flags.contains(.control) { mods |= 4 }
+        if flags.contains(.capsLock) { mods |= 64 }
+        return mods
+    }
+
     private func writePTY(_ bytes: [UInt8]) {
         guard let session = session, !bytes.isEmpty else { return }
         bytes.withUnsafeBufferPointer { buf in
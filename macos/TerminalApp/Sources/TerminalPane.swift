@@ -6,6 +6,15 @@ class TerminalPane {
     let view: TerminalMetalView
     var session: OpaquePointer?
     var ptySource: DispatchSourceRead?
+    /// macOS has no primary selection, so OSC 52 `p` is kept per pane
+    private var primarySelection = ""
+    /// Window title (OSC 0/2), working directory (OSC 7) and last exit
+    /// code (OSC 133;D), kept from terminal events
+    private(set) var title = ""
+    private(set) var workingDirectory = ""
+    private(set) var lastExitCode: Int32 = 0
+    /// Called when the title, directory or exit code changes
+    var onTitleChange: ((TerminalPane) -> Void)?
 
     init(id: Int, frame: NSRect) {
         self.id = id
@@ -49,6 +58,7 @@ class TerminalPane {
         guard let session = session else { return }
         let bytesRead = term_session_read_pty(session)
         if bytesRead > 0 {
+            handleEvents(session)
             view.scrollToBottom()
             view.setNeedsDisplay(view.bounds)
         } else if bytesRead < 0 {
@@ -57,6 +67,75 @@ class TerminalPane {
         }
     }
 
+    /// Drain terminal events: title, directory and exit code for the view
+    /// controller, the bell, and OSC 52 clipboard.
+    private func handleEvents(_ session: OpaquePointer) {
+        var value: Int32 = 0
+        var text: UnsafeMutablePointer<CChar>?
+        var detail: UnsafeMutablePointer<CChar>?
+        var titleChanged = false
+        var kind = term_session_next_event(session, &value, &text, &detail)
+        while kind != 0 {
+            let selection = detail.map { String(cString: $0) } ?? "s0"
+            switch kind {
+            case 1:
+                title = text.map { String(cString: $0) } ?? ""
+                titleChanged = true
+            case 5:
+                workingDirectory = text.map { String(cString: $0) } ?? ""
+                titleChanged = true
+            case 7:
+                lastExitCode = value
+                titleChanged = true
+            case 2:
+                NSSound.beep()
+            case 3:
+                storeClipboard(session, selection: selection, text: text.map { String(cString: $0) } ?? "")
+            case 4:
+                loadClipboard(session, selection: selection)
+            default:
+                break
+            }
+            if let text = text { term_string_free(text) }
+            if let detail = detail { term_string_free(detail) }
+            kind = term_session_next_event(session, &value, &text, &detail)
+        }
+        if titleChanged { onTitleChange?(self) }
+    }
+
+    /// OSC 52 `ask` policy: confirm with the user.
+    private func confirmClipboard(_ message: String) -> Bool {
+        let alert = NSAlert()
+        alert.messageText = message
+        alert.addButton(withTitle: "Allow")
+        alert.addButton(withTitle: "Deny")
+        return alert.runModal() == .alertFirstButtonReturn
+    }
+
+    private func storeClipboard(_ session: OpaquePointer, selection: String, text: String) {
+        let access = term_session_clipboard_access(session, 1)
+        guard access == 2 || (access == 1 && confirmClipboard("Allow the program to change the clipboard?")) else { return }
+        if selection.contains("p") { primarySelection = text }
+        if selection.contains("c") || selection.contains("s") {
+            NSPasteboard.general.clearContents()
+            if !text.isEmpty { NSPasteboard.general.setString(text, forType: .string) }
+        }
+    }
+
+    private func loadClipboard(_ session: OpaquePointer, selection: String) {
+        let access = term_session_clipboard_access(session, 0)
+        guard access == 2 || (access == 1 && confirmClipboard("Allow the program to read the clipboard?")) else { return }
+        let text: String
+        if selection.contains("c") || selection.contains("s") {
+            text = NSPasteboard.general.string(forType: .string) ?? ""
+        } else if selection.contains("p") {
+            text = primarySelection
+        } else {
+            text = ""
+        }
+        term_session_clipboard_reply(session, selection, text)
+    }
+
     func resize(cols: UInt32, rows: UInt32, width: UInt32, height: UInt32) {
         guard let session = session else { return }
         term_session_resize(session, cols, rows, width, height)
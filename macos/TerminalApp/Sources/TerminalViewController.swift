@@ -36,8 +36,8 @@ class TerminalViewController: NSViewController {
         splitView.addSubview(pane.view)
         activePaneIndex = panes.count - 1
 
-        // Start polling for title updates on active pane
-        setupTitlePolling(pane)
+        pane.onTitleChange = { [weak self] pane in self?.updateTitle(pane) }
+        setupConfigPolling(pane)
     }
 
     func splitVertical() {
@@ -65,6 +65,7 @@ class TerminalViewController: NSViewController {
         activePaneIndex = min(activePaneIndex, panes.count - 1)
         splitView.adjustSubviews()
         view.window?.makeFirstResponder(panes[activePaneIndex].view)
+        updateTitle(panes[activePaneIndex])
         for pane in panes { updatePaneSize(pane) }
     }
 
@@ -73,6 +74,7 @@ class TerminalViewController: NSViewController {
         activePaneIndex = (activePaneIndex + 1) % panes.count
         view.window?.makeFirstResponder(panes[activePaneIndex].view)
         highlightActivePane()
+        updateTitle(panes[activePaneIndex])
     }
 
     private func updatePaneSize(_ pane: TerminalPane) {
@@ -98,32 +100,19 @@ class TerminalViewController: NSViewController {
 
     // MARK: - Title
 
-    private func setupTitlePolling(_ pane: TerminalPane) {
-        // Use a timer to poll title + config
+    /// Title the active pane's window from its terminal events: the working
+    /// directory's name, else the OSC title, plus a failed exit code.
+    private func updateTitle(_ pane: TerminalPane) {
+        guard panes.indices.contains(activePaneIndex), panes[activePaneIndex] === pane else { return }
+        var title = (pane.workingDirectory as NSString).lastPathComponent
+        if title.isEmpty { title = pane.title }
+        if pane.lastExitCode > 0 { title += " ✘ \(pane.lastExitCode)" }
+        view.window?.title = title.isEmpty ? "Terminal" : title
+    }
+
+    private func setupConfigPolling(_ pane: TerminalPane) {
         Timer.scheduledTimer(withTimeInterval: 0.5, repeats: true) { [weak self, weak pane] _ in
             guard let self = self, let pane = pane, let session = pane.session else { return }
-
-            // Title
-            var title = ""
-            let dirPtr = term_session_working_dir(session)
-            if dirPtr != nil {
-                let dir = String(cString: dirPtr!)
-                term_string_free(dirPtr!)
-                if !dir.isEmpty { title = (dir as NSString).lastPathComponent }
-            }
-            if title.isEmpty {
-                let titlePtr = term_session_title(session)
-                if titlePtr != nil {
-                    let t = String(cString: titlePtr!)
-                    term_string_free(titlePtr!)
-                    if !t.isEmpty { title = t }
-                }
-            }
-            let exitCode = term_session_last_exit_code(session)
-            if exitCode > 0 { title += " ✘ \(exitCode)" }
-            if !title.isEmpty { self.view.window?.title = title }
-
-            // Config hot-reload
             let gen = term_session_poll_config(session)
             if gen > 0 && gen != self.lastConfigGen {
                 self.lastConfigGen = gen
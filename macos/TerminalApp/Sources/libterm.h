@@ -20,6 +20,10 @@ int term_session_spawn_shell(TermSession* session, const char* shell);
 // I/O
 int term_session_read_pty(TermSession* session);
 int term_session_write_pty(TermSession* session, const uint8_t* data, uint32_t len);
+int term_session_send_key(TermSession* session, uint32_t key, uint32_t mods, int kind,
+                          const char* text);
+int term_session_send_mouse(TermSession* session, int kind, int button, uint32_t mods,
+                            uint32_t col, uint32_t row, uint32_t x, uint32_t y);
 int term_session_pty_fd(const TermSession* session);
 
 // Resize
@@ -30,7 +34,8 @@ void term_session_resize(TermSession* session, uint32_t cols, uint32_t rows,
 uint32_t term_session_cell_char(const TermSession* session, uint32_t row, uint32_t col);
 uint32_t term_session_cell_fg(const TermSession* session, uint32_t row, uint32_t col);
 uint32_t term_session_cell_bg(const TermSession* session, uint32_t row, uint32_t col);
-uint8_t  term_session_cell_attr(const TermSession* session, uint32_t row, uint32_t col);
+uint16_t term_session_cell_attr(const TermSession* session, uint32_t row, uint32_t col);
+uint32_t term_session_cell_underline_color(const TermSession* session, uint32_t row, uint32_t col);
 
 // Cursor & grid
 void term_session_cursor_pos(const TermSession* session, uint32_t* out_row, uint32_t* out_col);
@@ -48,6 +53,17 @@ void term_string_free(char* s);
 
 // Terminal state queries
 int term_session_cursor_keys_app(const TermSession* session);
+int term_session_option_as_alt(const TermSession* session);
+int term_session_newline_mode(const TermSession* session);
+void term_session_set_focused(TermSession* session, int focused);
+int term_session_sync_pending(const TermSession* session);
+
+// Events: returns the kind (0 = none), see term_session_next_event in ffi.rs
+int term_session_next_event(TermSession* session, int32_t* out_value, char** out_text,
+                            char** out_detail);
+// OSC 52: policy 0 off, 1 ask, 2 allow; reply answers a load request (event 4)
+int term_session_clipboard_access(const TermSession* session, int write);
+void term_session_clipboard_reply(TermSession* session, const char* selection, const char* text);
 int term_session_cursor_visible(const TermSession* session);
 int term_session_bracketed_paste(const TermSession* session);
 
@@ -79,6 +95,8 @@ char* term_session_extract_text(const TermSession* session,
 
 // URL detection
 char* term_session_url_at(const TermSession* session, uint32_t row, uint32_t col);
+char* term_session_hyperlink_at(const TermSession* session, uint32_t row, uint32_t col,
+    uint32_t* out_col_start, uint32_t* out_col_end);
 
 // Search
 uint32_t term_session_search(TermSession* session, const char* pattern, int use_regex);
@@ -1,7 +1,7 @@
 /// Performance benchmarks for terminal core operations.
 /// Run with: cargo test --release bench_ -- --nocapture
 
-use crate::core::{Terminal, VtParser};
+use crate::core::{Params, Perform, Terminal, VtParser};
 use std::time::Instant;
 
 pub struct BenchResult {
@@ -46,6 +46,57 @@ pub fn bench_parser_throughput() -> BenchResult {
     }
 }
 
+/// Performer that discards everything, to measure the state machine alone.
+struct NullPerform;
+
+impl Perform for NullPerform {
+    fn print(&mut self, _ch: char) {}
+    fn execute(&mut self, _byte: u8) {}
+    fn csi_dispatch(&mut self, _final_byte: u8, _params: &Params, _intermediates: &[u8]) {}
+    fn esc_dispatch(&mut self, _final_byte: u8, _intermediates: &[u8]) {}
+    fn osc_dispatch(&mut self, _data: &[u8], _bell_terminated: bool) {}
+}
+
+/// Benchmark the bare parser through `Perform` (no allocation per action).
+pub fn bench_parser_perform() -> BenchResult {
+    let data = sgr_heavy_input();
+    let iterations = if cfg!(debug_assertions) { 5 } else { 100 };
+    let mut parser = VtParser::new();
+    let start = Instant::now();
+    for _ in 0..iterations {
+        parser.parse(&mut NullPerform, &data);
+    }
+    throughput_result("parser_perform", iterations, data.len(), start.elapsed())
+}
+
+/// Benchmark the bare parser through the `Vec<Action>` API, for comparison.
+pub fn bench_parser_actions() -> BenchResult {
+    let data = sgr_heavy_input();
+    let iterations = if cfg!(debug_assertions) { 5 } else { 100 };
+    let mut parser = VtParser::new();
+    let start = Instant::now();
+    for _ in 0..iterations {
+        std::hint::black_box(parser.feed(&data));
+    }
+    throughput_result("parser_actions", iterations, data.len(), start.elapsed())
+}
+
+fn sgr_heavy_input() -> Vec<u8> {
+    (0..10_000)
+        .flat_map(|_| b"Hello, World! \x1b[31mRed\x1b[0m \x1b[1;32mBoldGreen\x1b[0m\r\n".iter().copied())
+        .collect()
+}
+
+fn throughput_result(name: &'static str, iterations: usize, bytes: usize, elapsed: std::time::Duration) -> BenchResult {
+    BenchResult {
+        name,
+        iterations,
+        total_ms: elapsed.as_secs_f64() * 1000.0,
+        per_iter_us: elapsed.as_secs_f64() * 1_000_000.0 / iterations as f64,
+        throughput_mb_s: Some((bytes * iterations) as f64 / elapsed.as_secs_f64() / 1_048_576.0),
+    }
+}
+
 /// Benchmark grid scrolling.
 pub fn bench_grid_scroll() -> BenchResult {
     let mut terminal = Terminal::new(80, 24);
@@ -109,6 +160,8 @@ pub fn run_all() -> Vec<BenchResult> {
     vec![
         bench_startup(),
         bench_parser_throughput(),
+        bench_parser_perform(),
+        bench_parser_actions(),
         bench_grid_scroll(),
         bench_resize(),
     ]
@@ -126,7 +179,7 @@ mod tests {
             assert!(r.total_ms > 0.0);
             assert!(r.per_iter_us > 0.0);
         }
-        assert_eq!(results.len(), 4);
+        assert_eq!(results.len(), 6);
     }
 
     #[test]
@@ -148,4 +201,14 @@ mod tests {
             assert!(tp > 10.0, "parser too slow: {:.1} MB/s", tp);
         }
     }
+
+    #[test]
+    fn bench_perform_beats_actions() {
+        let perform = bench_parser_perform();
+        let actions = bench_parser_actions();
+        println!("{}\n{}", perform, actions);
+        #[cfg(not(debug_assertions))]
+        assert!(perform.throughput_mb_s > actions.throughput_mb_s,
+                "Perform path slower than Vec<Action>: {} vs {}", perform, actions);
+    }
 }
@@ -1,6 +1,8 @@
 /// Clipboard integration: copy/paste + OSC 52 support.
 
+use serde::Deserialize;
 use std::process::Command;
+use std::sync::Mutex;
 
 /// Copy text to system clipboard.
 pub fn copy(text: &str) -> bool {
@@ -36,19 +38,131 @@ pub fn bracketed_paste_wrap(text: &str) -> Vec<u8> {
     out
 }
 
-/// Decode OSC 52 clipboard set: `52;c;BASE64_DATA`
-pub fn decode_osc52_set(data: &str) -> Option<String> {
-    // Format: "52;c;BASE64" or "52;;BASE64"
-    let rest = data.strip_prefix("52;")?;
-    let (_target, b64) = rest.split_once(';')?;
-    if b64 == "?" {
-        return None; // query, not set
+/// Whether applications may access the clipboard with OSC 52.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipboardAccess {
+    /// Requests are dropped
+    Off,
+    /// The UI asks the user before each access
+    Ask,
+    Allow,
+}
+
+impl ClipboardAccess {
+    /// The policy for a front end that can't prompt: `Ask` becomes `Off`.
+    pub fn without_prompt(self) -> Self {
+        match self {
+            ClipboardAccess::Ask => ClipboardAccess::Off,
+            access => access,
+        }
+    }
+}
+
+/// Default cap on OSC 52 clipboard text, in bytes. Its base64 form stays
+/// under the parser's OSC length limit.
+pub const OSC52_MAX_BYTES: usize = 512 * 1024;
+
+/// OSC 52 request, after the selection targets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Osc52 {
+    /// Store the decoded text
+    Set(String),
+    /// `?`: report the selection contents
+    Query,
+    /// Empty data or data that is not base64 clears the selection
+    Clear,
+}
+
+/// A system selection named by OSC 52 targets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    Clipboard,
+    Primary,
+}
+
+/// Parse OSC 52 `52;Pc;Pd` into its targets and request.
+pub fn parse_osc52(data: &str) -> Option<(String, Osc52)> {
+    let (targets, payload) = data.strip_prefix("52;")?.split_once(';')?;
+    let targets = osc52_targets(targets);
+    let request = match payload {
+        "?" => Osc52::Query,
+        "" => Osc52::Clear,
+        b64 => match base64_decode_bytes(b64) {
+            Some(bytes) => Osc52::Set(String::from_utf8_lossy(&bytes).into_owned()),
+            None => Osc52::Clear,
+        },
+    };
+    Some((targets, request))
+}
+
+/// OSC 52 targets with unknown letters dropped; none means `s0`, as in
+/// xterm.
+pub fn osc52_targets(targets: &str) -> String {
+    let targets: String = targets.chars().filter(|c| "cpqs01234567".contains(*c)).collect();
+    if targets.is_empty() { "s0".into() } else { targets }
+}
+
+/// System selections for OSC 52 targets: `c` and `s` (xterm's configured
+/// selection) are the clipboard, `p` the primary selection. Secondary (`q`)
+/// and cut buffers (`0`–`7`) have no counterpart.
+pub fn osc52_selections(targets: &str) -> Vec<Selection> {
+    let mut out = Vec::new();
+    for c in targets.chars() {
+        let sel = match c {
+            'c' | 's' => Selection::Clipboard,
+            'p' => Selection::Primary,
+            _ => continue,
+        };
+        if !out.contains(&sel) {
+            out.push(sel);
+        }
+    }
+    out
+}
+
+/// macOS has no primary selection, so it lives in the process.
+static PRIMARY: Mutex<String> = Mutex::new(String::new());
+
+/// Copy text to a selection.
+pub fn copy_to(selection: Selection, text: &str) -> bool {
+    match selection {
+        Selection::Clipboard => copy(text),
+        Selection::Primary => {
+            *PRIMARY.lock().unwrap_or_else(|e| e.into_inner()) = text.to_string();
+            true
+        }
     }
-    // Decode base64
-    base64_decode(b64)
 }
 
-fn base64_decode(input: &str) -> Option<String> {
+/// Read text from a selection.
+pub fn paste_from(selection: Selection) -> Option<String> {
+    match selection {
+        Selection::Clipboard => paste(),
+        Selection::Primary => Some(PRIMARY.lock().unwrap_or_else(|e| e.into_inner()).clone()),
+    }
+}
+
+/// Encode bytes as base64 with padding.
+pub fn base64_encode(input: &[u8]) -> String {
+    let table = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(table[(n >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decode base64 to raw bytes (used for binary payloads such as Kitty graphics).
+pub fn base64_decode_bytes(input: &str) -> Option<Vec<u8>> {
     // Minimal base64 decoder — no external dep
     let table = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
     let mut buf = Vec::new();
@@ -65,7 +179,7 @@ fn base64_decode(input: &str) -> Option<String> {
             acc &= (1 << bits) - 1;
         }
     }
-    String::from_utf8(buf).ok()
+    Some(buf)
 }
 
 #[cfg(test)]
@@ -86,26 +200,66 @@ mod tests {
 
     #[test]
     fn test_base64_decode() {
-        assert_eq!(base64_decode("aGVsbG8="), Some("hello".into()));
-        assert_eq!(base64_decode("d29ybGQ="), Some("world".into()));
-        assert_eq!(base64_decode("YQ=="), Some("a".into()));
+        assert_eq!(base64_decode_bytes("aGVsbG8="), Some(b"hello".to_vec()));
+        assert_eq!(base64_decode_bytes("d29ybGQ="), Some(b"world".to_vec()));
+        assert_eq!(base64_decode_bytes("YQ=="), Some(b"a".to_vec()));
+    }
+
+    #[test]
+    fn test_base64_encode() {
+        assert_eq!(base64_encode(b"hello"), "aGVsbG8=");
+        assert_eq!(base64_encode(b"a"), "YQ==");
+        assert_eq!(base64_encode(b"abc"), "YWJj");
+        assert_eq!(base64_encode(b""), "");
+        let text = "héllo wörld";
+        assert_eq!(base64_decode_bytes(&base64_encode(text.as_bytes())), Some(text.as_bytes().to_vec()));
     }
 
     #[test]
     fn test_osc52_decode() {
-        // "52;c;aGVsbG8=" → "hello"
-        assert_eq!(decode_osc52_set("52;c;aGVsbG8="), Some("hello".into()));
-        assert_eq!(decode_osc52_set("52;;d29ybGQ="), Some("world".into()));
+        assert_eq!(parse_osc52("52;c;aGVsbG8="), Some(("c".into(), Osc52::Set("hello".into()))));
+        assert_eq!(parse_osc52("52;;d29ybGQ="), Some(("s0".into(), Osc52::Set("world".into()))));
+        assert_eq!(parse_osc52("52;pc;YQ=="), Some(("pc".into(), Osc52::Set("a".into()))));
     }
 
     #[test]
     fn test_osc52_query() {
-        assert_eq!(decode_osc52_set("52;c;?"), None);
+        assert_eq!(parse_osc52("52;c;?"), Some(("c".into(), Osc52::Query)));
+    }
+
+    #[test]
+    fn test_osc52_clear() {
+        assert_eq!(parse_osc52("52;c;"), Some(("c".into(), Osc52::Clear)));
+        assert_eq!(parse_osc52("52;p;!"), Some(("p".into(), Osc52::Clear)));
+        // Unknown targets are dropped
+        assert_eq!(parse_osc52("52;x\x1b;!"), Some(("s0".into(), Osc52::Clear)));
     }
 
     #[test]
     fn test_osc52_invalid() {
-        assert_eq!(decode_osc52_set("not_osc52"), None);
+        assert_eq!(parse_osc52("not_osc52"), None);
+        assert_eq!(parse_osc52("52;c"), None);
+    }
+
+    #[test]
+    fn test_osc52_selections() {
+        assert_eq!(osc52_selections("c"), [Selection::Clipboard]);
+        assert_eq!(osc52_selections("s0"), [Selection::Clipboard]);
+        assert_eq!(osc52_selections("pcs"), [Selection::Primary, Selection::Clipboard]);
+        assert_eq!(osc52_selections("q7"), []);
+    }
+
+    #[test]
+    fn test_access_without_prompt() {
+        assert_eq!(ClipboardAccess::Ask.without_prompt(), ClipboardAccess::Off);
+        assert_eq!(ClipboardAccess::Allow.without_prompt(), ClipboardAccess::Allow);
+        assert_eq!(ClipboardAccess::Off.without_prompt(), ClipboardAccess::Off);
+    }
+
+    #[test]
+    fn test_primary_selection() {
+        assert!(copy_to(Selection::Primary, "primary text"));
+        assert_eq!(paste_from(Selection::Primary), Some("primary text".into()));
     }
 
     #[test]
@@ -1,6 +1,7 @@
 /// Configuration system: TOML-based with sensible defaults.
 /// Config file: `~/.config/term/config.toml`
 
+use crate::clipboard::{ClipboardAccess, OSC52_MAX_BYTES};
 use serde::Deserialize;
 use std::path::PathBuf;
 
@@ -11,6 +12,7 @@ pub struct Config {
     pub window: WindowConfig,
     pub colors: ColorConfig,
     pub shell: ShellConfig,
+    pub terminal: TerminalConfig,
     pub scrollback: usize,
 }
 
@@ -47,6 +49,27 @@ pub struct ShellConfig {
     pub args: Vec<String>,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TerminalConfig {
+    /// Recognize 8-bit C1 controls (0x9B CSI, 0x9D OSC, ...). Hosts can
+    /// also toggle this with S8C1T / S7C1T.
+    pub c1_controls: bool,
+    /// Columns for East Asian Ambiguous characters: 1 or 2
+    pub ambiguous_width: u8,
+    /// OSC 52 clipboard writes from applications: "off", "ask" or "allow".
+    /// "ask" needs a front end that can prompt (the macOS app); the winit
+    /// app treats it as "off".
+    pub clipboard_write: ClipboardAccess,
+    /// OSC 52 clipboard reads, answered only when allowed or confirmed
+    pub clipboard_read: ClipboardAccess,
+    /// Largest OSC 52 text stored or reported, in bytes
+    pub clipboard_max_bytes: usize,
+    /// macOS: Option acts as Alt (ESC prefix) instead of composing
+    /// characters such as "∫"
+    pub option_as_alt: bool,
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -54,6 +77,7 @@ impl Default for Config {
             window: WindowConfig::default(),
             colors: ColorConfig::default(),
             shell: ShellConfig::default(),
+            terminal: TerminalConfig::default(),
             scrollback: 10_000,
         }
     }
@@ -100,6 +124,19 @@ impl Default for ShellConfig {
     }
 }
 
+impl Default for TerminalConfig {
+    fn default() -> Self {
+        Self {
+            c1_controls: false,
+            ambiguous_width: 1,
+            clipboard_write: ClipboardAccess::Allow,
+            clipboard_read: ClipboardAccess::Ask,
+            clipboard_max_bytes: OSC52_MAX_BYTES,
+            option_as_alt: false,
+        }
+    }
+}
+
 impl Config {
     /// Config file path: `~/.config/term/config.toml`
     pub fn path() -> PathBuf {
@@ -138,6 +175,24 @@ mod tests {
         assert_eq!(cfg.scrollback, 10_000);
         assert_eq!(cfg.window.opacity, 1.0);
         assert_eq!(cfg.colors.background, "#000000");
+        assert!(!cfg.terminal.c1_controls);
+        assert_eq!(cfg.terminal.ambiguous_width, 1);
+        assert_eq!(cfg.terminal.clipboard_write, ClipboardAccess::Allow);
+        assert_eq!(cfg.terminal.clipboard_read, ClipboardAccess::Ask);
+        assert!(!cfg.terminal.option_as_alt);
+    }
+
+    #[test]
+    fn test_parse_clipboard_policy() {
+        let cfg = Config::from_str(r#"
+            [terminal]
+            clipboard_write = "ask"
+            clipboard_read = "off"
+            clipboard_max_bytes = 4096
+        "#);
+        assert_eq!(cfg.terminal.clipboard_write, ClipboardAccess::Ask);
+        assert_eq!(cfg.terminal.clipboard_read, ClipboardAccess::Off);
+        assert_eq!(cfg.terminal.clipboard_max_bytes, 4096);
     }
 
     #[test]
@@ -188,6 +243,10 @@ mod tests {
             [shell]
             program = "/bin/bash"
             args = ["-l"]
+
+            [terminal]
+            c1_controls = true
+            ambiguous_width = 2
         "##);
         assert_eq!(cfg.font.family, "Fira Code");
         assert_eq!(cfg.window.opacity, 0.95);
@@ -195,6 +254,8 @@ mod tests {
         assert_eq!(cfg.colors.theme, "dracula");
         assert_eq!(cfg.shell.program, "/bin/bash");
         assert_eq!(cfg.scrollback, 20000);
+        assert!(cfg.terminal.c1_controls);
+        assert_eq!(cfg.terminal.ambiguous_width, 2);
     }
 
     #[test]
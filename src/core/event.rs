@@ -0,0 +1,48 @@
+//! Events the terminal raises while processing output, for the UI and
+//! plugins to react to instead of polling terminal state.
+
+use std::time::Duration;
+
+use super::handler::WindowOp;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TerminalEvent {
+    /// OSC 0/2, a title popped from the stack, or RIS clearing it
+    TitleChanged(String),
+    /// BEL
+    Bell,
+    /// OSC 52 set: `selection` holds the target letters (`c`, `p`, `s`...);
+    /// empty text clears the selection
+    ClipboardStore { selection: String, text: String },
+    /// OSC 52 query for the named selection, answered with
+    /// `Terminal::answer_clipboard_request`
+    ClipboardLoadRequest { selection: String },
+    /// OSC 7 changed the working directory (the path, without host)
+    CwdChanged(String),
+    /// OSC 133;C — the shell started running a command
+    CommandStarted,
+    /// OSC 133;D
+    CommandFinished { exit_code: Option<i32>, duration: Option<Duration> },
+    /// OSC 9 (`9;body`) or OSC 777 (`777;notify;title;body`)
+    Notification { title: String, body: String },
+    /// OSC 4/10/11/12 color query, already answered from the palette;
+    /// `index` is the OSC 4 palette index
+    ColorQuery { osc: u16, index: Option<u8> },
+    /// A mode the terminal knows (as listed for DECRQM) was set or reset
+    ModeChanged { mode: u16, private: bool, enabled: bool },
+    /// XTWINOPS request allowed by `Terminal::window_op_policy`
+    WindowOp(WindowOp),
+}
+
+impl TerminalEvent {
+    /// Bytes of text the event carries, for bounding the queue.
+    pub fn payload_len(&self) -> usize {
+        match self {
+            TerminalEvent::TitleChanged(s) | TerminalEvent::CwdChanged(s) => s.len(),
+            TerminalEvent::ClipboardStore { selection, text } => selection.len() + text.len(),
+            TerminalEvent::ClipboardLoadRequest { selection } => selection.len(),
+            TerminalEvent::Notification { title, body } => title.len() + body.len(),
+            _ => 0,
+        }
+    }
+}
@@ -1,15 +1,62 @@
 use bitflags::bitflags;
+use std::collections::VecDeque;
 
 bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-    pub struct CellAttr: u8 {
-        const BOLD       = 0b0000_0001;
-        const ITALIC     = 0b0000_0010;
-        const UNDERLINE  = 0b0000_0100;
-        const INVERSE    = 0b0000_1000;
-        const STRIKETHROUGH = 0b0001_0000;
-        const DIM        = 0b0010_0000;
-        const HIDDEN     = 0b0100_0000;
+    pub struct CellAttr: u16 {
+        const BOLD       = 0b0000_0000_0000_0001;
+        const ITALIC     = 0b0000_0000_0000_0010;
+        const UNDERLINE  = 0b0000_0000_0000_0100;
+        const INVERSE    = 0b0000_0000_0000_1000;
+        const STRIKETHROUGH = 0b0000_0000_0001_0000;
+        const DIM        = 0b0000_0000_0010_0000;
+        const HIDDEN     = 0b0000_0000_0100_0000;
+        const DOUBLE_UNDERLINE = 0b0000_0000_1000_0000;
+        const CURLY_UNDERLINE  = 0b0000_0001_0000_0000;
+        const DOTTED_UNDERLINE = 0b0000_0010_0000_0000;
+        const DASHED_UNDERLINE = 0b0000_0100_0000_0000;
+        /// All underline styles; at most one is set at a time
+        const ANY_UNDERLINE = Self::UNDERLINE.bits() | Self::DOUBLE_UNDERLINE.bits()
+            | Self::CURLY_UNDERLINE.bits() | Self::DOTTED_UNDERLINE.bits()
+            | Self::DASHED_UNDERLINE.bits();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnderlineStyle {
+    Single,
+    Double,
+    Curly,
+    Dotted,
+    Dashed,
+}
+
+impl UnderlineStyle {
+    fn attr(self) -> CellAttr {
+        match self {
+            Self::Single => CellAttr::UNDERLINE,
+            Self::Double => CellAttr::DOUBLE_UNDERLINE,
+            Self::Curly => CellAttr::CURLY_UNDERLINE,
+            Self::Dotted => CellAttr::DOTTED_UNDERLINE,
+            Self::Dashed => CellAttr::DASHED_UNDERLINE,
+        }
+    }
+}
+
+impl CellAttr {
+    pub fn underline(self) -> Option<UnderlineStyle> {
+        [UnderlineStyle::Single, UnderlineStyle::Double, UnderlineStyle::Curly,
+         UnderlineStyle::Dotted, UnderlineStyle::Dashed]
+            .into_iter()
+            .find(|style| self.contains(style.attr()))
+    }
+
+    /// Replace the underline style; `None` removes the underline.
+    pub fn set_underline(&mut self, style: Option<UnderlineStyle>) {
+        self.remove(Self::ANY_UNDERLINE);
+        if let Some(style) = style {
+            self.insert(style.attr());
+        }
     }
 }
 
@@ -25,12 +72,31 @@ impl Color {
     pub const DEFAULT_BG: Self = Self { r: 0, g: 0, b: 0 };
 }
 
+/// Cell color as set by SGR. `Default` and `Indexed` resolve against the
+/// terminal's `Palette` when drawn, so theme changes recolor old content.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CellColor {
+    #[default]
+    Default,
+    Indexed(u8),
+    Rgb(Color),
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Cell {
     pub ch: char,
     pub attr: CellAttr,
-    pub fg: Color,
-    pub bg: Color,
+    pub fg: CellColor,
+    pub bg: CellColor,
+    /// Underline color (SGR 58); `Default` draws the underline in `fg`
+    pub underline_color: CellColor,
+    /// Codepoints following `ch` in the same grapheme cluster (combining
+    /// marks, variation selectors, ZWJ sequences), as an id into the
+    /// owning grid's grapheme table. 0 = none.
+    pub extra: u32,
+    /// OSC 8 hyperlink, as an id into the terminal's hyperlink table.
+    /// 0 = none.
+    pub link: u16,
 }
 
 impl Default for Cell {
@@ -38,22 +104,85 @@ impl Default for Cell {
         Self {
             ch: ' ',
             attr: CellAttr::empty(),
-            fg: Color::DEFAULT_FG,
-            bg: Color::DEFAULT_BG,
+            fg: CellColor::Default,
+            bg: CellColor::Default,
+            underline_color: CellColor::Default,
+            extra: 0,
+            link: 0,
         }
     }
 }
 
+/// Side table for the extra codepoints of grapheme clusters. Entries are
+/// never shared-mutated: appending to a cluster allocates a new entry and
+/// unreferenced entries are reclaimed by `Grid::collect_graphemes`.
+#[derive(Debug, Default)]
+struct GraphemeTable {
+    /// Slot `id - 1` holds the codepoints for id `id`
+    slots: Vec<Vec<char>>,
+    free: Vec<u32>,
+    /// Live entries that trigger the next collection
+    gc_threshold: usize,
+}
+
+impl GraphemeTable {
+    const MIN_GC_THRESHOLD: usize = 256;
+
+    fn get(&self, id: u32) -> &[char] {
+        match id {
+            0 => &[],
+            id => self.slots.get(id as usize - 1).map_or(&[], Vec::as_slice),
+        }
+    }
+
+    fn insert(&mut self, chars: Vec<char>) -> u32 {
+        match self.free.pop() {
+            Some(id) => {
+                self.slots[id as usize - 1] = chars;
+                id
+            }
+            None => {
+                self.slots.push(chars);
+                self.slots.len() as u32
+            }
+        }
+    }
+
+    fn live(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+}
+
+/// Row text plus the byte offset at which each column starts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowText {
+    pub text: String,
+    offsets: Vec<usize>,
+}
+
+impl RowText {
+    /// Column containing byte `offset` of `text`.
+    pub fn col_at(&self, offset: usize) -> usize {
+        self.offsets.partition_point(|&o| o <= offset).saturating_sub(1)
+    }
+
+    /// Exclusive end column for a match ending at byte `offset`.
+    pub fn col_end(&self, offset: usize) -> usize {
+        self.offsets.partition_point(|&o| o < offset)
+    }
+}
+
 pub struct Grid {
     cols: usize,
     rows: usize,
     cells: Vec<Cell>,
     /// Scrollback buffer (ring buffer of rows)
-    scrollback: Vec<Vec<Cell>>,
+    scrollback: VecDeque<Vec<Cell>>,
     scrollback_max: usize,
     /// Cursor position
     pub cursor_row: usize,
     pub cursor_col: usize,
+    graphemes: GraphemeTable,
 }
 
 impl Grid {
@@ -62,17 +191,18 @@ impl Grid {
             cols,
             rows,
             cells: vec![Cell::default(); cols * rows],
-            scrollback: Vec::new(),
+            scrollback: VecDeque::new(),
             scrollback_max: 10_000,
             cursor_row: 0,
             cursor_col: 0,
+            graphemes: GraphemeTable::default(),
         }
     }
 
     pub fn cols(&self) -> usize { self.cols }
     pub fn rows(&self) -> usize { self.rows }
 
-    pub fn scrollback(&self) -> &[Vec<Cell>] { &self.scrollback }
+    pub fn scrollback(&self) -> &VecDeque<Vec<Cell>> { &self.scrollback }
 
     pub fn set_scrollback_max(&mut self, max: usize) { self.scrollback_max = max; }
 
@@ -84,8 +214,81 @@ impl Grid {
         &mut self.cells[row * self.cols + col]
     }
 
+    /// Cells of a screen row.
+    pub fn row(&self, row: usize) -> &[Cell] {
+        &self.cells[row * self.cols..(row + 1) * self.cols]
+    }
+
+    /// Codepoints stored after `cell.ch` for its grapheme cluster.
+    pub fn grapheme_extra(&self, cell: &Cell) -> &[char] {
+        self.graphemes.get(cell.extra)
+    }
+
+    /// Append a cell's full grapheme cluster to `out`. Wide-char spacers add nothing.
+    pub fn push_cell_text(&self, cell: &Cell, out: &mut String) {
+        if cell.ch == '\0' {
+            return;
+        }
+        out.push(cell.ch);
+        out.extend(self.grapheme_extra(cell));
+    }
+
+    /// Text of a row of cells (screen or scrollback) for matching, with the
+    /// byte offset where each column starts. Spacers become spaces so
+    /// columns stay aligned; trailing spaces are trimmed.
+    pub fn row_text(&self, cells: &[Cell]) -> RowText {
+        let mut text = String::with_capacity(cells.len());
+        let mut offsets = Vec::with_capacity(cells.len());
+        for cell in cells {
+            offsets.push(text.len());
+            if cell.ch == '\0' {
+                text.push(' ');
+            } else {
+                self.push_cell_text(cell, &mut text);
+            }
+        }
+        text.truncate(text.trim_end().len());
+        RowText { text, offsets }
+    }
+
+    /// Add a zero-width codepoint to the cluster in cell (row, col).
+    pub fn append_to_cell(&mut self, row: usize, col: usize, ch: char) {
+        let idx = row * self.cols + col;
+        let mut chars = self.graphemes.get(self.cells[idx].extra).to_vec();
+        chars.push(ch);
+        let id = self.graphemes.insert(chars);
+        self.cells[idx].extra = id;
+        if self.graphemes.live() > self.graphemes.gc_threshold {
+            self.collect_graphemes();
+        }
+    }
+
+    /// Free grapheme entries no longer referenced from the screen or scrollback.
+    pub fn collect_graphemes(&mut self) {
+        let mut used = vec![false; self.graphemes.slots.len()];
+        let cells = self.cells.iter().chain(self.scrollback.iter().flatten());
+        for cell in cells.filter(|c| c.extra != 0) {
+            if let Some(u) = used.get_mut(cell.extra as usize - 1) {
+                *u = true;
+            }
+        }
+        self.graphemes.free.clear();
+        for (i, slot) in self.graphemes.slots.iter_mut().enumerate() {
+            if !used[i] {
+                *slot = Vec::new();
+                self.graphemes.free.push(i as u32 + 1);
+            }
+        }
+        // Drop trailing free slots so the table can shrink
+        while self.graphemes.free.last() == Some(&(self.graphemes.slots.len() as u32)) {
+            self.graphemes.slots.pop();
+            self.graphemes.free.pop();
+        }
+        self.graphemes.gc_threshold = (self.graphemes.live() * 2).max(GraphemeTable::MIN_GC_THRESHOLD);
+    }
+
     /// Write a character at cursor, advance cursor.
-    pub fn put_char(&mut self, ch: char, attr: CellAttr, fg: Color, bg: Color) {
+    pub fn put_char(&mut self, ch: char, attr: CellAttr, fg: CellColor, bg: CellColor) {
         if self.cursor_col >= self.cols {
             self.cursor_col = 0;
             self.newline();
@@ -95,9 +298,24 @@ impl Grid {
         cell.attr = attr;
         cell.fg = fg;
         cell.bg = bg;
+        cell.underline_color = CellColor::Default;
+        cell.extra = 0;
+        cell.link = 0;
         self.cursor_col += 1;
     }
 
+    /// Write ASCII bytes at cursor in one pass, advance cursor.
+    /// Bytes past the right margin are dropped; the caller handles wrapping.
+    pub fn write_ascii(&mut self, bytes: &[u8], attr: CellAttr, fg: CellColor, bg: CellColor) {
+        let col = self.cursor_col.min(self.cols);
+        let n = bytes.len().min(self.cols - col);
+        let start = self.cursor_row * self.cols + col;
+        for (cell, &b) in self.cells[start..start + n].iter_mut().zip(bytes) {
+            *cell = Cell { ch: b as char, attr, fg, bg, underline_color: CellColor::Default, extra: 0, link: 0 };
+        }
+        self.cursor_col = col + n;
+    }
+
     /// Move to next line, scroll if at bottom.
     pub fn newline(&mut self) {
         if self.cursor_row + 1 >= self.rows {
@@ -111,27 +329,31 @@ impl Grid {
     /// Scroll the grid up by one line.
     fn scroll_up(&mut self) {
         // Save top row to scrollback
-        let top_row: Vec<Cell> = (0..self.cols)
-            .map(|c| *self.cell(0, c))
-            .collect();
-        self.scrollback.push(top_row);
-        if self.scrollback.len() > self.scrollback_max {
-            self.scrollback.remove(0);
-        }
+        self.push_scrollback(0);
 
         // Shift rows up
-        for row in 1..self.rows {
-            for col in 0..self.cols {
-                let src = self.cells[row * self.cols + col];
-                self.cells[(row - 1) * self.cols + col] = src;
-            }
-        }
+        let cols = self.cols;
+        self.cells.copy_within(cols.., 0);
 
         // Clear bottom row
         let last = self.rows - 1;
-        for col in 0..self.cols {
-            self.cells[last * self.cols + col] = Cell::default();
+        self.cells[last * cols..].fill(Cell::default());
+    }
+
+    /// Copy a screen row into scrollback, reusing the evicted row's buffer when full.
+    fn push_scrollback(&mut self, row: usize) {
+        if self.scrollback_max == 0 {
+            return;
         }
+        let src = &self.cells[row * self.cols..(row + 1) * self.cols];
+        let mut line = if self.scrollback.len() >= self.scrollback_max {
+            self.scrollback.pop_front().unwrap_or_default()
+        } else {
+            Vec::with_capacity(self.cols)
+        };
+        line.clear();
+        line.extend_from_slice(src);
+        self.scrollback.push_back(line);
     }
 
     /// Resize the grid (reflow not implemented yet).
@@ -202,96 +424,96 @@ impl Grid {
         }
     }
 
-    /// Scroll a region up by one line.
-    pub fn scroll_region_up(&mut self, top: usize, bottom: usize) {
-        if top == 0 {
+    /// Scroll rows `top..=bottom` up by one line within columns `left..=right`.
+    /// The top line goes to scrollback only when the region starts at row 0
+    /// and spans the full width.
+    pub fn scroll_region_up(&mut self, top: usize, bottom: usize, left: usize, right: usize) {
+        if top == 0 && left == 0 && right + 1 >= self.cols {
             // Save to scrollback
-            let top_row: Vec<Cell> = (0..self.cols).map(|c| self.cells[c]).collect();
-            self.scrollback.push(top_row);
-            if self.scrollback.len() > self.scrollback_max {
-                self.scrollback.remove(0);
-            }
+            self.push_scrollback(top);
         }
-        for row in top..bottom {
-            for col in 0..self.cols {
-                self.cells[row * self.cols + col] = self.cells[(row + 1) * self.cols + col];
+        self.shift_rows_up(top, bottom, left, right);
+    }
+
+    /// Scroll rows `top..=bottom` down by one line within columns `left..=right`.
+    pub fn scroll_region_down(&mut self, top: usize, bottom: usize, left: usize, right: usize) {
+        let cols = self.cols;
+        if left == 0 && right + 1 >= cols {
+            self.cells.copy_within(top * cols..bottom * cols, (top + 1) * cols);
+        } else {
+            for row in (top + 1..=bottom).rev() {
+                let src = (row - 1) * cols;
+                self.cells.copy_within(src + left..=src + right, row * cols + left);
             }
         }
-        for col in 0..self.cols {
-            self.cells[bottom * self.cols + col] = Cell::default();
-        }
+        self.cells[top * cols + left..=top * cols + right].fill(Cell::default());
     }
 
-    /// Scroll a region down by one line.
-    pub fn scroll_region_down(&mut self, top: usize, bottom: usize) {
-        for row in (top + 1..=bottom).rev() {
-            for col in 0..self.cols {
-                self.cells[row * self.cols + col] = self.cells[(row - 1) * self.cols + col];
+    fn shift_rows_up(&mut self, top: usize, bottom: usize, left: usize, right: usize) {
+        let cols = self.cols;
+        if left == 0 && right + 1 >= cols {
+            self.cells.copy_within((top + 1) * cols..(bottom + 1) * cols, top * cols);
+        } else {
+            for row in top..bottom {
+                let src = (row + 1) * cols;
+                self.cells.copy_within(src + left..=src + right, row * cols + left);
             }
         }
-        for col in 0..self.cols {
-            self.cells[top * self.cols + col] = Cell::default();
-        }
-    }
-
-    /// Insert n blank lines at cursor row, pushing lines down.
-    pub fn insert_lines(&mut self, at: usize, n: usize, bottom: usize) {
-        for _ in 0..n {
-            if at <= bottom {
-                // Shift rows down
-                for row in (at + 1..=bottom).rev() {
-                    for col in 0..self.cols {
-                        self.cells[row * self.cols + col] = self.cells[(row - 1) * self.cols + col];
-                    }
-                }
-                for col in 0..self.cols {
-                    self.cells[at * self.cols + col] = Cell::default();
-                }
+        self.cells[bottom * cols + left..=bottom * cols + right].fill(Cell::default());
+    }
+
+    /// Insert n blank lines at row `at`, pushing lines down to `bottom`
+    /// within columns `left..=right`.
+    pub fn insert_lines(&mut self, at: usize, n: usize, bottom: usize, left: usize, right: usize) {
+        if at <= bottom {
+            for _ in 0..n.min(bottom + 1 - at) {
+                self.scroll_region_down(at, bottom, left, right);
             }
         }
     }
 
-    /// Delete n lines at cursor row, pulling lines up.
-    pub fn delete_lines(&mut self, at: usize, n: usize, bottom: usize) {
-        for _ in 0..n {
-            if at <= bottom {
-                for row in at..bottom {
-                    for col in 0..self.cols {
-                        self.cells[row * self.cols + col] = self.cells[(row + 1) * self.cols + col];
-                    }
-                }
-                for col in 0..self.cols {
-                    self.cells[bottom * self.cols + col] = Cell::default();
-                }
+    /// Delete n lines at row `at`, pulling lines up from `bottom` within
+    /// columns `left..=right`.
+    pub fn delete_lines(&mut self, at: usize, n: usize, bottom: usize, left: usize, right: usize) {
+        if at <= bottom {
+            for _ in 0..n.min(bottom + 1 - at) {
+                self.shift_rows_up(at, bottom, left, right);
             }
         }
     }
 
-    /// Delete n characters at cursor, shifting remaining left.
-    pub fn delete_chars(&mut self, n: usize) {
-        let row = self.cursor_row;
+    /// Delete n characters at cursor, shifting the rest of the line up to
+    /// column `right` left.
+    pub fn delete_chars(&mut self, n: usize, right: usize) {
+        let start = self.cursor_row * self.cols;
         let col = self.cursor_col;
-        for c in col..self.cols {
-            let src = if c + n < self.cols {
-                self.cells[row * self.cols + c + n]
+        for c in col..=right {
+            let src = if c + n <= right {
+                self.cells[start + c + n]
             } else {
                 Cell::default()
             };
-            self.cells[row * self.cols + c] = src;
+            self.cells[start + c] = src;
         }
     }
 
-    /// Insert n blank characters at cursor, shifting existing right.
-    pub fn insert_chars(&mut self, n: usize) {
-        let row = self.cursor_row;
+    /// Insert n blank characters at cursor, shifting existing ones right;
+    /// characters pushed past column `right` are lost.
+    pub fn insert_chars(&mut self, n: usize, right: usize) {
+        let start = self.cursor_row * self.cols;
         let col = self.cursor_col;
-        for c in (col..self.cols).rev() {
+        // A wide character whose spacer is pushed off the line is cut in half
+        let orphan = n > 0 && col + n <= right && self.cells[start + right + 1 - n].ch == '\0';
+        for c in (col..=right).rev() {
             if c >= col + n {
-                self.cells[row * self.cols + c] = self.cells[row * self.cols + c - n];
+                self.cells[start + c] = self.cells[start + c - n];
             } else {
-                self.cells[row * self.cols + c] = Cell::default();
+                self.cells[start + c] = Cell::default();
             }
         }
+        if orphan {
+            self.cells[start + right] = Cell::default();
+        }
     }
 }
 
@@ -307,25 +529,59 @@ mod tests {
     fn test_put_char_and_wrap() {
         let mut g = Grid::new(5, 3);
         for ch in "ABCDE".chars() {
-            g.put_char(ch, CellAttr::empty(), Color::DEFAULT_FG, Color::DEFAULT_BG);
+            g.put_char(ch, CellAttr::empty(), CellColor::Default, CellColor::Default);
         }
         assert_eq!(g.cursor_col, 5);
         assert_eq!(g.cursor_row, 0);
         // Next char should wrap
-        g.put_char('F', CellAttr::empty(), Color::DEFAULT_FG, Color::DEFAULT_BG);
+        g.put_char('F', CellAttr::empty(), CellColor::Default, CellColor::Default);
         assert_eq!(g.cursor_row, 1);
         assert_eq!(g.cursor_col, 1);
         assert_eq!(g.cell(1, 0).ch, 'F');
     }
 
+    #[test]
+    fn test_write_ascii_clips_at_margin() {
+        let mut g = Grid::new(5, 3);
+        g.cursor_col = 2;
+        g.write_ascii(b"XYZW", CellAttr::BOLD, CellColor::Default, CellColor::Default);
+        assert_eq!(grid_row_chars(&g, 0), "  XYZ");
+        assert_eq!(g.cursor_col, 5);
+        assert!(g.cell(0, 4).attr.contains(CellAttr::BOLD));
+    }
+
+    #[test]
+    fn test_grapheme_gc_reclaims_overwritten() {
+        let mut g = Grid::new(4, 2);
+        for i in 0..1000 {
+            g.cursor_col = 0;
+            g.put_char('e', CellAttr::empty(), CellColor::Default, CellColor::Default);
+            g.append_to_cell(0, 0, if i % 2 == 0 { '\u{301}' } else { '\u{300}' });
+        }
+        assert!(g.graphemes.live() <= GraphemeTable::MIN_GC_THRESHOLD + 1);
+        assert_eq!(g.grapheme_extra(g.cell(0, 0)), &['\u{300}']);
+    }
+
+    #[test]
+    fn test_grapheme_survives_scrollback() {
+        let mut g = Grid::new(4, 1);
+        g.put_char('a', CellAttr::empty(), CellColor::Default, CellColor::Default);
+        g.append_to_cell(0, 0, '\u{308}');
+        g.newline();
+        g.collect_graphemes();
+        let line = g.row_text(&g.scrollback()[0]);
+        assert_eq!(line.text, "a\u{308}");
+        assert_eq!(line.col_at(1), 0);
+    }
+
     #[test]
     fn test_newline_scrolls() {
         let mut g = Grid::new(5, 3);
-        g.put_char('A', CellAttr::empty(), Color::DEFAULT_FG, Color::DEFAULT_BG);
+        g.put_char('A', CellAttr::empty(), CellColor::Default, CellColor::Default);
         g.newline();
-        g.put_char('B', CellAttr::empty(), Color::DEFAULT_FG, Color::DEFAULT_BG);
+        g.put_char('B', CellAttr::empty(), CellColor::Default, CellColor::Default);
         g.newline();
-        g.put_char('C', CellAttr::empty(), Color::DEFAULT_FG, Color::DEFAULT_BG);
+        g.put_char('C', CellAttr::empty(), CellColor::Default, CellColor::Default);
         g.newline(); // should scroll
         assert_eq!(g.cell(0, 0).ch, 'B');
         assert_eq!(g.cell(1, 0).ch, 'C');
@@ -336,7 +592,7 @@ mod tests {
     fn test_erase_line_right() {
         let mut g = Grid::new(10, 1);
         for ch in "ABCDEFGHIJ".chars() {
-            g.put_char(ch, CellAttr::empty(), Color::DEFAULT_FG, Color::DEFAULT_BG);
+            g.put_char(ch, CellAttr::empty(), CellColor::Default, CellColor::Default);
         }
         g.cursor_col = 5;
         g.erase_line_right();
@@ -347,7 +603,7 @@ mod tests {
     fn test_erase_line_left() {
         let mut g = Grid::new(10, 1);
         for ch in "ABCDEFGHIJ".chars() {
-            g.put_char(ch, CellAttr::empty(), Color::DEFAULT_FG, Color::DEFAULT_BG);
+            g.put_char(ch, CellAttr::empty(), CellColor::Default, CellColor::Default);
         }
         g.cursor_col = 4;
         g.erase_line_left();
@@ -361,7 +617,7 @@ mod tests {
         let mut g = Grid::new(5, 3);
         for r in 0..3 {
             for ch in "XXXXX".chars() {
-                g.put_char(ch, CellAttr::empty(), Color::DEFAULT_FG, Color::DEFAULT_BG);
+                g.put_char(ch, CellAttr::empty(), CellColor::Default, CellColor::Default);
             }
             if r < 2 { g.newline(); }
         }
@@ -382,10 +638,10 @@ mod tests {
             g.cursor_row = r;
             g.cursor_col = 0;
             for ch in s.chars() {
-                g.put_char(ch, CellAttr::empty(), Color::DEFAULT_FG, Color::DEFAULT_BG);
+                g.put_char(ch, CellAttr::empty(), CellColor::Default, CellColor::Default);
             }
         }
-        g.scroll_region_up(1, 3); // scroll rows 1-3
+        g.scroll_region_up(1, 3, 0, 2); // scroll rows 1-3
         assert_eq!(grid_row_chars(&g, 0), "AAA");
         assert_eq!(grid_row_chars(&g, 1), "CCC");
         assert_eq!(grid_row_chars(&g, 2), "DDD");
@@ -400,10 +656,10 @@ mod tests {
             g.cursor_row = r;
             g.cursor_col = 0;
             for ch in s.chars() {
-                g.put_char(ch, CellAttr::empty(), Color::DEFAULT_FG, Color::DEFAULT_BG);
+                g.put_char(ch, CellAttr::empty(), CellColor::Default, CellColor::Default);
             }
         }
-        g.scroll_region_down(1, 3);
+        g.scroll_region_down(1, 3, 0, 2);
         assert_eq!(grid_row_chars(&g, 0), "AAA");
         assert_eq!(grid_row_chars(&g, 1), "");    // cleared (new blank line)
         assert_eq!(grid_row_chars(&g, 2), "BBB");
@@ -418,10 +674,10 @@ mod tests {
             g.cursor_row = r;
             g.cursor_col = 0;
             for ch in s.chars() {
-                g.put_char(ch, CellAttr::empty(), Color::DEFAULT_FG, Color::DEFAULT_BG);
+                g.put_char(ch, CellAttr::empty(), CellColor::Default, CellColor::Default);
             }
         }
-        g.insert_lines(1, 1, 3);
+        g.insert_lines(1, 1, 3, 0, 2);
         assert_eq!(grid_row_chars(&g, 0), "AAA");
         assert_eq!(grid_row_chars(&g, 1), "");    // inserted blank
         assert_eq!(grid_row_chars(&g, 2), "BBB");
@@ -435,10 +691,10 @@ mod tests {
             g.cursor_row = r;
             g.cursor_col = 0;
             for ch in s.chars() {
-                g.put_char(ch, CellAttr::empty(), Color::DEFAULT_FG, Color::DEFAULT_BG);
+                g.put_char(ch, CellAttr::empty(), CellColor::Default, CellColor::Default);
             }
         }
-        g.delete_lines(1, 1, 3);
+        g.delete_lines(1, 1, 3, 0, 2);
         assert_eq!(grid_row_chars(&g, 0), "AAA");
         assert_eq!(grid_row_chars(&g, 1), "CCC");
         assert_eq!(grid_row_chars(&g, 2), "DDD");
@@ -449,10 +705,10 @@ mod tests {
     fn test_delete_chars() {
         let mut g = Grid::new(10, 1);
         for ch in "ABCDEFGHIJ".chars() {
-            g.put_char(ch, CellAttr::empty(), Color::DEFAULT_FG, Color::DEFAULT_BG);
+            g.put_char(ch, CellAttr::empty(), CellColor::Default, CellColor::Default);
         }
         g.cursor_col = 3;
-        g.delete_chars(2); // delete D, E
+        g.delete_chars(2, 9); // delete D, E
         assert_eq!(grid_row_chars(&g, 0), "ABCFGHIJ");
     }
 
@@ -460,20 +716,59 @@ mod tests {
     fn test_insert_chars() {
         let mut g = Grid::new(10, 1);
         for ch in "ABCDEFGHIJ".chars() {
-            g.put_char(ch, CellAttr::empty(), Color::DEFAULT_FG, Color::DEFAULT_BG);
+            g.put_char(ch, CellAttr::empty(), CellColor::Default, CellColor::Default);
         }
         g.cursor_col = 3;
-        g.insert_chars(2); // insert 2 blanks at D
+        g.insert_chars(2, 9); // insert 2 blanks at D
         assert_eq!(g.cell(0, 3).ch, ' ');
         assert_eq!(g.cell(0, 4).ch, ' ');
         assert_eq!(g.cell(0, 5).ch, 'D');
     }
 
+    #[test]
+    fn test_insert_chars_splits_wide_char_at_edge() {
+        let mut g = Grid::new(6, 1);
+        g.write_ascii(b"ABCD", CellAttr::empty(), CellColor::Default, CellColor::Default);
+        g.put_char('中', CellAttr::empty(), CellColor::Default, CellColor::Default);
+        g.put_char('\0', CellAttr::empty(), CellColor::Default, CellColor::Default);
+        g.cursor_col = 0;
+        g.insert_chars(1, 5);
+        // The wide char's spacer fell off; its first half is blanked
+        assert_eq!(grid_row_chars(&g, 0), " ABCD");
+    }
+
+    #[test]
+    fn test_scroll_region_up_within_margins() {
+        let mut g = Grid::new(4, 3);
+        for (r, s) in ["AAAA", "BBBB", "CCCC"].iter().enumerate() {
+            g.cursor_row = r;
+            g.cursor_col = 0;
+            g.write_ascii(s.as_bytes(), CellAttr::empty(), CellColor::Default, CellColor::Default);
+        }
+        g.scroll_region_up(0, 2, 1, 2);
+        assert_eq!(grid_row_chars(&g, 0), "ABBA");
+        assert_eq!(grid_row_chars(&g, 1), "BCCB");
+        assert_eq!(grid_row_chars(&g, 2), "C  C");
+        // Only full-width scrolls feed scrollback
+        assert!(g.scrollback().is_empty());
+    }
+
+    #[test]
+    fn test_insert_delete_chars_stop_at_right_margin() {
+        let mut g = Grid::new(6, 1);
+        g.write_ascii(b"ABCDEF", CellAttr::empty(), CellColor::Default, CellColor::Default);
+        g.cursor_col = 1;
+        g.insert_chars(1, 3);
+        assert_eq!(grid_row_chars(&g, 0), "A BCEF");
+        g.delete_chars(2, 3);
+        assert_eq!(grid_row_chars(&g, 0), "AC  EF");
+    }
+
     #[test]
     fn test_resize_shrink() {
         let mut g = Grid::new(10, 5);
         for ch in "Hello".chars() {
-            g.put_char(ch, CellAttr::empty(), Color::DEFAULT_FG, Color::DEFAULT_BG);
+            g.put_char(ch, CellAttr::empty(), CellColor::Default, CellColor::Default);
         }
         g.cursor_row = 3;
         g.cursor_col = 8;
@@ -489,7 +784,7 @@ mod tests {
     fn test_resize_grow() {
         let mut g = Grid::new(5, 3);
         for ch in "Hi".chars() {
-            g.put_char(ch, CellAttr::empty(), Color::DEFAULT_FG, Color::DEFAULT_BG);
+            g.put_char(ch, CellAttr::empty(), CellColor::Default, CellColor::Default);
         }
         g.resize(10, 5);
         assert_eq!(g.cols(), 10);
@@ -501,9 +796,9 @@ mod tests {
     #[test]
     fn test_scrollback_saved() {
         let mut g = Grid::new(3, 2);
-        g.put_char('A', CellAttr::empty(), Color::DEFAULT_FG, Color::DEFAULT_BG);
+        g.put_char('A', CellAttr::empty(), CellColor::Default, CellColor::Default);
         g.newline();
-        g.put_char('B', CellAttr::empty(), Color::DEFAULT_FG, Color::DEFAULT_BG);
+        g.put_char('B', CellAttr::empty(), CellColor::Default, CellColor::Default);
         g.newline(); // scrolls, A goes to scrollback
         assert_eq!(g.scrollback.len(), 1);
         assert_eq!(g.scrollback[0][0].ch, 'A');
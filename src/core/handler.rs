@@ -1,40 +1,38 @@
 /// Terminal handler: translates VT parser Actions into Grid operations.
 /// This is the brain that interprets CSI/ESC/OSC sequences.
 
-use crate::core::grid::{Grid, Cell, CellAttr, Color};
-use crate::core::parser::Action;
-use crate::core::utf8::{Utf8Decoder, char_width};
-
-/// Standard 8 ANSI colors + bright variants
-const ANSI_COLORS: [Color; 16] = [
-    Color { r: 0,   g: 0,   b: 0   }, // 0 black
-    Color { r: 205, g: 49,  b: 49  }, // 1 red
-    Color { r: 13,  g: 188, b: 121 }, // 2 green
-    Color { r: 229, g: 229, b: 16  }, // 3 yellow
-    Color { r: 36,  g: 114, b: 200 }, // 4 blue
-    Color { r: 188, g: 63,  b: 188 }, // 5 magenta
-    Color { r: 17,  g: 168, b: 205 }, // 6 cyan
-    Color { r: 204, g: 204, b: 204 }, // 7 white
-    Color { r: 102, g: 102, b: 102 }, // 8 bright black
-    Color { r: 241, g: 76,  b: 76  }, // 9 bright red
-    Color { r: 35,  g: 209, b: 139 }, // 10 bright green
-    Color { r: 245, g: 245, b: 67  }, // 11 bright yellow
-    Color { r: 59,  g: 142, b: 234 }, // 12 bright blue
-    Color { r: 214, g: 112, b: 214 }, // 13 bright magenta
-    Color { r: 41,  g: 184, b: 219 }, // 14 bright cyan
-    Color { r: 242, g: 242, b: 242 }, // 15 bright white
-];
+use crate::core::grid::{Grid, Cell, CellAttr, CellColor, Color, UnderlineStyle};
+use crate::core::hyperlink::HyperlinkTable;
+use crate::core::palette::{self, Palette};
+use crate::core::parser::{Action, Params, Perform, VtParser};
+use crate::clipboard::{ClipboardAccess, Osc52};
+use crate::core::event::TerminalEvent;
+use crate::core::width::{self, AmbiguousWidth};
+use crate::input::{KeyboardFlags, KeyboardModeStack, TerminalModes};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Where the last printed character went. A zero-width codepoint joins
+/// that cell's cluster only while the cursor hasn't moved since.
+#[derive(Debug, Clone, Copy)]
+struct LastPrint {
+    row: usize,
+    col: usize,
+    cursor: (usize, usize),
+}
 
 pub struct Terminal {
     pub grid: Grid,
-    utf8: Utf8Decoder,
     attr: CellAttr,
-    fg: Color,
-    bg: Color,
+    fg: CellColor,
+    bg: CellColor,
+    /// Underline color (SGR 58), `Default` = foreground
+    underline_color: CellColor,
     saved_cursor: (usize, usize),
     saved_attr: CellAttr,
-    saved_fg: Color,
-    saved_bg: Color,
+    saved_fg: CellColor,
+    saved_bg: CellColor,
+    saved_underline_color: CellColor,
     /// Alternate screen buffer
     alt_grid: Option<Grid>,
     /// Tab stops (column indices)
@@ -43,9 +41,23 @@ pub struct Terminal {
     origin_mode: bool,
     /// Auto-wrap mode (DECAWM)
     auto_wrap: bool,
+    /// Insert/replace mode (IRM): printing shifts the rest of the line right
+    insert_mode: bool,
+    /// Line feed/new line mode (LNM): LF also returns the carriage, and
+    /// Enter should send CR LF
+    pub newline_mode: bool,
     /// Scroll region (top, bottom) — inclusive
     scroll_top: usize,
     scroll_bottom: usize,
+    /// Left/right margin mode (DECLRMM): `CSI Pl;Pr s` sets the margins
+    lr_margin_mode: bool,
+    /// Left/right margins (left, right) — inclusive, full width unless DECLRMM is set
+    margin_left: usize,
+    margin_right: usize,
+    /// Colors that cell colors resolve against, as changed by OSC 4/10/11/12
+    pub palette: Palette,
+    /// Palette from the theme, restored by OSC 104/110/111/112
+    theme_palette: Palette,
     /// Title set via OSC
     pub title: String,
     /// Write-back buffer for DSR responses
@@ -56,24 +68,148 @@ pub struct Terminal {
     pub cursor_visible: bool,
     /// Bracketed paste mode
     pub bracketed_paste: bool,
+    /// Focus in/out reporting (mode 1004)
+    pub focus_reporting: bool,
+    /// Whether the window or pane has focus, as last passed to `set_focused`
+    focused: bool,
+    /// Synchronized output (mode 2026): when the current update began
+    sync_update: Option<Instant>,
+    /// Kitty keyboard protocol flag stacks: [main screen, alternate screen]
+    keyboard_modes: [KeyboardModeStack; 2],
     /// Mouse reporting mode
     pub mouse_mode: MouseMode,
     /// Mouse encoding
     pub mouse_encoding: MouseEncoding,
+    /// Alternate scroll (mode 1007): the wheel sends cursor keys on the
+    /// alternate screen when mouse reporting is off
+    pub alternate_scroll: bool,
     /// Keypad application mode
     pub keypad_app: bool,
-    /// OSC 7 working directory (latest)
-    pub osc7_cwd: Option<String>,
-    /// OSC 133 shell integration data (latest)
-    pub osc133_data: Option<String>,
-    /// OSC 52 clipboard data (latest)
-    pub osc52_data: Option<String>,
+    /// xterm modifyOtherKeys level, set by `CSI > 4 ; level m`
+    pub modify_other_keys: u8,
+    /// S8C1T: replies use 8-bit C1 introducers
+    pub c1_responses: bool,
+    /// Cell written by the last print, for joining zero-width codepoints
+    last_print: Option<LastPrint>,
+    /// Width of East Asian Ambiguous characters
+    pub ambiguous_width: AmbiguousWidth,
+    /// Mode 2027: cell width follows the whole grapheme cluster
+    grapheme_clusters: bool,
+    /// OSC 8 hyperlinks referenced by cells
+    pub hyperlinks: HyperlinkTable,
+    /// Link applied to printed cells (OSC 8), 0 = none
+    link: u16,
+    /// OSC 8 link (params, URI) not yet in the table: it is added when a
+    /// cell first uses it
+    pending_link: Option<(String, String)>,
+    /// Events raised since the last `drain_events`
+    events: VecDeque<TerminalEvent>,
+    /// `payload_len` total of `events`
+    event_bytes: usize,
     /// Shell integration state
     pub shell: crate::shell_integration::ShellIntegration,
+    /// Images placed via the Kitty graphics protocol (APC G)
+    pub images: crate::image::ImageManager,
+    /// Cursor style from DECSCUSR (0–6); not used by the renderers yet
+    pub cursor_style: u16,
+    /// Titles saved by `CSI 22 t`, restored by `CSI 23 t`
+    title_stack: Vec<String>,
+    /// Cell size in pixels (width, height) for XTWINOPS size reports;
+    /// zero until the UI calls `set_cell_size`, and reported as zero then
+    cell_pixels: (u16, u16),
+    /// Decides which XTWINOPS window manipulations are allowed
+    pub window_op_policy: WindowOpPolicy,
+    /// OSC 52 writes; `Ask` and `Allow` raise `ClipboardStore`, the UI
+    /// confirms `Ask`
+    pub clipboard_write: ClipboardAccess,
+    /// OSC 52 reads; `Ask` and `Allow` raise `ClipboardLoadRequest`
+    pub clipboard_read: ClipboardAccess,
+    /// Largest OSC 52 text stored or reported, in bytes
+    pub clipboard_max_bytes: usize,
+    /// DCS request being collected, with its payload
+    dcs_request: Option<(DcsRequest, Vec<u8>)>,
+}
+
+/// DCS strings the terminal answers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DcsRequest {
+    /// DECRQSS — `DCS $ q Pt ST`
+    StatusString,
+    /// XTGETTCAP — `DCS + q Pt ST`
+    Termcap,
 }
 
+/// Window manipulations requested with XTWINOPS (`CSI Ps ; Ps ; Ps t`).
+/// They are raised as `TerminalEvent::WindowOp` only if the policy allows
+/// them.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowOp {
+    /// `CSI 1 t`
+    Deiconify,
+    /// `CSI 2 t`
+    Iconify,
+    /// `CSI 3 ; x ; y t`
+    Move { x: u16, y: u16 },
+    /// `CSI 4 ; height ; width t`; 0 keeps that dimension
+    ResizePixels { width: u16, height: u16 },
+    /// `CSI 5 t`
+    Raise,
+    /// `CSI 6 t`
+    Lower,
+    /// `CSI 7 t`
+    Refresh,
+    /// `CSI 8 ; rows ; cols t` and `CSI Ps t` for Ps ≥ 24 (rows only); 0
+    /// keeps that dimension
+    ResizeCells { cols: u16, rows: u16 },
+    /// `CSI 9 ; Ps t`: 0 restore, 1 maximize, 2 vertically, 3 horizontally
+    Maximize(u16),
+    /// `CSI 10 ; Ps t`: 0 leave, 1 enter, 2 toggle full screen
+    Fullscreen(u16),
+    /// `CSI 20 t` / `CSI 21 t`: answer with the title, which lets the
+    /// application read back text an attacker may have put there
+    ReportTitle,
+}
+
+/// Decides whether a window manipulation may go ahead.
+pub type WindowOpPolicy = fn(&WindowOp) -> bool;
+
+/// The default policy: no window manipulation is allowed.
+pub fn deny_window_ops(_op: &WindowOp) -> bool {
+    false
+}
+
+/// Events kept for a consumer that doesn't drain them; older ones are
+/// dropped.
+const MAX_EVENTS: usize = 1024;
+
+/// Text the event queue may hold (titles, clipboard data, notifications);
+/// the oldest events are dropped beyond it.
+const MAX_EVENT_BYTES: usize = 4 << 20;
+
+/// Titles kept by `CSI 22 t`; pushing onto a full stack drops the oldest.
+const MAX_TITLE_STACK: usize = 10;
+
+/// Longest a synchronized update (mode 2026) may hold back rendering.
+pub const SYNC_UPDATE_TIMEOUT: Duration = Duration::from_millis(150);
+
+/// Payload cap for DCS requests; longer ones are dropped.
+const MAX_DCS_REQUEST: usize = 1024;
+
+/// Capabilities answered by XTGETTCAP; `None` marks a boolean capability.
+const TERMCAPS: &[(&str, Option<&str>)] = &[
+    ("TN", Some("xterm-256color")),
+    ("name", Some("xterm-256color")),
+    ("Co", Some("256")),
+    ("colors", Some("256")),
+    ("RGB", Some("8/8/8")),
+    ("Tc", None),
+    ("Su", None),
+    ("Smulx", Some("\x1b[4:%p1%dm")),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum MouseMode {
+    #[default]
     Off,
     X10,       // 9 — press only
     Normal,    // 1000 — press + release
@@ -81,12 +217,51 @@ pub enum MouseMode {
     Any,       // 1003 — all motion
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum MouseEncoding {
-    X10,   // default
-    Sgr,   // 1006
+    #[default]
+    X10,       // default
+    Utf8,      // 1005
+    Sgr,       // 1006
+    Urxvt,     // 1015
+    SgrPixels, // 1016
 }
 
+/// Reads the state of one mode, for DECRQM.
+type ModeGetter = fn(&Terminal) -> bool;
+
+/// DEC private modes (`CSI ? Ps h`) reported by DECRQM. Modes missing here
+/// are reported as not recognized.
+const DEC_MODES: &[(u16, ModeGetter)] = &[
+    (1, |t| t.cursor_keys_app),                           // DECCKM
+    (6, |t| t.origin_mode),                               // DECOM
+    (7, |t| t.auto_wrap),                                 // DECAWM
+    (9, |t| t.mouse_mode == MouseMode::X10),
+    (25, |t| t.cursor_visible),                           // DECTCEM
+    (47, |t| t.alt_grid.is_some()),
+    (69, |t| t.lr_margin_mode),                           // DECLRMM
+    (1000, |t| t.mouse_mode == MouseMode::Normal),
+    (1002, |t| t.mouse_mode == MouseMode::Button),
+    (1003, |t| t.mouse_mode == MouseMode::Any),
+    (1004, |t| t.focus_reporting),
+    (1005, |t| t.mouse_encoding == MouseEncoding::Utf8),
+    (1006, |t| t.mouse_encoding == MouseEncoding::Sgr),
+    (1007, |t| t.alternate_scroll),
+    (1015, |t| t.mouse_encoding == MouseEncoding::Urxvt),
+    (1016, |t| t.mouse_encoding == MouseEncoding::SgrPixels),
+    (1047, |t| t.alt_grid.is_some()),
+    (1049, |t| t.alt_grid.is_some()),
+    (2004, |t| t.bracketed_paste),
+    (2026, |t| t.sync_update.is_some()),                  // Synchronized output
+    (2027, |t| t.grapheme_clusters),
+];
+
+/// ANSI modes (`CSI Ps h`) reported by DECRQM.
+const ANSI_MODES: &[(u16, ModeGetter)] = &[
+    (4, |t| t.insert_mode),                               // IRM
+    (20, |t| t.newline_mode),                             // LNM
+];
+
 impl Terminal {
     pub fn new(cols: usize, rows: usize) -> Self {
         let mut tab_stops = vec![false; cols];
@@ -95,52 +270,67 @@ impl Terminal {
         }
         Self {
             grid: Grid::new(cols, rows),
-            utf8: Utf8Decoder::new(),
             attr: CellAttr::empty(),
-            fg: Color::DEFAULT_FG,
-            bg: Color::DEFAULT_BG,
+            fg: CellColor::Default,
+            bg: CellColor::Default,
+            underline_color: CellColor::Default,
             saved_cursor: (0, 0),
             saved_attr: CellAttr::empty(),
-            saved_fg: Color::DEFAULT_FG,
-            saved_bg: Color::DEFAULT_BG,
+            saved_fg: CellColor::Default,
+            saved_bg: CellColor::Default,
+            saved_underline_color: CellColor::Default,
             alt_grid: None,
             tab_stops,
             origin_mode: false,
             auto_wrap: true,
+            insert_mode: false,
+            newline_mode: false,
             scroll_top: 0,
             scroll_bottom: rows - 1,
+            lr_margin_mode: false,
+            margin_left: 0,
+            margin_right: cols - 1,
+            palette: Palette::default(),
+            theme_palette: Palette::default(),
             title: String::new(),
             write_back: Vec::new(),
             cursor_keys_app: false,
             cursor_visible: true,
             bracketed_paste: false,
+            focus_reporting: false,
+            focused: true,
+            sync_update: None,
+            keyboard_modes: Default::default(),
             mouse_mode: MouseMode::Off,
             mouse_encoding: MouseEncoding::X10,
+            alternate_scroll: false,
             keypad_app: false,
-            osc7_cwd: None,
-            osc133_data: None,
-            osc52_data: None,
+            modify_other_keys: 0,
+            c1_responses: false,
+            last_print: None,
+            ambiguous_width: AmbiguousWidth::Narrow,
+            grapheme_clusters: false,
+            hyperlinks: HyperlinkTable::new(),
+            link: 0,
+            pending_link: None,
+            events: VecDeque::new(),
+            event_bytes: 0,
             shell: crate::shell_integration::ShellIntegration::new(),
+            images: crate::image::ImageManager::new(),
+            cursor_style: 0,
+            dcs_request: None,
+            title_stack: Vec::new(),
+            cell_pixels: (0, 0),
+            window_op_policy: deny_window_ops,
+            clipboard_write: ClipboardAccess::Allow,
+            clipboard_read: ClipboardAccess::Ask,
+            clipboard_max_bytes: crate::clipboard::OSC52_MAX_BYTES,
         }
     }
 
     /// Feed raw bytes from PTY. Decodes UTF-8 and processes VT actions.
-    pub fn feed_bytes(&mut self, parser: &mut crate::core::parser::VtParser, data: &[u8]) {
-        for &byte in data {
-            // Let parser handle control chars and escape sequences directly
-            if byte < 0x80 || self.utf8.is_pending() || byte >= 0x80 {
-                let action = parser.advance(byte);
-                match action {
-                    Action::Print(ch) if ch == char::REPLACEMENT_CHARACTER && byte >= 0x80 => {
-                        // Parser doesn't handle UTF-8; decode ourselves
-                        if let Some(decoded) = self.utf8.feed(byte) {
-                            self.print(decoded);
-                        }
-                    }
-                    _ => self.handle_action(action),
-                }
-            }
-        }
+    pub fn feed_bytes(&mut self, parser: &mut VtParser, data: &[u8]) {
+        parser.parse(self, data);
     }
 
     pub fn handle_action(&mut self, action: Action) {
@@ -153,41 +343,55 @@ impl Terminal {
             Action::EscDispatch { final_byte, intermediates } => {
                 self.esc_dispatch(final_byte, &intermediates);
             }
-            Action::OscDispatch(data) => self.osc_dispatch(&data),
-            Action::None => {}
+            // Actions don't record the terminator; replies use ST
+            Action::OscDispatch(data) => self.osc_dispatch(&data, false),
+            Action::DcsHook { params, intermediates, final_byte } => {
+                self.hook(&params, &intermediates, final_byte);
+            }
+            Action::DcsPut(byte) => self.put(byte),
+            Action::DcsUnhook => self.unhook(),
+            Action::ApcDispatch(data) => self.apc_dispatch(&data),
         }
     }
 
     fn print(&mut self, ch: char) {
-        let width = char_width(ch);
-        if width == 0 {
+        self.intern_pending_link();
+        let width = width::char_width(ch, self.ambiguous_width);
+        if width == 0 || self.after_zwj() || self.completes_flag(ch) {
+            self.append_to_cluster(ch);
             return;
         }
 
         let cols = self.grid.cols();
+        let end = self.line_end();
         // Auto-wrap
-        if self.grid.cursor_col >= cols {
+        if self.grid.cursor_col > end {
             if self.auto_wrap {
-                self.grid.cursor_col = 0;
+                self.carriage_return();
                 self.index();
             } else {
-                self.grid.cursor_col = cols - 1;
+                self.grid.cursor_col = end;
             }
         }
 
         // For wide chars, check if there's room
-        if width == 2 && self.grid.cursor_col + 1 >= cols {
+        if width == 2 && self.grid.cursor_col + 1 > end {
             if self.auto_wrap {
                 self.grid.put_char(' ', self.attr, self.fg, self.bg);
-                self.grid.cursor_col = 0;
+                self.carriage_return();
                 self.index();
             } else {
                 // No room for wide char at end, overwrite last cell
-                self.grid.cursor_col = cols - 2;
+                self.grid.cursor_col = end - 1;
             }
         }
 
+        if self.insert_mode {
+            self.grid.insert_chars(width, end);
+        }
         self.grid.put_char(ch, self.attr, self.fg, self.bg);
+        let (row, col) = (self.grid.cursor_row, self.grid.cursor_col - 1);
+        self.apply_pen(row, col);
 
         // Wide char occupies two cells
         if width == 2 && self.grid.cursor_col < cols {
@@ -196,44 +400,210 @@ impl Terminal {
             cell.attr = self.attr;
             cell.fg = self.fg;
             cell.bg = self.bg;
+            cell.extra = 0;
+            self.apply_pen(row, col + 1);
             self.grid.cursor_col += 1;
         }
 
         // Clamp cursor when auto-wrap is off
-        if !self.auto_wrap && self.grid.cursor_col >= cols {
-            self.grid.cursor_col = cols - 1;
+        if !self.auto_wrap && self.grid.cursor_col > end {
+            self.grid.cursor_col = end;
+        }
+        self.remember_print(row, col);
+    }
+
+    /// Whether the cursor sits just past the right margin after printing
+    /// into its last column, so the next character wraps.
+    fn wrap_pending(&self) -> bool {
+        let col = self.grid.cursor_col;
+        col > self.margin_right
+            && (col >= self.grid.cols() || (col == self.margin_right + 1 && self.joinable_cell().is_some()))
+    }
+
+    /// Whether the cursor is between the left and right margins.
+    fn in_lr_margins(&self) -> bool {
+        let col = self.grid.cursor_col;
+        col >= self.margin_left && (col <= self.margin_right || self.wrap_pending())
+    }
+
+    /// First column of the cursor's line: the left margin unless the
+    /// cursor is left of it.
+    fn line_start(&self) -> usize {
+        if self.grid.cursor_col >= self.margin_left { self.margin_left } else { 0 }
+    }
+
+    /// Last column of the cursor's line: the right margin unless the
+    /// cursor is right of it.
+    fn line_end(&self) -> usize {
+        if self.grid.cursor_col <= self.margin_right || self.wrap_pending() {
+            self.margin_right
+        } else {
+            self.grid.cols() - 1
+        }
+    }
+
+    fn carriage_return(&mut self) {
+        self.grid.cursor_col = self.line_start();
+    }
+
+    /// Set the cell state `Grid::put_char`/`write_ascii` don't take.
+    fn apply_pen(&mut self, row: usize, col: usize) {
+        let cell = self.grid.cell_mut(row, col);
+        cell.underline_color = self.underline_color;
+        cell.link = self.link;
+    }
+
+    fn remember_print(&mut self, row: usize, col: usize) {
+        let cursor = (self.grid.cursor_row, self.grid.cursor_col);
+        self.last_print = Some(LastPrint { row, col, cursor });
+    }
+
+    /// Cell of the last print, if the cursor hasn't moved since.
+    fn joinable_cell(&self) -> Option<(usize, usize)> {
+        let last = self.last_print?;
+        let cursor = (self.grid.cursor_row, self.grid.cursor_col);
+        let in_bounds = last.row < self.grid.rows() && last.col < self.grid.cols();
+        (last.cursor == cursor && in_bounds).then_some((last.row, last.col))
+    }
+
+    /// The previous cluster ends in ZWJ, so the next codepoint belongs to it.
+    fn after_zwj(&self) -> bool {
+        self.joinable_cell().is_some_and(|(row, col)| {
+            let cell = self.grid.cell(row, col);
+            self.grid.grapheme_extra(cell).last() == Some(&'\u{200d}')
+        })
+    }
+
+    /// Mode 2027: a regional indicator following a lone one forms a flag.
+    fn completes_flag(&self, ch: char) -> bool {
+        self.grapheme_clusters && width::is_regional_indicator(ch)
+            && self.joinable_cell().is_some_and(|(row, col)| {
+                let cell = self.grid.cell(row, col);
+                width::is_regional_indicator(cell.ch) && cell.extra == 0
+            })
+    }
+
+    /// Add a combining mark, variation selector, ZWJ or ZWJ-joined
+    /// codepoint to the previous cell's cluster. Dropped if there is none.
+    fn append_to_cluster(&mut self, ch: char) {
+        if let Some((row, col)) = self.joinable_cell() {
+            self.grid.append_to_cell(row, col, ch);
+            if self.grapheme_clusters {
+                self.update_cluster_width(row, col);
+            }
+        }
+    }
+
+    /// Mode 2027: widen or narrow the cell at (row, col) when its cluster's
+    /// width changes (VS16, VS15, flags), if the cursor still sits after it.
+    fn update_cluster_width(&mut self, row: usize, col: usize) {
+        let cell = *self.grid.cell(row, col);
+        let mut text = String::new();
+        self.grid.push_cell_text(&cell, &mut text);
+        let width = width::cluster_width(&text, self.ambiguous_width);
+
+        let cols = self.grid.cols();
+        let has_spacer = col + 1 < cols && self.grid.cell(row, col + 1).ch == '\0';
+        let cursor = (self.grid.cursor_row, self.grid.cursor_col);
+        if width == 2 && !has_spacer && col + 1 < cols && cursor == (row, col + 1) {
+            *self.grid.cell_mut(row, col + 1) = Cell { ch: '\0', extra: 0, ..cell };
+            self.grid.cursor_col += 1;
+        } else if width == 1 && has_spacer && cursor == (row, col + 2) {
+            *self.grid.cell_mut(row, col + 1) = Cell { ch: ' ', extra: 0, ..cell };
+            self.grid.cursor_col -= 1;
         }
+        self.remember_print(row, col);
+    }
+
+    /// Print a run of text, writing ASCII stretches straight into grid rows.
+    fn print_str(&mut self, s: &str) {
+        let mut rest = s;
+        while !rest.is_empty() {
+            let ascii = rest.bytes().position(|b| !b.is_ascii()).unwrap_or(rest.len());
+            let (run, tail) = rest.split_at(ascii);
+            if !run.is_empty() {
+                self.print_ascii(run.as_bytes());
+            }
+            let mut chars = tail.chars();
+            if let Some(ch) = chars.next() {
+                self.print(ch);
+            }
+            rest = chars.as_str();
+        }
+    }
+
+    fn print_ascii(&mut self, mut bytes: &[u8]) {
+        self.intern_pending_link();
+        let margins = self.margin_left > 0 || self.margin_right + 1 < self.grid.cols();
+        if !self.auto_wrap || self.insert_mode || margins {
+            // Overflowing characters all land in the last column, inserting
+            // shifts the line per character, and margins wrap mid-row
+            for &b in bytes {
+                self.print(b as char);
+            }
+            return;
+        }
+        let cols = self.grid.cols();
+        while !bytes.is_empty() {
+            if self.grid.cursor_col >= cols {
+                self.grid.cursor_col = 0;
+                self.index();
+            }
+            let n = bytes.len().min(cols - self.grid.cursor_col);
+            self.grid.write_ascii(&bytes[..n], self.attr, self.fg, self.bg);
+            if self.link != 0 || self.underline_color != CellColor::Default {
+                let (row, end) = (self.grid.cursor_row, self.grid.cursor_col);
+                for col in end - n..end {
+                    self.apply_pen(row, col);
+                }
+            }
+            bytes = &bytes[n..];
+        }
+        self.remember_print(self.grid.cursor_row, self.grid.cursor_col - 1);
     }
 
     fn execute(&mut self, byte: u8) {
         match byte {
-            0x07 => {} // BEL — TODO: visual bell
+            0x07 => self.emit(TerminalEvent::Bell), // BEL
             0x08 => {  // BS
-                if self.grid.cursor_col > 0 {
+                if self.grid.cursor_col > self.line_start() {
                     self.grid.cursor_col -= 1;
                 }
             }
             0x09 => {  // HT (tab)
                 let col = self.grid.cursor_col;
-                let cols = self.grid.cols();
+                let end = self.line_end();
                 let next = self.tab_stops.iter()
                     .enumerate()
                     .skip(col + 1)
                     .find(|(_, &stop)| stop)
                     .map(|(i, _)| i)
-                    .unwrap_or(cols - 1);
-                self.grid.cursor_col = next;
+                    .unwrap_or(end);
+                self.grid.cursor_col = next.min(end);
             }
-            0x0a | 0x0b | 0x0c => self.index(), // LF, VT, FF
-            0x0d => self.grid.cursor_col = 0,     // CR
+            0x0a | 0x0b | 0x0c => {             // LF, VT, FF
+                self.index();
+                if self.newline_mode {
+                    self.carriage_return();
+                }
+            }
+            0x0d => self.carriage_return(),       // CR
+            // 8-bit C1 controls, delivered when the parser recognizes them
+            0x84 => self.esc_dispatch(b'D', &[]), // IND
+            0x85 => self.esc_dispatch(b'E', &[]), // NEL
+            0x88 => self.esc_dispatch(b'H', &[]), // HTS
+            0x8d => self.esc_dispatch(b'M', &[]), // RI
             _ => {}
         }
     }
 
     /// Move cursor down one line, scrolling if at bottom of scroll region.
+    /// Outside the left/right margins the cursor stops there instead.
     fn index(&mut self) {
         if self.grid.cursor_row == self.scroll_bottom {
-            self.grid.scroll_region_up(self.scroll_top, self.scroll_bottom);
+            if self.in_lr_margins() {
+                self.scroll_up();
+            }
         } else if self.grid.cursor_row < self.grid.rows() - 1 {
             self.grid.cursor_row += 1;
         }
@@ -242,13 +612,43 @@ impl Terminal {
     /// Move cursor up one line, scrolling down if at top of scroll region.
     fn reverse_index(&mut self) {
         if self.grid.cursor_row == self.scroll_top {
-            self.grid.scroll_region_down(self.scroll_top, self.scroll_bottom);
+            if self.in_lr_margins() {
+                self.scroll_down();
+            }
         } else if self.grid.cursor_row > 0 {
             self.grid.cursor_row -= 1;
         }
     }
 
-    fn csi_dispatch(&mut self, final_byte: u8, params: &[u16], intermediates: &[u8]) {
+    /// Scroll the region inside the margins up by one line.
+    fn scroll_up(&mut self) {
+        self.grid.scroll_region_up(self.scroll_top, self.scroll_bottom, self.margin_left, self.margin_right);
+    }
+
+    /// Scroll the region inside the margins down by one line.
+    fn scroll_down(&mut self) {
+        self.grid.scroll_region_down(self.scroll_top, self.scroll_bottom, self.margin_left, self.margin_right);
+    }
+
+    /// Cursor column for a 1-based column parameter; relative to the left
+    /// margin and clamped to the right one in origin mode.
+    fn origin_col(&self, col: usize) -> usize {
+        if self.origin_mode {
+            (self.margin_left + col - 1).min(self.margin_right)
+        } else {
+            (col - 1).min(self.grid.cols() - 1)
+        }
+    }
+
+    /// Move the cursor to the home position, the margins' top-left corner
+    /// in origin mode.
+    fn cursor_home(&mut self) {
+        let (row, col) = if self.origin_mode { (self.scroll_top, self.margin_left) } else { (0, 0) };
+        self.grid.cursor_row = row;
+        self.grid.cursor_col = col;
+    }
+
+    fn csi_dispatch(&mut self, final_byte: u8, params: &Params, intermediates: &[u8]) {
         let is_private = intermediates.first() == Some(&b'?');
         let is_space = intermediates.first() == Some(&b' ');
 
@@ -266,58 +666,65 @@ impl Terminal {
             }
             b'C' => { // CUF
                 let n = param(params, 0, 1) as usize;
-                self.grid.cursor_col = (self.grid.cursor_col + n).min(self.grid.cols() - 1);
+                self.grid.cursor_col = (self.grid.cursor_col + n).min(self.line_end());
             }
             b'D' => { // CUB
                 let n = param(params, 0, 1) as usize;
-                self.grid.cursor_col = self.grid.cursor_col.saturating_sub(n);
+                self.grid.cursor_col = self.grid.cursor_col.saturating_sub(n).max(self.line_start());
             }
+            // CNL/CPL return to the line start like CR, the left margin under DECLRMM
             b'E' => { // CNL
                 let n = param(params, 0, 1) as usize;
                 let max = self.grid.rows() - 1;
                 self.grid.cursor_row = (self.grid.cursor_row + n).min(max);
-                self.grid.cursor_col = 0;
+                self.carriage_return();
             }
             b'F' => { // CPL
                 let n = param(params, 0, 1) as usize;
                 self.grid.cursor_row = self.grid.cursor_row.saturating_sub(n);
-                self.grid.cursor_col = 0;
+                self.carriage_return();
             }
             b'G' | b'`' => { // CHA / HPA
                 let col = param(params, 0, 1) as usize;
-                self.grid.cursor_col = (col - 1).min(self.grid.cols() - 1);
+                self.grid.cursor_col = self.origin_col(col);
             }
             b'H' | b'f' => { // CUP / HVP
                 let row = param(params, 0, 1) as usize;
                 let col = param(params, 1, 1) as usize;
-                let offset = if self.origin_mode { self.scroll_top } else { 0 };
-                self.grid.cursor_row = (offset + row - 1).min(self.grid.rows() - 1);
-                self.grid.cursor_col = (col - 1).min(self.grid.cols() - 1);
+                let (offset, bottom) = if self.origin_mode {
+                    (self.scroll_top, self.scroll_bottom)
+                } else {
+                    (0, self.grid.rows() - 1)
+                };
+                self.grid.cursor_row = (offset + row - 1).min(bottom);
+                self.grid.cursor_col = self.origin_col(col);
             }
             b'd' => { // VPA
                 let row = param(params, 0, 1) as usize;
                 self.grid.cursor_row = (row - 1).min(self.grid.rows() - 1);
             }
-            b'I' => { // CHT — Cursor Forward Tabulation
+            b'I' => { // CHT — Cursor Forward Tabulation, stops at the right margin
                 let n = param(params, 0, 1) as usize;
+                let end = self.line_end();
                 for _ in 0..n {
                     let col = self.grid.cursor_col;
                     let next = self.tab_stops.iter()
                         .enumerate().skip(col + 1)
                         .find(|(_, &s)| s).map(|(i, _)| i)
-                        .unwrap_or(self.grid.cols() - 1);
-                    self.grid.cursor_col = next;
+                        .unwrap_or(end);
+                    self.grid.cursor_col = next.min(end);
                 }
             }
-            b'Z' => { // CBT — Cursor Backward Tabulation
+            b'Z' => { // CBT — Cursor Backward Tabulation, stops at the left margin
                 let n = param(params, 0, 1) as usize;
+                let start = self.line_start();
                 for _ in 0..n {
-                    let col = self.grid.cursor_col;
+                    let col = self.grid.cursor_col.min(self.tab_stops.len());
                     let prev = self.tab_stops.iter()
                         .enumerate().rev().skip(self.tab_stops.len() - col)
                         .find(|(_, &s)| s).map(|(i, _)| i)
                         .unwrap_or(0);
-                    self.grid.cursor_col = prev;
+                    self.grid.cursor_col = prev.max(start);
                 }
             }
 
@@ -340,44 +747,55 @@ impl Terminal {
                     _ => {}
                 }
             }
-            b'X' => { // ECH — Erase Characters
+            b'X' => { // ECH — Erase Characters, up to the right margin
                 let n = param(params, 0, 1) as usize;
                 let row = self.grid.cursor_row;
                 let col = self.grid.cursor_col;
-                for c in col..(col + n).min(self.grid.cols()) {
+                let end = (self.line_end() + 1).min(self.grid.cols());
+                for c in col..(col + n).min(end) {
                     *self.grid.cell_mut(row, c) = Cell::default();
                 }
             }
 
-            // Insert/Delete
+            // Insert/Delete — no effect with the cursor outside the left/right margins
             b'L' => {
                 let n = param(params, 0, 1) as usize;
-                self.grid.insert_lines(self.grid.cursor_row, n, self.scroll_bottom);
+                if (self.margin_left..=self.margin_right).contains(&self.grid.cursor_col) {
+                    let (left, right) = (self.margin_left, self.margin_right);
+                    self.grid.insert_lines(self.grid.cursor_row, n, self.scroll_bottom, left, right);
+                }
             }
             b'M' => {
                 let n = param(params, 0, 1) as usize;
-                self.grid.delete_lines(self.grid.cursor_row, n, self.scroll_bottom);
+                if (self.margin_left..=self.margin_right).contains(&self.grid.cursor_col) {
+                    let (left, right) = (self.margin_left, self.margin_right);
+                    self.grid.delete_lines(self.grid.cursor_row, n, self.scroll_bottom, left, right);
+                }
             }
             b'P' => {
                 let n = param(params, 0, 1) as usize;
-                self.grid.delete_chars(n);
+                if (self.margin_left..=self.margin_right).contains(&self.grid.cursor_col) {
+                    self.grid.delete_chars(n, self.margin_right);
+                }
             }
             b'@' => {
                 let n = param(params, 0, 1) as usize;
-                self.grid.insert_chars(n);
+                if (self.margin_left..=self.margin_right).contains(&self.grid.cursor_col) {
+                    self.grid.insert_chars(n, self.margin_right);
+                }
             }
 
             // Scroll
             b'S' if !is_private => {
                 let n = param(params, 0, 1) as usize;
                 for _ in 0..n {
-                    self.grid.scroll_region_up(self.scroll_top, self.scroll_bottom);
+                    self.scroll_up();
                 }
             }
             b'T' => {
                 let n = param(params, 0, 1) as usize;
                 for _ in 0..n {
-                    self.grid.scroll_region_down(self.scroll_top, self.scroll_bottom);
+                    self.scroll_down();
                 }
             }
 
@@ -392,6 +810,12 @@ impl Terminal {
                 }
             }
 
+            // XTMODKEYS — only modifyOtherKeys (resource 4) is supported
+            b'm' if intermediates.first() == Some(&b'>') && param(params, 0, 0) == 4 => {
+                self.modify_other_keys = param(params, 1, 0).min(2) as u8;
+            }
+            b'm' if intermediates.first() == Some(&b'>') => {}
+
             // SGR
             b'm' => self.handle_sgr(params),
 
@@ -401,8 +825,24 @@ impl Terminal {
                 let bottom = param(params, 1, self.grid.rows() as u16) as usize;
                 self.scroll_top = (top - 1).min(self.grid.rows() - 1);
                 self.scroll_bottom = (bottom - 1).min(self.grid.rows() - 1);
-                self.grid.cursor_row = if self.origin_mode { self.scroll_top } else { 0 };
-                self.grid.cursor_col = 0;
+                self.cursor_home();
+            }
+
+            // DECSLRM — Set Left/Right Margins, while DECLRMM is set
+            b's' if !is_private && self.lr_margin_mode => {
+                let cols = self.grid.cols();
+                let left = param(params, 0, 1) as usize;
+                let right = (param(params, 1, cols as u16) as usize).min(cols);
+                if left < right {
+                    self.margin_left = left - 1;
+                    self.margin_right = right - 1;
+                    self.cursor_home();
+                }
+            }
+
+            // DECRQM — Request Mode (CSI ? Ps $ p / CSI Ps $ p)
+            b'p' if intermediates.last() == Some(&b'$') => {
+                self.report_mode(param(params, 0, 0), is_private);
             }
 
             // DEC Private modes
@@ -418,27 +858,55 @@ impl Terminal {
                 self.saved_attr = self.attr;
                 self.saved_fg = self.fg;
                 self.saved_bg = self.bg;
+                self.saved_underline_color = self.underline_color;
             }
-            b'u' => {
+            // Kitty keyboard protocol: push, pop, set and query the flags
+            b'u' if intermediates.first() == Some(&b'>') => {
+                let flags = KeyboardFlags::from_bits_truncate(param(params, 0, 0) as u8);
+                self.keyboard_stack().push(flags);
+            }
+            b'u' if intermediates.first() == Some(&b'<') => {
+                let n = param(params, 0, 1) as usize;
+                self.keyboard_stack().pop(n);
+            }
+            b'u' if intermediates.first() == Some(&b'=') => {
+                let flags = KeyboardFlags::from_bits_truncate(param(params, 0, 0) as u8);
+                let mode = param(params, 1, 1);
+                self.keyboard_stack().set(flags, mode);
+            }
+            b'u' if is_private => {
+                let flags = self.keyboard_flags().bits();
+                self.reply(b'[', &format!("?{}u", flags));
+            }
+            b'u' if intermediates.is_empty() => {
                 let (r, c) = self.saved_cursor;
                 self.grid.cursor_row = r.min(self.grid.rows() - 1);
                 self.grid.cursor_col = c.min(self.grid.cols() - 1);
                 self.attr = self.saved_attr;
                 self.fg = self.saved_fg;
                 self.bg = self.saved_bg;
+                self.underline_color = self.saved_underline_color;
             }
 
+            // XTWINOPS — window manipulation, size reports, title stack
+            b't' if intermediates.is_empty() => self.window_op(params),
+
+            // XTMODKEYS reset: `CSI > 4 n` turns modifyOtherKeys off
+            b'n' if intermediates.first() == Some(&b'>') && param(params, 0, 0) == 4 => {
+                self.modify_other_keys = 0;
+            }
+            b'n' if intermediates.first() == Some(&b'>') => {}
+
             // DSR — Device Status Report
             b'n' if !is_private => {
                 match param(params, 0, 0) {
                     5 => { // Status report — "OK"
-                        self.write_back.extend_from_slice(b"\x1b[0n");
+                        self.reply(b'[', "0n");
                     }
                     6 => { // CPR — Cursor Position Report
                         let r = self.grid.cursor_row + 1;
                         let c = self.grid.cursor_col + 1;
-                        let resp = format!("\x1b[{};{}R", r, c);
-                        self.write_back.extend_from_slice(resp.as_bytes());
+                        self.reply(b'[', &format!("{};{}R", r, c));
                     }
                     _ => {}
                 }
@@ -448,8 +916,7 @@ impl Terminal {
                     6 => { // DECXCPR
                         let r = self.grid.cursor_row + 1;
                         let c = self.grid.cursor_col + 1;
-                        let resp = format!("\x1b[?{};{}R", r, c);
-                        self.write_back.extend_from_slice(resp.as_bytes());
+                        self.reply(b'[', &format!("?{};{}R", r, c));
                     }
                     _ => {}
                 }
@@ -459,7 +926,7 @@ impl Terminal {
             b'c' if !is_private => {
                 if param(params, 0, 0) == 0 {
                     // Report as VT220
-                    self.write_back.extend_from_slice(b"\x1b[?62;22c");
+                    self.reply(b'[', "?62;22c");
                 }
             }
 
@@ -484,13 +951,22 @@ impl Terminal {
                 // 0,1 = block blink, 2 = block steady, 3 = underline blink,
                 // 4 = underline steady, 5 = bar blink, 6 = bar steady
                 // TODO: pass to renderer
+                let style = param(params, 0, 0);
+                if style <= 6 {
+                    self.cursor_style = style;
+                }
+            }
+
+            // XTVERSION — report name and version (CSI > q)
+            b'q' if intermediates.first() == Some(&b'>') && param(params, 0, 0) == 0 => {
+                self.reply_string(b'P', &format!(">|term {}", crate::VERSION));
             }
 
             _ => {} // Unhandled CSI
         }
     }
 
-    fn handle_sgr(&mut self, params: &[u16]) {
+    fn handle_sgr(&mut self, params: &Params) {
         if params.is_empty() {
             self.sgr_reset();
             return;
@@ -498,41 +974,59 @@ impl Terminal {
 
         let mut i = 0;
         while i < params.len() {
-            match params[i] {
+            let group = params.get(i).unwrap_or(&[0]);
+            match group[0] {
                 0 => self.sgr_reset(),
                 1 => self.attr.insert(CellAttr::BOLD),
                 2 => self.attr.insert(CellAttr::DIM),
                 3 => self.attr.insert(CellAttr::ITALIC),
-                4 => self.attr.insert(CellAttr::UNDERLINE),
+                // 4:0 turns underline off; 4:1..4:5 select a style
+                4 => self.attr.set_underline(match group.get(1) {
+                    Some(0) => None,
+                    Some(2) => Some(UnderlineStyle::Double),
+                    Some(3) => Some(UnderlineStyle::Curly),
+                    Some(4) => Some(UnderlineStyle::Dotted),
+                    Some(5) => Some(UnderlineStyle::Dashed),
+                    _ => Some(UnderlineStyle::Single),
+                }),
                 7 => self.attr.insert(CellAttr::INVERSE),
                 8 => self.attr.insert(CellAttr::HIDDEN),
                 9 => self.attr.insert(CellAttr::STRIKETHROUGH),
+                21 => self.attr.set_underline(Some(UnderlineStyle::Double)),
                 22 => { self.attr.remove(CellAttr::BOLD); self.attr.remove(CellAttr::DIM); }
                 23 => self.attr.remove(CellAttr::ITALIC),
-                24 => self.attr.remove(CellAttr::UNDERLINE),
+                24 => self.attr.set_underline(None),
                 27 => self.attr.remove(CellAttr::INVERSE),
                 28 => self.attr.remove(CellAttr::HIDDEN),
                 29 => self.attr.remove(CellAttr::STRIKETHROUGH),
                 // Foreground colors
-                30..=37 => self.fg = ANSI_COLORS[(params[i] - 30) as usize],
+                n @ 30..=37 => self.fg = CellColor::Indexed((n - 30) as u8),
                 38 => {
-                    if let Some((color, skip)) = parse_extended_color(params, i + 1) {
+                    if let Some((color, skip)) = parse_extended_color(params, i) {
                         self.fg = color;
                         i += skip;
                     }
                 }
-                39 => self.fg = Color::DEFAULT_FG,
-                90..=97 => self.fg = ANSI_COLORS[(params[i] - 90 + 8) as usize],
+                39 => self.fg = CellColor::Default,
+                n @ 90..=97 => self.fg = CellColor::Indexed((n - 90 + 8) as u8),
                 // Background colors
-                40..=47 => self.bg = ANSI_COLORS[(params[i] - 40) as usize],
+                n @ 40..=47 => self.bg = CellColor::Indexed((n - 40) as u8),
                 48 => {
-                    if let Some((color, skip)) = parse_extended_color(params, i + 1) {
+                    if let Some((color, skip)) = parse_extended_color(params, i) {
                         self.bg = color;
                         i += skip;
                     }
                 }
-                49 => self.bg = Color::DEFAULT_BG,
-                100..=107 => self.bg = ANSI_COLORS[(params[i] - 100 + 8) as usize],
+                49 => self.bg = CellColor::Default,
+                n @ 100..=107 => self.bg = CellColor::Indexed((n - 100 + 8) as u8),
+                // Underline color
+                58 => {
+                    if let Some((color, skip)) = parse_extended_color(params, i) {
+                        self.underline_color = color;
+                        i += skip;
+                    }
+                }
+                59 => self.underline_color = CellColor::Default,
                 _ => {}
             }
             i += 1;
@@ -541,15 +1035,91 @@ impl Terminal {
 
     fn sgr_reset(&mut self) {
         self.attr = CellAttr::empty();
-        self.fg = Color::DEFAULT_FG;
-        self.bg = Color::DEFAULT_BG;
+        self.fg = CellColor::Default;
+        self.bg = CellColor::Default;
+        self.underline_color = CellColor::Default;
+    }
+
+    /// XTWINOPS. Size reports and the title stack are always answered;
+    /// everything that changes the window, or reads the title back, goes
+    /// through `window_op_policy`.
+    fn window_op(&mut self, params: &Params) {
+        let arg = |idx| param(params, idx, 0);
+        let (rows, cols) = (self.grid.rows(), self.grid.cols());
+        let (cell_width, cell_height) = self.cell_pixels;
+        let op = match param(params, 0, 0) {
+            1 => WindowOp::Deiconify,
+            2 => WindowOp::Iconify,
+            3 => WindowOp::Move { x: arg(1), y: arg(2) },
+            4 => WindowOp::ResizePixels { width: arg(2), height: arg(1) },
+            5 => WindowOp::Raise,
+            6 => WindowOp::Lower,
+            7 => WindowOp::Refresh,
+            8 => WindowOp::ResizeCells { cols: arg(2), rows: arg(1) },
+            9 => WindowOp::Maximize(arg(1)),
+            10 => WindowOp::Fullscreen(arg(1)),
+            20 | 21 => WindowOp::ReportTitle,
+            // Not iconified
+            11 => return self.reply(b'[', "1t"),
+            // Text area in pixels. Answered even without cell metrics (as
+            // 0;0) so applications waiting for the reply don't hang.
+            14 => {
+                let (height, width) = (rows * cell_height as usize, cols * cell_width as usize);
+                return self.reply(b'[', &format!("4;{};{}t", height, width));
+            }
+            16 => return self.reply(b'[', &format!("6;{};{}t", cell_height, cell_width)),
+            18 => return self.reply(b'[', &format!("8;{};{}t", rows, cols)),
+            19 => return self.reply(b'[', &format!("9;{};{}t", rows, cols)),
+            22 => {
+                if self.title_stack.len() >= MAX_TITLE_STACK {
+                    self.title_stack.remove(0);
+                }
+                self.title_stack.push(self.title.clone());
+                return;
+            }
+            23 => {
+                if let Some(title) = self.title_stack.pop() {
+                    self.set_title(title);
+                }
+                return;
+            }
+            // DECSLPP — lines per page
+            n @ 24.. => WindowOp::ResizeCells { cols: 0, rows: n },
+            _ => return,
+        };
+        if !(self.window_op_policy)(&op) {
+            return;
+        }
+        if op == WindowOp::ReportTitle {
+            let label = if param(params, 0, 0) == 20 { 'L' } else { 'l' };
+            let title = crate::security::sanitize_osc(self.title.as_bytes());
+            return self.reply_string(b']', &format!("{}{}", label, title));
+        }
+        self.emit(TerminalEvent::WindowOp(op));
+    }
+
+    /// Resetting an encoding other than the current one leaves it alone.
+    fn set_mouse_encoding(&mut self, encoding: MouseEncoding, enable: bool) {
+        if enable {
+            self.mouse_encoding = encoding;
+        } else if self.mouse_encoding == encoding {
+            self.mouse_encoding = MouseEncoding::X10;
+        }
     }
 
-    fn set_dec_mode(&mut self, params: &[u16], enable: bool) {
-        for &p in params {
+    fn set_dec_mode(&mut self, params: &Params, enable: bool) {
+        for p in params.iter().map(|g| g[0]) {
+            let before = self.mode_state(p, true);
             match p {
                 1 => self.cursor_keys_app = enable,  // DECCKM
                 6 => self.origin_mode = enable,       // DECOM
+                69 => {                               // DECLRMM
+                    self.lr_margin_mode = enable;
+                    if !enable {
+                        self.margin_left = 0;
+                        self.margin_right = self.grid.cols() - 1;
+                    }
+                }
                 7 => self.auto_wrap = enable,         // DECAWM
                 12 => {}                              // Cursor blink (renderer)
                 25 => self.cursor_visible = enable,   // DECTCEM
@@ -557,7 +1127,12 @@ impl Terminal {
                 1000 => self.mouse_mode = if enable { MouseMode::Normal } else { MouseMode::Off },
                 1002 => self.mouse_mode = if enable { MouseMode::Button } else { MouseMode::Off },
                 1003 => self.mouse_mode = if enable { MouseMode::Any } else { MouseMode::Off },
-                1006 => self.mouse_encoding = if enable { MouseEncoding::Sgr } else { MouseEncoding::X10 },
+                1004 => self.focus_reporting = enable,
+                1005 => self.set_mouse_encoding(MouseEncoding::Utf8, enable),
+                1006 => self.set_mouse_encoding(MouseEncoding::Sgr, enable),
+                1007 => self.alternate_scroll = enable,
+                1015 => self.set_mouse_encoding(MouseEncoding::Urxvt, enable),
+                1016 => self.set_mouse_encoding(MouseEncoding::SgrPixels, enable),
                 47 => { // Alt screen (no save/restore cursor)
                     if enable {
                         let (cols, rows) = (self.grid.cols(), self.grid.rows());
@@ -582,6 +1157,7 @@ impl Terminal {
                         self.saved_attr = self.attr;
                         self.saved_fg = self.fg;
                         self.saved_bg = self.bg;
+                        self.saved_underline_color = self.underline_color;
                     } else {
                         let (r, c) = self.saved_cursor;
                         self.grid.cursor_row = r.min(self.grid.rows() - 1);
@@ -589,6 +1165,7 @@ impl Terminal {
                         self.attr = self.saved_attr;
                         self.fg = self.saved_fg;
                         self.bg = self.saved_bg;
+                        self.underline_color = self.saved_underline_color;
                     }
                 }
                 1049 => { // Alt screen + save/restore cursor
@@ -598,6 +1175,7 @@ impl Terminal {
                         self.saved_attr = self.attr;
                         self.saved_fg = self.fg;
                         self.saved_bg = self.bg;
+                        self.saved_underline_color = self.underline_color;
                         let old = std::mem::replace(&mut self.grid, Grid::new(cols, rows));
                         self.alt_grid = Some(old);
                     } else if let Some(main) = self.alt_grid.take() {
@@ -608,21 +1186,56 @@ impl Terminal {
                         self.attr = self.saved_attr;
                         self.fg = self.saved_fg;
                         self.bg = self.saved_bg;
+                        self.underline_color = self.saved_underline_color;
                     }
                 }
                 2004 => self.bracketed_paste = enable,
+                2026 => { // Synchronized output (BSU/ESU)
+                    if !enable {
+                        self.sync_update = None;
+                    } else if self.sync_update.is_none() {
+                        // A repeated BSU doesn't extend the timeout
+                        self.sync_update = Some(Instant::now());
+                    }
+                }
+                2027 => self.grapheme_clusters = enable, // Grapheme cluster width
                 _ => {}
             }
+            self.note_mode_change(p, true, before);
         }
     }
 
-    fn set_ansi_mode(&mut self, params: &[u16], _enable: bool) {
-        for &p in params {
+    /// State of a mode listed in `DEC_MODES`/`ANSI_MODES`.
+    fn mode_state(&self, mode: u16, dec: bool) -> Option<bool> {
+        let table = if dec { DEC_MODES } else { ANSI_MODES };
+        table.iter().find(|(m, _)| *m == mode).map(|(_, get)| get(self))
+    }
+
+    /// Raise `ModeChanged` if setting `mode` flipped it.
+    fn note_mode_change(&mut self, mode: u16, dec: bool, before: Option<bool>) {
+        if let (Some(was), Some(enabled)) = (before, self.mode_state(mode, dec)) {
+            if was != enabled {
+                self.emit(TerminalEvent::ModeChanged { mode, private: dec, enabled });
+            }
+        }
+    }
+
+    /// Answer DECRQM with DECRPM: 0 = not recognized, 1 = set, 2 = reset.
+    fn report_mode(&mut self, mode: u16, dec: bool) {
+        let state = self.mode_state(mode, dec).map_or(0, |on| if on { 1 } else { 2 });
+        let marker = if dec { "?" } else { "" };
+        self.reply(b'[', &format!("{}{};{}$y", marker, mode, state));
+    }
+
+    fn set_ansi_mode(&mut self, params: &Params, enable: bool) {
+        for p in params.iter().map(|g| g[0]) {
+            let before = self.mode_state(p, false);
             match p {
-                4 => {} // IRM — Insert/Replace mode (TODO)
-                20 => {} // LNM — Line feed/new line mode
+                4 => self.insert_mode = enable,   // IRM — Insert/Replace mode
+                20 => self.newline_mode = enable, // LNM — Line feed/new line mode
                 _ => {}
             }
+            self.note_mode_change(p, false, before);
         }
     }
 
@@ -635,9 +1248,10 @@ impl Terminal {
                         for c in 0..self.grid.cols() {
                             let cell = self.grid.cell_mut(r, c);
                             cell.ch = 'E';
+                            cell.extra = 0;
                             cell.attr = CellAttr::empty();
-                            cell.fg = Color::DEFAULT_FG;
-                            cell.bg = Color::DEFAULT_BG;
+                            cell.fg = CellColor::Default;
+                            cell.bg = CellColor::Default;
                         }
                     }
                 }
@@ -646,12 +1260,22 @@ impl Terminal {
             return;
         }
 
+        if intermediates == b" " {
+            match final_byte {
+                b'F' => self.c1_responses = false, // S7C1T
+                b'G' => self.c1_responses = true,  // S8C1T
+                _ => {}
+            }
+            return;
+        }
+
         match final_byte {
             b'7' => { // DECSC — Save Cursor + attrs
                 self.saved_cursor = (self.grid.cursor_row, self.grid.cursor_col);
                 self.saved_attr = self.attr;
                 self.saved_fg = self.fg;
                 self.saved_bg = self.bg;
+                self.saved_underline_color = self.underline_color;
             }
             b'8' => { // DECRC — Restore Cursor + attrs
                 let (r, c) = self.saved_cursor;
@@ -660,11 +1284,12 @@ impl Terminal {
                 self.attr = self.saved_attr;
                 self.fg = self.saved_fg;
                 self.bg = self.saved_bg;
+                self.underline_color = self.saved_underline_color;
             }
             b'M' => self.reverse_index(),
             b'D' => self.index(),
             b'E' => {
-                self.grid.cursor_col = 0;
+                self.carriage_return();
                 self.index();
             }
             b'H' => { // HTS — Horizontal Tab Set
@@ -678,98 +1303,601 @@ impl Terminal {
             b'c' => { // RIS — Full Reset
                 let cols = self.grid.cols();
                 let rows = self.grid.rows();
+                // Keep the theme, drop colors the application changed
+                let theme_palette = std::mem::take(&mut self.theme_palette);
+                let (focused, ambiguous_width) = (self.focused, self.ambiguous_width);
+                let (cell_pixels, window_op_policy) = (self.cell_pixels, self.window_op_policy);
+                let clipboard = (self.clipboard_write, self.clipboard_read, self.clipboard_max_bytes);
+                let events = (std::mem::take(&mut self.events), self.event_bytes);
+                let titled = !self.title.is_empty();
                 *self = Terminal::new(cols, rows);
+                (self.events, self.event_bytes) = events;
+                if titled {
+                    self.emit(TerminalEvent::TitleChanged(String::new()));
+                }
+                self.palette = theme_palette.clone();
+                self.theme_palette = theme_palette;
+                self.focused = focused;
+                self.ambiguous_width = ambiguous_width;
+                self.cell_pixels = cell_pixels;
+                self.window_op_policy = window_op_policy;
+                (self.clipboard_write, self.clipboard_read, self.clipboard_max_bytes) = clipboard;
             }
             _ => {}
         }
     }
 
-    fn osc_dispatch(&mut self, data: &[u8]) {
+    fn osc_dispatch(&mut self, data: &[u8], bell_terminated: bool) {
         let s = String::from_utf8_lossy(data);
+        // Dynamic colors: OSC 4/10/11/12 set or query, 104/110/111/112 reset
+        let (cmd, rest) = s.split_once(';').unwrap_or((&s, ""));
+        match cmd {
+            "4" => self.osc_palette(rest, bell_terminated),
+            "10" | "11" | "12" => self.osc_dynamic_colors(cmd.parse().unwrap_or(10), rest, bell_terminated),
+            "104" => self.osc_reset_palette(rest),
+            "110" => self.palette.fg = self.theme_palette.fg,
+            "111" => self.palette.bg = self.theme_palette.bg,
+            "112" => self.palette.cursor = self.theme_palette.cursor,
+            _ => {}
+        }
         if let Some(rest) = s.strip_prefix("0;").or_else(|| s.strip_prefix("2;")) {
-            self.title = rest.to_string();
+            self.set_title(rest.to_string());
+        }
+        // OSC 8 — hyperlink: 8;params;URI, empty URI ends the link
+        if let Some(rest) = s.strip_prefix("8;") {
+            if let Some((params, uri)) = rest.split_once(';') {
+                self.set_hyperlink(params, uri);
+            }
         }
         // OSC 7 — working directory
-        if s.starts_with("7;") {
-            if let Some(url) = s.strip_prefix("7;") {
-                self.osc7_cwd = Some(url.to_string());
-                self.shell.handle_osc7(url);
+        if let Some(url) = s.strip_prefix("7;") {
+            let before = self.shell.working_dir.clone();
+            self.shell.handle_osc7(url);
+            if self.shell.working_dir != before {
+                self.emit(TerminalEvent::CwdChanged(self.shell.working_dir.clone()));
             }
         }
         // OSC 133 — shell integration (FinalTerm)
         if let Some(rest) = s.strip_prefix("133;") {
-            self.osc133_data = Some(rest.to_string());
             self.shell.handle_osc133(rest, self.grid.cursor_row);
+            match rest.chars().next() {
+                Some('C') => self.emit(TerminalEvent::CommandStarted),
+                Some('D') => {
+                    let last = self.shell.history().last();
+                    let (exit_code, duration) = last.map_or((None, None), |c| (c.exit_code, c.duration));
+                    self.emit(TerminalEvent::CommandFinished { exit_code, duration });
+                }
+                _ => {}
+            }
+        }
+        // OSC 52 — clipboard: 52;targets;base64, `?` to query, anything
+        // else to clear
+        if let Some((selection, request)) = crate::clipboard::parse_osc52(&s) {
+            let writable = self.clipboard_write != ClipboardAccess::Off;
+            match request {
+                Osc52::Query if self.clipboard_read != ClipboardAccess::Off => {
+                    self.emit(TerminalEvent::ClipboardLoadRequest { selection });
+                }
+                Osc52::Set(text) if writable && text.len() <= self.clipboard_max_bytes => {
+                    self.emit(TerminalEvent::ClipboardStore { selection, text });
+                }
+                Osc52::Clear if writable => {
+                    self.emit(TerminalEvent::ClipboardStore { selection, text: String::new() });
+                }
+                _ => {}
+            }
+        }
+        // OSC 9 (iTerm2) / OSC 777 (rxvt) — desktop notification. ConEmu's
+        // numbered OSC 9 subcommands (`9;4;...` progress) are not messages.
+        if let Some(body) = s.strip_prefix("9;") {
+            let subcommand = body.split_once(';').is_some_and(|(n, _)| n.parse::<u8>().is_ok());
+            if !subcommand {
+                self.emit(TerminalEvent::Notification { title: String::new(), body: body.to_string() });
+            }
+        }
+        if let Some(rest) = s.strip_prefix("777;notify;") {
+            let (title, body) = rest.split_once(';').unwrap_or((rest, ""));
+            self.emit(TerminalEvent::Notification { title: title.to_string(), body: body.to_string() });
+        }
+    }
+
+    fn set_title(&mut self, title: String) {
+        self.title = title.clone();
+        self.emit(TerminalEvent::TitleChanged(title));
+    }
+
+    /// Queue an event, dropping the oldest if nobody is draining them.
+    fn emit(&mut self, event: TerminalEvent) {
+        // A newer title, or a repeat of the same color query, supersedes
+        // the event before it
+        let superseded = match (self.events.back(), &event) {
+            (Some(TerminalEvent::TitleChanged(_)), TerminalEvent::TitleChanged(_)) => true,
+            (Some(last @ TerminalEvent::ColorQuery { .. }), TerminalEvent::ColorQuery { .. }) => *last == event,
+            _ => false,
+        };
+        if superseded {
+            if let Some(last) = self.events.pop_back() {
+                self.event_bytes -= last.payload_len();
+            }
+        }
+        self.event_bytes += event.payload_len();
+        self.events.push_back(event);
+        while self.events.len() > MAX_EVENTS || self.event_bytes > MAX_EVENT_BYTES {
+            self.next_event();
+        }
+    }
+
+    /// Take the events raised since the last call, oldest first.
+    pub fn drain_events(&mut self) -> std::collections::vec_deque::Drain<'_, TerminalEvent> {
+        self.event_bytes = 0;
+        self.events.drain(..)
+    }
+
+    /// Take the oldest pending event.
+    pub fn next_event(&mut self) -> Option<TerminalEvent> {
+        let event = self.events.pop_front()?;
+        self.event_bytes -= event.payload_len();
+        Some(event)
+    }
+
+    /// OSC 4: `index;spec` pairs. A `?` spec queries the entry.
+    fn osc_palette(&mut self, args: &str, bell_terminated: bool) {
+        let mut args = args.split(';');
+        while let (Some(idx), Some(spec)) = (args.next(), args.next()) {
+            let Ok(idx) = idx.parse::<u8>() else { continue };
+            if spec == "?" {
+                let color = palette::format_color_spec(self.palette.colors[idx as usize]);
+                self.reply_osc(&format!("4;{idx};{color}"), bell_terminated);
+                self.emit(TerminalEvent::ColorQuery { osc: 4, index: Some(idx) });
+            } else if let Some(color) = palette::parse_color_spec(spec) {
+                self.palette.colors[idx as usize] = color;
+            }
+        }
+    }
+
+    /// OSC 10/11/12: foreground, background, cursor color. Further specs
+    /// apply to the following slots, as in xterm (`10;fg;bg`).
+    fn osc_dynamic_colors(&mut self, first: u8, args: &str, bell_terminated: bool) {
+        for (slot, spec) in (first..=12).zip(args.split(';')) {
+            let color = match slot {
+                10 => &mut self.palette.fg,
+                11 => &mut self.palette.bg,
+                _ => &mut self.palette.cursor,
+            };
+            if spec == "?" {
+                let color = palette::format_color_spec(*color);
+                self.reply_osc(&format!("{slot};{color}"), bell_terminated);
+                self.emit(TerminalEvent::ColorQuery { osc: slot as u16, index: None });
+            } else if let Some(c) = palette::parse_color_spec(spec) {
+                *color = c;
+            }
+        }
+    }
+
+    /// OSC 104: reset the listed palette entries, or all of them.
+    fn osc_reset_palette(&mut self, args: &str) {
+        if args.is_empty() {
+            self.palette.colors = self.theme_palette.colors;
+            return;
+        }
+        for idx in args.split(';').filter_map(|i| i.parse::<u8>().ok()) {
+            self.palette.colors[idx as usize] = self.theme_palette.colors[idx as usize];
+        }
+    }
+
+    /// Start (or end, for an empty URI) the link applied to printed cells.
+    /// URIs that fail `security::is_safe_url` or are too long end the
+    /// current link instead. The link enters the table on the next print,
+    /// so opening links without printing anything costs no slots.
+    fn set_hyperlink(&mut self, params: &str, uri: &str) {
+        self.link = 0;
+        self.pending_link = None;
+        let oversized = uri.len() > HyperlinkTable::MAX_URI_LEN || params.len() > HyperlinkTable::MAX_PARAMS_LEN;
+        if uri.is_empty() || oversized || !crate::security::is_safe_url(uri) {
+            return;
+        }
+        self.pending_link = Some((params.to_string(), uri.to_string()));
+    }
+
+    /// Add the pending OSC 8 link to the table, collecting unreferenced
+    /// links first if it is full.
+    fn intern_pending_link(&mut self) {
+        let Some((params, uri)) = self.pending_link.take() else { return };
+        if self.hyperlinks.is_full() {
+            self.collect_hyperlinks();
+        }
+        self.link = self.hyperlinks.insert(&params, &uri);
+    }
+
+    /// Free links no longer referenced by any cell, on either screen or in
+    /// scrollback.
+    fn collect_hyperlinks(&mut self) {
+        let mut live = vec![false; HyperlinkTable::MAX_LINKS + 1];
+        for grid in std::iter::once(&self.grid).chain(self.alt_grid.as_ref()) {
+            let rows = (0..grid.rows()).map(|r| grid.row(r));
+            for row in rows.chain(grid.scrollback().iter().map(Vec::as_slice)) {
+                for cell in row {
+                    live[cell.link as usize] = true;
+                }
+            }
+        }
+        self.hyperlinks.retain(&live);
+    }
+
+    /// Start of a DCS string: collect DECRQSS and XTGETTCAP requests,
+    /// ignore everything else (Sixel, tmux passthrough, ...).
+    fn hook(&mut self, _params: &Params, intermediates: &[u8], final_byte: u8) {
+        let request = match (intermediates, final_byte) {
+            (b"$", b'q') => Some(DcsRequest::StatusString),
+            (b"+", b'q') => Some(DcsRequest::Termcap),
+            _ => None,
+        };
+        self.dcs_request = request.map(|r| (r, Vec::new()));
+    }
+
+    fn put(&mut self, byte: u8) {
+        if let Some((_, data)) = &mut self.dcs_request {
+            if data.len() < MAX_DCS_REQUEST {
+                data.push(byte);
+            } else {
+                self.dcs_request = None;
+            }
         }
-        // OSC 52 — clipboard
-        if s.starts_with("52;") {
-            self.osc52_data = Some(s.to_string());
+    }
+
+    fn unhook(&mut self) {
+        match self.dcs_request.take() {
+            Some((DcsRequest::StatusString, data)) => self.report_status_string(&data),
+            Some((DcsRequest::Termcap, data)) => self.report_termcaps(&data),
+            None => {}
         }
     }
 
-    pub fn set_default_colors(&mut self, fg: Color, bg: Color) {
-        self.fg = fg;
-        self.bg = bg;
+    /// Answer DECRQSS: `DCS 1 $ r Pt ST` with the control that restores
+    /// the setting, or `DCS 0 $ r ST` for settings we don't report.
+    fn report_status_string(&mut self, request: &[u8]) {
+        let setting = match request {
+            b"m" => Some(format!("{}m", self.sgr_string())),
+            b"r" => Some(format!("{};{}r", self.scroll_top + 1, self.scroll_bottom + 1)),
+            b"s" => Some(format!("{};{}s", self.margin_left + 1, self.margin_right + 1)),
+            b" q" => Some(format!("{} q", self.cursor_style.max(1))),
+            _ => None,
+        };
+        match setting {
+            Some(setting) => self.reply_string(b'P', &format!("1$r{}", setting)),
+            None => self.reply_string(b'P', "0$r"),
+        }
+    }
+
+    /// SGR parameters that recreate the current pen from a reset.
+    fn sgr_string(&self) -> String {
+        let mut sgr = vec!["0".to_string()];
+        let flags = [
+            (CellAttr::BOLD, "1"), (CellAttr::DIM, "2"), (CellAttr::ITALIC, "3"),
+            (CellAttr::INVERSE, "7"), (CellAttr::HIDDEN, "8"), (CellAttr::STRIKETHROUGH, "9"),
+        ];
+        for (flag, code) in flags {
+            if self.attr.contains(flag) {
+                sgr.push(code.to_string());
+            }
+        }
+        match self.attr.underline() {
+            Some(UnderlineStyle::Single) => sgr.push("4".to_string()),
+            Some(UnderlineStyle::Double) => sgr.push("4:2".to_string()),
+            Some(UnderlineStyle::Curly) => sgr.push("4:3".to_string()),
+            Some(UnderlineStyle::Dotted) => sgr.push("4:4".to_string()),
+            Some(UnderlineStyle::Dashed) => sgr.push("4:5".to_string()),
+            None => {}
+        }
+        // (code for colors 0–7, code for 8–15, extended color introducer)
+        let color = |(normal, bright, ext): (u16, u16, u16), color: CellColor| match color {
+            CellColor::Default => None,
+            CellColor::Indexed(n) if n < 8 && normal != 0 => Some((normal + n as u16).to_string()),
+            CellColor::Indexed(n) if n < 16 && bright != 0 => Some((bright + n as u16 - 8).to_string()),
+            CellColor::Indexed(n) => Some(format!("{}:5:{}", ext, n)),
+            CellColor::Rgb(c) => Some(format!("{}:2::{}:{}:{}", ext, c.r, c.g, c.b)),
+        };
+        sgr.extend(color((30, 90, 38), self.fg));
+        sgr.extend(color((40, 100, 48), self.bg));
+        // Underline color has no short form
+        sgr.extend(color((0, 0, 58), self.underline_color));
+        sgr.join(";")
+    }
+
+    /// Answer XTGETTCAP: one `DCS 1 + r name=value ST` per known
+    /// capability, names and values hex-encoded; an unknown name gets
+    /// `DCS 0 + r name ST`. A name that is not hex is not echoed, so
+    /// the reply can't carry arbitrary bytes to the application.
+    fn report_termcaps(&mut self, request: &[u8]) {
+        for hex_name in request.split(|&b| b == b';') {
+            let Some(name) = hex_decode(hex_name) else {
+                self.reply_string(b'P', "0+r");
+                continue;
+            };
+            let known = TERMCAPS.iter().find(|(cap, _)| *cap == name);
+            let hex_name = String::from_utf8_lossy(hex_name).into_owned();
+            match known {
+                Some((_, Some(value))) => {
+                    self.reply_string(b'P', &format!("1+r{}={}", hex_name, hex_encode(value)));
+                }
+                Some((_, None)) => self.reply_string(b'P', &format!("1+r{}", hex_name)),
+                None => self.reply_string(b'P', &format!("0+r{}", hex_name)),
+            }
+        }
+    }
+
+    /// APC strings: only Kitty graphics (`G...`) is recognized.
+    fn apc_dispatch(&mut self, data: &[u8]) {
+        use crate::image::{parse_kitty_graphics, parse_kitty_control, kitty_pixels_to_rgba, KittyCommand};
+
+        let Some(payload) = data.strip_prefix(b"G") else { return };
+        let payload = String::from_utf8_lossy(payload);
+        let Some(cmd) = parse_kitty_graphics(&payload) else { return };
+        let (ctl, b64) = parse_kitty_control(&payload);
+
+        let result = match cmd {
+            KittyCommand::Query => match ctl.format {
+                24 | 32 => Ok(()),
+                _ => Err("EINVAL:unsupported format"),
+            },
+            // Deletes never reply. Only all (`d=a`) and by id (`d=i`) are
+            // supported; other targets are ignored rather than widened to all.
+            KittyCommand::Delete => {
+                match ctl.delete {
+                    'a' | 'A' => self.images.clear(),
+                    'i' | 'I' => {
+                        self.images.remove_image(ctl.id);
+                    }
+                    _ => {}
+                }
+                return;
+            }
+            KittyCommand::Transmit if ctl.display => {
+                let rgba = crate::clipboard::base64_decode_bytes(b64)
+                    .and_then(|px| kitty_pixels_to_rgba(&ctl, &px));
+                match rgba {
+                    Some(rgba) => {
+                        let (row, col) = (self.grid.cursor_row, self.grid.cursor_col);
+                        self.images.place(ctl.id, ctl.width, ctl.height, row, col, rgba);
+                        Ok(())
+                    }
+                    None => Err("EINVAL:bad image data"),
+                }
+            }
+            // Transmit-only and placement of stored images are not supported yet
+            KittyCommand::Transmit | KittyCommand::Place => Err("ENOTSUP:unsupported action"),
+        };
+
+        // Replies are only sent when the client named the image
+        if ctl.id == 0 || ctl.quiet >= 2 || (result.is_ok() && ctl.quiet == 1) {
+            return;
+        }
+        let msg = result.err().unwrap_or("OK");
+        self.reply_string(b'_', &format!("Gi={};{}", ctl.id, msg));
+    }
+
+    /// Answer a `ClipboardLoadRequest` with the selection's text:
+    /// `OSC 52 ; selection ; base64 ST`. Ignored when reads are off; text
+    /// over `clipboard_max_bytes` is reported empty.
+    pub fn answer_clipboard_request(&mut self, selection: &str, text: &str) {
+        if self.clipboard_read == ClipboardAccess::Off {
+            return;
+        }
+        let selection = crate::clipboard::osc52_targets(selection);
+        let text = if text.len() <= self.clipboard_max_bytes { text } else { "" };
+        let body = format!("52;{};{}", selection, crate::clipboard::base64_encode(text.as_bytes()));
+        self.reply_string(b']', &body);
+    }
+
+    /// Queue a reply introduced by `ESC fe`, or by the matching 8-bit C1
+    /// byte (`fe + 0x40`) after S8C1T.
+    fn reply(&mut self, fe: u8, body: &str) {
+        if self.c1_responses {
+            self.write_back.push(fe + 0x40);
+        } else {
+            self.write_back.extend_from_slice(&[0x1b, fe]);
+        }
+        self.write_back.extend_from_slice(body.as_bytes());
+    }
+
+    /// Queue a DCS/OSC/APC reply terminated by ST.
+    fn reply_string(&mut self, fe: u8, body: &str) {
+        self.reply(fe, body);
+        self.reply(b'\\', "");
+    }
+
+    /// Queue an OSC reply ending with the request's terminator.
+    fn reply_osc(&mut self, body: &str, bell_terminated: bool) {
+        if bell_terminated {
+            self.reply(b']', body);
+            self.write_back.push(0x07);
+        } else {
+            self.reply_string(b']', body);
+        }
+    }
+
+    /// Replace the palette with a theme's colors. Cells keep their
+    /// default/indexed colors, so the screen and scrollback recolor.
+    /// Colors changed by OSC 4/10/11/12 are discarded.
+    pub fn set_theme(&mut self, theme: &crate::theme::Theme) {
+        self.theme_palette = theme.palette();
+        self.palette = self.theme_palette.clone();
+    }
+
+    /// Whether renderers should keep showing the last complete frame: the
+    /// application is in a synchronized update (mode 2026) that hasn't
+    /// outlasted `SYNC_UPDATE_TIMEOUT`.
+    pub fn sync_update_pending(&self) -> bool {
+        self.sync_update.is_some_and(|start| start.elapsed() < SYNC_UPDATE_TIMEOUT)
+    }
+
+    /// Kitty keyboard protocol flags in effect on the current screen.
+    pub fn keyboard_flags(&self) -> KeyboardFlags {
+        self.keyboard_modes[self.alt_grid.is_some() as usize].current()
+    }
+
+    /// Modes the key and mouse encoders need (`input::encode_key`,
+    /// `input::encode_mouse`).
+    pub fn input_modes(&self) -> TerminalModes {
+        TerminalModes {
+            cursor_keys_app: self.cursor_keys_app,
+            keypad_app: self.keypad_app,
+            newline_mode: self.newline_mode,
+            modify_other_keys: self.modify_other_keys,
+            keyboard: self.keyboard_flags(),
+            mouse_mode: self.mouse_mode,
+            mouse_encoding: self.mouse_encoding,
+            alternate_scroll: self.alternate_scroll,
+            alt_screen: self.alt_grid.is_some(),
+        }
+    }
+
+    fn keyboard_stack(&mut self) -> &mut KeyboardModeStack {
+        &mut self.keyboard_modes[self.alt_grid.is_some() as usize]
+    }
+
+    /// The window or pane gained or lost focus. With mode 1004 set, queues
+    /// `CSI I` (focus in) or `CSI O` (focus out) in `write_back`.
+    pub fn set_focused(&mut self, focused: bool) {
+        if focused == self.focused {
+            return;
+        }
+        self.focused = focused;
+        if self.focus_reporting {
+            self.reply(b'[', if focused { "I" } else { "O" });
+        }
+    }
+
+    /// Cell size in pixels, for XTWINOPS size reports (`CSI 14 t`,
+    /// `CSI 16 t`).
+    pub fn set_cell_size(&mut self, width: u16, height: u16) {
+        self.cell_pixels = (width, height);
+    }
+
+    pub fn resize(&mut self, cols: usize, rows: usize) {
+        self.grid.resize(cols, rows);
+        self.scroll_top = 0;
+        self.scroll_bottom = rows - 1;
+        self.margin_left = 0;
+        self.margin_right = cols - 1;
+        self.tab_stops = vec![false; cols];
+        for i in (0..cols).step_by(8) {
+            self.tab_stops[i] = true;
+        }
+    }
+}
+
+impl Perform for Terminal {
+    fn print(&mut self, ch: char) {
+        Terminal::print(self, ch);
+    }
+
+    fn print_str(&mut self, s: &str) {
+        Terminal::print_str(self, s);
+    }
+
+    fn execute(&mut self, byte: u8) {
+        Terminal::execute(self, byte);
+    }
+
+    fn csi_dispatch(&mut self, final_byte: u8, params: &Params, intermediates: &[u8]) {
+        Terminal::csi_dispatch(self, final_byte, params, intermediates);
+    }
+
+    fn esc_dispatch(&mut self, final_byte: u8, intermediates: &[u8]) {
+        Terminal::esc_dispatch(self, final_byte, intermediates);
+    }
+
+    fn osc_dispatch(&mut self, data: &[u8], bell_terminated: bool) {
+        Terminal::osc_dispatch(self, data, bell_terminated);
+    }
+
+    fn hook(&mut self, params: &Params, intermediates: &[u8], final_byte: u8) {
+        Terminal::hook(self, params, intermediates, final_byte);
     }
 
-    pub fn resize(&mut self, cols: usize, rows: usize) {
-        self.grid.resize(cols, rows);
-        self.scroll_top = 0;
-        self.scroll_bottom = rows - 1;
-        self.tab_stops = vec![false; cols];
-        for i in (0..cols).step_by(8) {
-            self.tab_stops[i] = true;
-        }
+    fn put(&mut self, byte: u8) {
+        Terminal::put(self, byte);
+    }
+
+    fn unhook(&mut self) {
+        Terminal::unhook(self);
+    }
+
+    fn apc_dispatch(&mut self, data: &[u8]) {
+        Terminal::apc_dispatch(self, data);
+    }
+}
+
+/// Decode a hex string as used by XTGETTCAP.
+fn hex_decode(hex: &[u8]) -> Option<String> {
+    if !hex.iter().all(u8::is_ascii_hexdigit) {
+        return None;
     }
+    let pairs = hex.chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return None;
+    }
+    let bytes = pairs
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    String::from_utf8(bytes).ok()
+}
+
+fn hex_encode(s: &str) -> String {
+    s.bytes().map(|b| format!("{:02X}", b)).collect()
 }
 
-/// Get param at index with default value.
-fn param(params: &[u16], idx: usize, default: u16) -> u16 {
-    params.get(idx).copied().filter(|&v| v != 0).unwrap_or(default)
+/// Get param at index with default value. Sub-parameters are ignored.
+fn param(params: &Params, idx: usize, default: u16) -> u16 {
+    params.get(idx).and_then(|g| g.first()).copied().filter(|&v| v != 0).unwrap_or(default)
 }
 
-/// Parse 256-color (38;5;N) or truecolor (38;2;R;G;B) sequences.
-/// Returns (Color, number of extra params consumed).
-fn parse_extended_color(params: &[u16], start: usize) -> Option<(Color, usize)> {
-    match params.get(start)? {
+/// Parse the extended color introduced by the 38/48/58 group at `idx`.
+/// Accepts the colon form (38:5:N, 38:2::R:G:B, 38:2:R:G:B) and the
+/// semicolon form (38;5;N, 38;2;R;G;B).
+/// Returns (CellColor, number of extra groups consumed).
+fn parse_extended_color(params: &Params, idx: usize) -> Option<(CellColor, usize)> {
+    let group = params.get(idx)?;
+    if group.len() > 1 {
+        return parse_colon_color(&group[1..]).map(|c| (c, 0));
+    }
+
+    let value = |n: usize| params.get(idx + n).and_then(|g| g.first()).copied();
+    match value(1)? {
         5 => {
             // 256-color: index
-            let idx = *params.get(start + 1)? as usize;
-            Some((color_from_256(idx), 2))
+            Some((indexed_color(value(2)?), 2))
         }
         2 => {
             // Truecolor: R;G;B
-            let r = *params.get(start + 1)? as u8;
-            let g = *params.get(start + 2)? as u8;
-            let b = *params.get(start + 3)? as u8;
-            Some((Color { r, g, b }, 4))
+            let r = value(2)? as u8;
+            let g = value(3)? as u8;
+            let b = value(4)? as u8;
+            Some((CellColor::Rgb(Color { r, g, b }), 4))
         }
         _ => None,
     }
 }
 
-/// Convert 256-color index to RGB.
-fn color_from_256(idx: usize) -> Color {
-    match idx {
-        0..=15 => ANSI_COLORS[idx],
-        16..=231 => {
-            // 6x6x6 color cube
-            let idx = idx - 16;
-            let r = (idx / 36) % 6;
-            let g = (idx / 6) % 6;
-            let b = idx % 6;
-            let to_val = |v: usize| if v == 0 { 0u8 } else { (55 + 40 * v) as u8 };
-            Color { r: to_val(r), g: to_val(g), b: to_val(b) }
-        }
-        232..=255 => {
-            // Grayscale ramp
-            let v = (8 + 10 * (idx - 232)) as u8;
-            Color { r: v, g: v, b: v }
-        }
-        _ => Color::DEFAULT_FG,
+/// Parse colon sub-parameters following 38/48/58: `5:N`, `2:CS:R:G:B` or `2:R:G:B`.
+fn parse_colon_color(sub: &[u16]) -> Option<CellColor> {
+    match *sub.first()? {
+        5 => Some(indexed_color(*sub.get(1)?)),
+        2 => {
+            // ITU T.416 puts a color-space id before the components
+            let rgb = if sub.len() >= 5 { &sub[2..5] } else { sub.get(1..4)? };
+            Some(CellColor::Rgb(Color { r: rgb[0] as u8, g: rgb[1] as u8, b: rgb[2] as u8 }))
+        }
+        _ => None,
     }
 }
 
+/// 256-color index; out-of-range indexes fall back to the default color.
+fn indexed_color(idx: u16) -> CellColor {
+    u8::try_from(idx).map_or(CellColor::Default, CellColor::Indexed)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -813,6 +1941,109 @@ mod tests {
         assert_eq!(t.grid.cell(0, 3).ch, '\0');
     }
 
+    #[test]
+    fn test_bulk_print_wraps_and_scrolls() {
+        let mut t = Terminal::new(4, 2);
+        let mut p = VtParser::new();
+        t.feed_bytes(&mut p, b"abcdefghij");
+        assert_eq!(t.grid.cell(0, 0).ch, 'e');
+        assert_eq!(t.grid.cell(1, 0).ch, 'i');
+        assert_eq!(t.grid.cell(1, 1).ch, 'j');
+        assert_eq!(t.grid.cursor_col, 2);
+    }
+
+    #[test]
+    fn test_utf8_split_across_feeds() {
+        let (mut t, mut p) = make_term();
+        let bytes = "x中".as_bytes();
+        t.feed_bytes(&mut p, &bytes[..2]);
+        t.feed_bytes(&mut p, &bytes[2..]);
+        assert_eq!(t.grid.cell(0, 0).ch, 'x');
+        assert_eq!(t.grid.cell(0, 1).ch, '中');
+        assert_eq!(t.grid.cursor_col, 3);
+    }
+
+    #[test]
+    fn test_combining_mark_joins_cell() {
+        let (mut t, mut p) = make_term();
+        t.feed_bytes(&mut p, "e\u{301}x".as_bytes());
+        let mut text = String::new();
+        t.grid.push_cell_text(t.grid.cell(0, 0), &mut text);
+        assert_eq!(text, "e\u{301}");
+        assert_eq!(t.grid.cell(0, 1).ch, 'x');
+        assert_eq!(t.grid.cursor_col, 2);
+    }
+
+    #[test]
+    fn test_zwj_sequence_single_cell() {
+        let (mut t, mut p) = make_term();
+        let family = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+        t.feed_bytes(&mut p, format!("{family}!").as_bytes());
+        let mut text = String::new();
+        t.grid.push_cell_text(t.grid.cell(0, 0), &mut text);
+        assert_eq!(text, family);
+        assert_eq!(t.grid.cell(0, 2).ch, '!');
+    }
+
+    #[test]
+    fn test_zero_width_after_cursor_move_dropped() {
+        let (mut t, mut p) = make_term();
+        t.feed_bytes(&mut p, "a\x1b[5G\u{301}".as_bytes());
+        assert_eq!(t.grid.cell(0, 0).extra, 0);
+        // SGR doesn't move the cursor, so the mark still joins
+        t.feed_bytes(&mut p, "\rb\x1b[1m\u{301}".as_bytes());
+        assert_ne!(t.grid.cell(0, 0).extra, 0);
+    }
+
+    #[test]
+    fn test_ambiguous_width_wide() {
+        let (mut t, mut p) = make_term();
+        t.ambiguous_width = AmbiguousWidth::Wide;
+        t.feed_bytes(&mut p, "─x".as_bytes());
+        assert_eq!(t.grid.cell(0, 1).ch, '\0');
+        assert_eq!(t.grid.cell(0, 2).ch, 'x');
+    }
+
+    #[test]
+    fn test_ris_keeps_ambiguous_width() {
+        let (mut t, mut p) = make_term();
+        t.ambiguous_width = AmbiguousWidth::Wide;
+        t.feed_bytes(&mut p, "\x1bc─x".as_bytes());
+        assert_eq!(t.ambiguous_width, AmbiguousWidth::Wide);
+        assert_eq!(t.grid.cell(0, 2).ch, 'x');
+    }
+
+    #[test]
+    fn test_mode_2027_emoji_presentation() {
+        let (mut t, mut p) = make_term();
+        // Legacy: VS16 joins the cluster but ❤ stays one column
+        t.feed_bytes(&mut p, "\u{2764}\u{fe0f}x".as_bytes());
+        assert_eq!(t.grid.cell(0, 1).ch, 'x');
+
+        t.feed_bytes(&mut p, "\x1b[?2027h\r\x1b[K\u{2764}\u{fe0f}x".as_bytes());
+        assert_eq!(t.grid.cell(0, 1).ch, '\0');
+        assert_eq!(t.grid.cell(0, 2).ch, 'x');
+
+        // VS15 narrows a default-emoji character
+        t.feed_bytes(&mut p, "\r\x1b[K\u{231a}\u{fe0e}y".as_bytes());
+        assert_eq!(t.grid.cell(0, 1).ch, 'y');
+    }
+
+    #[test]
+    fn test_mode_2027_flag() {
+        let (mut t, mut p) = make_term();
+        let flag = "\u{1f1fa}\u{1f1f8}";
+        t.feed_bytes(&mut p, format!("{flag}|").as_bytes());
+        assert_eq!(t.grid.cell(0, 2).ch, '|'); // two 1-column indicators
+
+        t.feed_bytes(&mut p, format!("\x1b[?2027h\r\x1b[K{flag}|").as_bytes());
+        let mut text = String::new();
+        t.grid.push_cell_text(t.grid.cell(0, 0), &mut text);
+        assert_eq!(text, flag);
+        assert_eq!(t.grid.cell(0, 1).ch, '\0');
+        assert_eq!(t.grid.cell(0, 2).ch, '|');
+    }
+
     #[test]
     fn test_cjk_wrap_at_boundary() {
         // 9-col terminal: wide char at col 8 should wrap
@@ -906,21 +2137,22 @@ mod tests {
         let (mut t, mut p) = make_term();
         t.feed_bytes(&mut p, b"\x1b[1;31m");
         assert!(t.attr.contains(CellAttr::BOLD));
-        assert_eq!(t.fg, ANSI_COLORS[1]);
+        assert_eq!(t.fg, CellColor::Indexed(1));
     }
 
     #[test]
     fn test_sgr_truecolor() {
         let (mut t, mut p) = make_term();
         t.feed_bytes(&mut p, b"\x1b[38;2;100;150;200m");
-        assert_eq!(t.fg, Color { r: 100, g: 150, b: 200 });
+        assert_eq!(t.fg, CellColor::Rgb(Color { r: 100, g: 150, b: 200 }));
     }
 
     #[test]
     fn test_sgr_256_color() {
         let (mut t, mut p) = make_term();
         t.feed_bytes(&mut p, b"\x1b[38;5;196m");
-        assert_eq!(t.fg, Color { r: 255, g: 0, b: 0 });
+        assert_eq!(t.fg, CellColor::Indexed(196));
+        assert_eq!(t.palette.resolve_fg(t.fg), Color { r: 255, g: 0, b: 0 });
     }
 
     #[test]
@@ -928,16 +2160,75 @@ mod tests {
         let (mut t, mut p) = make_term();
         t.feed_bytes(&mut p, b"\x1b[48;5;21m"); // blue in cube
         // 21 - 16 = 5, r=0, g=0, b=5 → (0, 0, 255)
-        assert_eq!(t.bg, Color { r: 0, g: 0, b: 255 });
+        assert_eq!(t.palette.resolve_bg(t.bg), Color { r: 0, g: 0, b: 255 });
+    }
+
+    #[test]
+    fn test_sgr_truecolor_colon() {
+        let (mut t, mut p) = make_term();
+        t.feed_bytes(&mut p, b"\x1b[38:2::100:150:200m");
+        assert_eq!(t.fg, CellColor::Rgb(Color { r: 100, g: 150, b: 200 }));
+        t.feed_bytes(&mut p, b"\x1b[48:2:1:2:3;1m");
+        assert_eq!(t.bg, CellColor::Rgb(Color { r: 1, g: 2, b: 3 }));
+        assert!(t.attr.contains(CellAttr::BOLD));
+        t.feed_bytes(&mut p, b"\x1b[38:5:196m");
+        assert_eq!(t.fg, CellColor::Indexed(196));
+    }
+
+    #[test]
+    fn test_sgr_underline_subparam() {
+        let (mut t, mut p) = make_term();
+        t.feed_bytes(&mut p, b"\x1b[4:3m");
+        assert_eq!(t.attr.underline(), Some(UnderlineStyle::Curly));
+        // Sub-parameter 3 must not be read as SGR 3 (italic)
+        assert!(!t.attr.contains(CellAttr::ITALIC));
+        t.feed_bytes(&mut p, b"\x1b[4:0m");
+        assert_eq!(t.attr.underline(), None);
+    }
+
+    #[test]
+    fn test_sgr_underline_styles() {
+        let (mut t, mut p) = make_term();
+        for (seq, style) in [
+            (&b"\x1b[4m"[..], UnderlineStyle::Single),
+            (b"\x1b[21m", UnderlineStyle::Double),
+            (b"\x1b[4:2m", UnderlineStyle::Double),
+            (b"\x1b[4:4m", UnderlineStyle::Dotted),
+            (b"\x1b[4:5m", UnderlineStyle::Dashed),
+            (b"\x1b[4:1m", UnderlineStyle::Single),
+        ] {
+            t.feed_bytes(&mut p, seq);
+            // Styles replace each other rather than accumulate
+            assert_eq!(t.attr.underline(), Some(style));
+            assert_eq!((t.attr & CellAttr::ANY_UNDERLINE).bits().count_ones(), 1);
+        }
+        t.feed_bytes(&mut p, b"\x1b[24m");
+        assert_eq!(t.attr.underline(), None);
+    }
+
+    #[test]
+    fn test_sgr_underline_color() {
+        let (mut t, mut p) = make_term();
+        t.feed_bytes(&mut p, b"\x1b[4:3;58:2::255:0:0mab\x1b[59mc\x1b[58;5;4md\x1b[0me");
+        let red = CellColor::Rgb(Color { r: 255, g: 0, b: 0 });
+        assert_eq!(t.grid.cell(0, 0).underline_color, red);
+        assert_eq!(t.grid.cell(0, 1).underline_color, red);
+        assert_eq!(t.grid.cell(0, 1).attr.underline(), Some(UnderlineStyle::Curly));
+        assert_eq!(t.grid.cell(0, 2).underline_color, CellColor::Default);
+        assert_eq!(t.grid.cell(0, 3).underline_color, CellColor::Indexed(4));
+        // Underline color doesn't change the foreground
+        assert_eq!(t.grid.cell(0, 3).fg, CellColor::Default);
+        assert_eq!(t.grid.cell(0, 4).underline_color, CellColor::Default);
+        assert_eq!(t.grid.cell(0, 4).attr.underline(), None);
     }
 
     #[test]
     fn test_sgr_bright_colors() {
         let (mut t, mut p) = make_term();
         t.feed_bytes(&mut p, b"\x1b[91m"); // bright red fg
-        assert_eq!(t.fg, ANSI_COLORS[9]);
+        assert_eq!(t.fg, CellColor::Indexed(9));
         t.feed_bytes(&mut p, b"\x1b[104m"); // bright blue bg
-        assert_eq!(t.bg, ANSI_COLORS[12]);
+        assert_eq!(t.bg, CellColor::Indexed(12));
     }
 
     #[test]
@@ -948,7 +2239,7 @@ mod tests {
         assert!(t.attr.contains(CellAttr::ITALIC));
         t.feed_bytes(&mut p, b"\x1b[0m"); // reset
         assert_eq!(t.attr, CellAttr::empty());
-        assert_eq!(t.fg, Color::DEFAULT_FG);
+        assert_eq!(t.fg, CellColor::Default);
     }
 
     #[test]
@@ -964,9 +2255,9 @@ mod tests {
     fn test_sgr_256_grayscale() {
         let (mut t, mut p) = make_term();
         t.feed_bytes(&mut p, b"\x1b[38;5;232m"); // darkest gray
-        assert_eq!(t.fg, Color { r: 8, g: 8, b: 8 });
+        assert_eq!(t.palette.resolve_fg(t.fg), Color { r: 8, g: 8, b: 8 });
         t.feed_bytes(&mut p, b"\x1b[38;5;255m"); // lightest gray
-        assert_eq!(t.fg, Color { r: 238, g: 238, b: 238 });
+        assert_eq!(t.palette.resolve_fg(t.fg), Color { r: 238, g: 238, b: 238 });
     }
 
     // --- Erase ---
@@ -1121,7 +2412,7 @@ mod tests {
         t.feed_bytes(&mut p, b"\x1bc"); // RIS
         assert_eq!(t.grid.cell(0, 0).ch, ' ');
         assert_eq!(t.attr, CellAttr::empty());
-        assert_eq!(t.fg, Color::DEFAULT_FG);
+        assert_eq!(t.fg, CellColor::Default);
         assert_eq!(t.grid.cursor_row, 0);
         assert_eq!(t.grid.cursor_col, 0);
     }
@@ -1224,28 +2515,6 @@ mod tests {
         assert_eq!(t.grid.cell(0, 0).ch, 'H');
     }
 
-    // --- Color helpers ---
-
-    #[test]
-    fn test_color_from_256_ansi() {
-        assert_eq!(color_from_256(0), ANSI_COLORS[0]);
-        assert_eq!(color_from_256(15), ANSI_COLORS[15]);
-    }
-
-    #[test]
-    fn test_color_from_256_cube() {
-        // Index 16 = (0,0,0) = black
-        assert_eq!(color_from_256(16), Color { r: 0, g: 0, b: 0 });
-        // Index 231 = (5,5,5) = white-ish
-        assert_eq!(color_from_256(231), Color { r: 255, g: 255, b: 255 });
-    }
-
-    #[test]
-    fn test_color_from_256_grayscale() {
-        assert_eq!(color_from_256(232), Color { r: 8, g: 8, b: 8 });
-        assert_eq!(color_from_256(255), Color { r: 238, g: 238, b: 238 });
-    }
-
     // ---- Phase 3 tests ----
 
     #[test]
@@ -1274,6 +2543,35 @@ mod tests {
         assert_eq!(t.write_back, b"\x1b[?62;22c");
     }
 
+    #[test]
+    fn test_s8c1t_replies_and_c1_input() {
+        let mut t = Terminal::new(10, 5);
+        let mut p = VtParser::new();
+        t.feed_bytes(&mut p, b"\x1b G");
+        assert!(t.c1_responses);
+        // 8-bit CSI now recognized on input, and replies use 8-bit CSI
+        t.feed_bytes(&mut p, b"\x9b2;3H\x9b6n");
+        assert_eq!(t.write_back, b"\x9b2;3R");
+        t.write_back.clear();
+        t.feed_bytes(&mut p, b"\x1b F\x1b[5n");
+        assert_eq!(t.write_back, b"\x1b[0n");
+        // C1 NEL moves to the next line
+        p.set_c1_controls(true);
+        t.feed_bytes(&mut p, b"ab\x85");
+        assert_eq!((t.grid.cursor_row, t.grid.cursor_col), (2, 0));
+    }
+
+    #[test]
+    fn test_ris_resets_s8c1t() {
+        let mut t = Terminal::new(10, 5);
+        let mut p = VtParser::new();
+        t.feed_bytes(&mut p, b"\x1b G\x1bc");
+        assert!(!t.c1_responses);
+        assert!(!p.c1_controls());
+        t.feed_bytes(&mut p, b"\x1b[5n");
+        assert_eq!(t.write_back, b"\x1b[0n");
+    }
+
     #[test]
     fn test_cursor_keys_app_mode() {
         let mut t = Terminal::new(10, 5);
@@ -1327,6 +2625,10 @@ mod tests {
         assert_eq!(t.mouse_encoding, MouseEncoding::X10);
         t.feed_bytes(&mut p, b"\x1b[?1006h");
         assert_eq!(t.mouse_encoding, MouseEncoding::Sgr);
+        t.feed_bytes(&mut p, b"\x1b[?1016h\x1b[?1006l");
+        assert_eq!(t.mouse_encoding, MouseEncoding::SgrPixels);
+        t.feed_bytes(&mut p, b"\x1b[?1016l");
+        assert_eq!(t.mouse_encoding, MouseEncoding::X10);
     }
 
     #[test]
@@ -1416,7 +2718,7 @@ mod tests {
         t.feed_bytes(&mut p, b"\x1b[u");
         t.feed_bytes(&mut p, b"X");
         assert!(t.grid.cell(0, 0).attr.contains(CellAttr::BOLD));
-        assert_eq!(t.grid.cell(0, 0).fg, ANSI_COLORS[1]);
+        assert_eq!(t.grid.cell(0, 0).fg, CellColor::Indexed(1));
     }
 
     #[test]
@@ -1446,22 +2748,448 @@ mod tests {
         let mut t = Terminal::new(40, 5);
         let mut p = VtParser::new();
         t.feed_bytes(&mut p, b"\x1b]7;file://hostname/home/user\x07");
-        assert_eq!(t.osc7_cwd, Some("file://hostname/home/user".into()));
+        assert_eq!(t.drain_events().collect::<Vec<_>>(), [TerminalEvent::CwdChanged("/home/user".into())]);
+        // Unchanged directory: no event
+        t.feed_bytes(&mut p, b"\x1b]7;file://hostname/home/user\x07");
+        assert_eq!(t.drain_events().count(), 0);
     }
 
     #[test]
     fn test_osc133_shell_integration() {
         let mut t = Terminal::new(40, 5);
         let mut p = VtParser::new();
-        t.feed_bytes(&mut p, b"\x1b]133;A\x07");
-        assert_eq!(t.osc133_data, Some("A".into()));
+        t.feed_bytes(&mut p, b"\x1b]133;A\x07\x1b]133;B\x07\x1b]133;C\x07\x1b]133;D;2\x07");
+        let events: Vec<_> = t.drain_events().collect();
+        assert_eq!(events[0], TerminalEvent::CommandStarted);
+        assert!(matches!(events[1], TerminalEvent::CommandFinished { exit_code: Some(2), duration: Some(_) }));
+        assert_eq!(events.len(), 2);
+    }
+
+    #[test]
+    fn test_dcs_payload_not_printed() {
+        let mut t = Terminal::new(20, 5);
+        let mut p = VtParser::new();
+        t.feed_bytes(&mut p, b"\x1bP$qm\x1b\\OK");
+        assert_eq!(grid_row(&t, 0), "OK");
+    }
+
+    #[test]
+    fn test_apc_kitty_query() {
+        let mut t = Terminal::new(20, 5);
+        let mut p = VtParser::new();
+        t.feed_bytes(&mut p, b"\x1b_Ga=q,i=31,s=1,v=1,f=24;AAAA\x1b\\");
+        assert_eq!(t.write_back, b"\x1b_Gi=31;OK\x1b\\");
+        assert_eq!(grid_row(&t, 0), "");
+    }
+
+    #[test]
+    fn test_apc_kitty_display_and_delete() {
+        let mut t = Terminal::new(20, 5);
+        let mut p = VtParser::new();
+        // 1x1 RGB pixel (0x010203) = "AQID"
+        t.feed_bytes(&mut p, b"\x1b[2;3H\x1b_Ga=T,f=24,s=1,v=1;AQID\x1b\\");
+        assert_eq!(t.images.count(), 1);
+        let img = t.images.visible(0, 4)[0];
+        assert_eq!((img.row, img.col), (1, 2));
+        assert_eq!(img.data, vec![1, 2, 3, 255]);
+        t.feed_bytes(&mut p, b"\x1b_Ga=d\x1b\\");
+        assert_eq!(t.images.count(), 0);
+    }
+
+    #[test]
+    fn test_apc_kitty_delete_by_id() {
+        let mut t = Terminal::new(20, 5);
+        let mut p = VtParser::new();
+        for id in [1, 2, 3] {
+            t.feed_bytes(&mut p, format!("\x1b_Ga=T,i={},q=1,f=24,s=1,v=1;AQID\x1b\\", id).as_bytes());
+        }
+        assert_eq!(t.images.count(), 3);
+        t.feed_bytes(&mut p, b"\x1b_Ga=d,d=i,i=2\x1b\\");
+        let mut left: Vec<u32> = t.images.visible(0, 4).iter().map(|img| img.image_id).collect();
+        left.sort();
+        assert_eq!(left, [1, 3]);
+        // Unsupported targets don't fall back to deleting everything
+        t.feed_bytes(&mut p, b"\x1b_Ga=d,d=z,z=1\x1b\\");
+        assert_eq!(t.images.count(), 2);
+        t.feed_bytes(&mut p, b"\x1b_Ga=d,d=A\x1b\\");
+        assert_eq!(t.images.count(), 0);
+        assert!(t.write_back.is_empty());
     }
 
     #[test]
     fn test_osc52_clipboard() {
         let mut t = Terminal::new(40, 5);
         let mut p = VtParser::new();
+        t.feed_bytes(&mut p, b"\x1b]52;c;aGVsbG8=\x07\x1b]52;;?\x07\x1b]52;p;!\x07");
+        assert_eq!(t.drain_events().collect::<Vec<_>>(), [
+            TerminalEvent::ClipboardStore { selection: "c".into(), text: "hello".into() },
+            TerminalEvent::ClipboardLoadRequest { selection: "s0".into() },
+            TerminalEvent::ClipboardStore { selection: "p".into(), text: String::new() },
+        ]);
+        t.answer_clipboard_request("c", "hi");
+        assert_eq!(t.write_back, b"\x1b]52;c;aGk=\x1b\\");
+    }
+
+    #[test]
+    fn test_osc52_policy() {
+        let mut t = Terminal::new(40, 5);
+        let mut p = VtParser::new();
+        t.clipboard_max_bytes = 4;
         t.feed_bytes(&mut p, b"\x1b]52;c;aGVsbG8=\x07");
-        assert_eq!(t.osc52_data, Some("52;c;aGVsbG8=".into()));
+        assert_eq!(t.next_event(), None);
+        t.answer_clipboard_request("c", "hello");
+        assert_eq!(t.write_back, b"\x1b]52;c;\x1b\\");
+        t.write_back.clear();
+
+        t.clipboard_write = ClipboardAccess::Off;
+        t.clipboard_read = ClipboardAccess::Off;
+        t.feed_bytes(&mut p, b"\x1b]52;c;YQ==\x07\x1b]52;c;?\x07\x1b]52;c;\x07");
+        assert_eq!(t.next_event(), None);
+        t.answer_clipboard_request("c", "a");
+        assert!(t.write_back.is_empty());
+        // RIS keeps the policy
+        t.feed_bytes(&mut p, b"\x1bc");
+        assert_eq!(t.clipboard_read, ClipboardAccess::Off);
+    }
+
+    #[test]
+    fn test_osc8_hyperlink_cells() {
+        let mut t = Terminal::new(40, 5);
+        let mut p = VtParser::new();
+        t.feed_bytes(&mut p, "a\x1b]8;id=1;https://example.com\x1b\\link中\x1b]8;;\x1b\\b".as_bytes());
+        let link = t.grid.cell(0, 1).link;
+        assert_ne!(link, 0);
+        assert_eq!(t.hyperlinks.get(link).unwrap().uri, "https://example.com");
+        assert_eq!(t.hyperlinks.get(link).unwrap().id.as_deref(), Some("1"));
+        assert_eq!(t.grid.cell(0, 0).link, 0);
+        assert_eq!(t.grid.cell(0, 4).link, link);
+        // Wide char and its spacer both carry the link
+        assert_eq!(t.grid.cell(0, 5).link, link);
+        assert_eq!(t.grid.cell(0, 6).link, link);
+        assert_eq!(t.grid.cell(0, 7).link, 0);
+    }
+
+    #[test]
+    fn test_osc8_unsafe_uri_ignored() {
+        let mut t = Terminal::new(40, 5);
+        let mut p = VtParser::new();
+        t.feed_bytes(&mut p, b"\x1b]8;;https://ok.com\x07a\x1b]8;;javascript:alert(1)\x07b");
+        assert_ne!(t.grid.cell(0, 0).link, 0);
+        assert_eq!(t.grid.cell(0, 1).link, 0);
+        assert_eq!(t.hyperlinks.len(), 1);
+    }
+
+    #[test]
+    fn test_osc8_overwrite_clears_link() {
+        let mut t = Terminal::new(40, 5);
+        let mut p = VtParser::new();
+        t.feed_bytes(&mut p, b"\x1b]8;;https://ok.com\x07ab\x1b]8;;\x07\rx");
+        assert_eq!(t.grid.cell(0, 0).link, 0);
+        assert_ne!(t.grid.cell(0, 1).link, 0);
+    }
+
+    #[test]
+    fn test_osc8_table_collects_unreferenced_links() {
+        let mut t = Terminal::new(10, 2);
+        let mut p = VtParser::new();
+        t.feed_bytes(&mut p, b"\x1b]8;;https://old.com\x07a\x1b]8;;\x07");
+        t.feed_bytes(&mut p, b"\x1b[2J");
+        t.collect_hyperlinks();
+        assert!(t.hyperlinks.is_empty());
+    }
+
+    #[test]
+    fn test_osc8_unprinted_links_take_no_slots() {
+        let mut t = Terminal::new(10, 2);
+        let mut p = VtParser::new();
+        for i in 0..1000 {
+            t.feed_bytes(&mut p, format!("\x1b]8;;https://a.com/{}\x07", i).as_bytes());
+        }
+        assert!(t.hyperlinks.is_empty());
+        let long = format!("\x1b]8;;https://a.com/{}\x07a", "x".repeat(HyperlinkTable::MAX_URI_LEN));
+        t.feed_bytes(&mut p, long.as_bytes());
+        assert_eq!(t.grid.cell(0, 0).link, 0);
+        t.feed_bytes(&mut p, b"\x1b]8;;https://b.com\x07b");
+        assert_eq!(t.hyperlinks.len(), 1);
+        assert_eq!(t.hyperlinks.get(t.grid.cell(0, 1).link).unwrap().uri, "https://b.com");
+    }
+
+    #[test]
+    fn test_theme_change_recolors_existing_cells() {
+        let (mut t, mut p) = make_term();
+        t.feed_bytes(&mut p, b"\x1b[31mR\x1b[0mD\x1b[38;2;1;2;3mX");
+        let red = |t: &Terminal| t.palette.resolve_fg(t.grid.cell(0, 0).fg);
+        assert_eq!(red(&t), Color { r: 205, g: 49, b: 49 });
+
+        let dracula = crate::theme::Theme::dracula();
+        t.set_theme(&dracula);
+        assert_eq!(red(&t), dracula.ansi[1]);
+        assert_eq!(t.palette.resolve_fg(t.grid.cell(0, 1).fg), dracula.fg);
+        assert_eq!(t.palette.resolve_bg(t.grid.cell(0, 1).bg), dracula.bg);
+        // Truecolor is unaffected
+        assert_eq!(t.palette.resolve_fg(t.grid.cell(0, 2).fg), Color { r: 1, g: 2, b: 3 });
+        // RIS keeps the theme
+        t.feed_bytes(&mut p, b"\x1bc");
+        assert_eq!(t.palette.fg, dracula.fg);
+    }
+
+    #[test]
+    fn test_osc_color_query_matches_terminator() {
+        let (mut t, mut p) = make_term();
+        t.feed_bytes(&mut p, b"\x1b]11;?\x07");
+        assert_eq!(t.write_back, b"\x1b]11;rgb:0000/0000/0000\x07");
+        t.write_back.clear();
+        t.feed_bytes(&mut p, b"\x1b]10;?\x1b\\");
+        assert_eq!(t.write_back, b"\x1b]10;rgb:cccc/cccc/cccc\x1b\\");
+    }
+
+    #[test]
+    fn test_osc_dynamic_colors_set_and_reset() {
+        let (mut t, mut p) = make_term();
+        t.set_theme(&crate::theme::Theme::dracula());
+        t.feed_bytes(&mut p, b"\x1b]10;#ffffff;rgb:10/20/30\x07\x1b]12;#ff0000\x07");
+        assert_eq!(t.palette.fg, Color { r: 255, g: 255, b: 255 });
+        assert_eq!(t.palette.bg, Color { r: 0x10, g: 0x20, b: 0x30 });
+        assert_eq!(t.palette.cursor, Color { r: 255, g: 0, b: 0 });
+        t.feed_bytes(&mut p, b"\x1b]11;?\x07");
+        assert_eq!(t.write_back, b"\x1b]11;rgb:1010/2020/3030\x07");
+        t.feed_bytes(&mut p, b"\x1b]110\x07\x1b]111\x07\x1b]112\x07");
+        let dracula = crate::theme::Theme::dracula();
+        assert_eq!((t.palette.fg, t.palette.bg, t.palette.cursor), (dracula.fg, dracula.bg, dracula.cursor));
+    }
+
+    #[test]
+    fn test_osc4_palette() {
+        let (mut t, mut p) = make_term();
+        t.feed_bytes(&mut p, b"\x1b[31mR\x1b]4;1;#102030;200;rgb:ff/ff/00\x1b\\");
+        assert_eq!(t.palette.resolve_fg(t.grid.cell(0, 0).fg), Color { r: 0x10, g: 0x20, b: 0x30 });
+        assert_eq!(t.palette.colors[200], Color { r: 255, g: 255, b: 0 });
+        t.feed_bytes(&mut p, b"\x1b]4;1;?;2;?\x07");
+        assert_eq!(t.write_back, b"\x1b]4;1;rgb:1010/2020/3030\x07\x1b]4;2;rgb:0d0d/bcbc/7979\x07");
+        // Reset one entry, then all
+        t.feed_bytes(&mut p, b"\x1b]104;1\x07");
+        assert_eq!(t.palette.colors[1], crate::core::ANSI_COLORS[1]);
+        assert_eq!(t.palette.colors[200], Color { r: 255, g: 255, b: 0 });
+        t.feed_bytes(&mut p, b"\x1b]104\x07");
+        assert_eq!(t.palette, Palette::default());
+    }
+
+    #[test]
+    fn test_osc_color_reply_8bit() {
+        let (mut t, mut p) = make_term();
+        t.c1_responses = true;
+        t.feed_bytes(&mut p, b"\x1b]11;?\x1b\\");
+        assert_eq!(t.write_back, b"\x9d11;rgb:0000/0000/0000\x9c");
+    }
+
+    #[test]
+    fn test_decrqss_sgr_roundtrip() {
+        let (mut t, mut p) = make_term();
+        t.feed_bytes(&mut p, b"\x1b[1;3;4:3;93;48;5;200;58;2;1;2;3m\x1bP$qm\x1b\\");
+        let reply = String::from_utf8(std::mem::take(&mut t.write_back)).unwrap();
+        assert_eq!(reply, "\x1bP1$r0;1;3;4:3;93;48:5:200;58:2::1:2:3m\x1b\\");
+        // Replaying the reported SGR restores the same pen
+        let (attr, fg, bg, ul) = (t.attr, t.fg, t.bg, t.underline_color);
+        let sgr = &reply[5..reply.len() - 2];
+        t.feed_bytes(&mut p, format!("\x1b[0m\x1b[{}", sgr).as_bytes());
+        assert_eq!((t.attr, t.fg, t.bg, t.underline_color), (attr, fg, bg, ul));
+    }
+
+    #[test]
+    fn test_dcs_request_via_actions() {
+        let mut t = Terminal::new(80, 24);
+        let mut p = VtParser::new();
+        for action in p.feed(b"\x1bP$qr\x1b\\") {
+            t.handle_action(action);
+        }
+        assert_eq!(t.write_back, b"\x1bP1$r1;24r\x1b\\");
+    }
+
+    #[test]
+    fn test_focus_reporting() {
+        let (mut t, mut p) = make_term();
+        // Not reported until mode 1004 is set
+        t.set_focused(false);
+        t.set_focused(true);
+        assert!(t.write_back.is_empty());
+        t.feed_bytes(&mut p, b"\x1b[?1004h");
+        t.set_focused(false);
+        t.set_focused(false);
+        t.set_focused(true);
+        assert_eq!(t.write_back, b"\x1b[O\x1b[I");
+        t.write_back.clear();
+        t.feed_bytes(&mut p, b"\x1b[?1004$p\x1b[?1004l");
+        t.set_focused(false);
+        assert_eq!(t.write_back, b"\x1b[?1004;1$y");
+    }
+
+    #[test]
+    fn test_sync_update() {
+        let (mut t, mut p) = make_term();
+        assert!(!t.sync_update_pending());
+        t.feed_bytes(&mut p, b"\x1b[?2026h\x1b[2J");
+        assert!(t.sync_update_pending());
+        t.feed_bytes(&mut p, b"\x1b[?2026$p\x1b[?2026l\x1b[?2026$p");
+        assert!(!t.sync_update_pending());
+        assert_eq!(t.write_back, b"\x1b[?2026;1$y\x1b[?2026;2$y");
+    }
+
+    #[test]
+    fn test_sync_update_times_out() {
+        let (mut t, mut p) = make_term();
+        t.feed_bytes(&mut p, b"\x1b[?2026h");
+        t.sync_update = Instant::now().checked_sub(SYNC_UPDATE_TIMEOUT);
+        assert!(!t.sync_update_pending());
+        // Another BSU doesn't restart the timer
+        t.feed_bytes(&mut p, b"\x1b[?2026h");
+        assert!(!t.sync_update_pending());
+    }
+
+    #[test]
+    fn test_kitty_keyboard_flags() {
+        let (mut t, mut p) = make_term();
+        t.feed_bytes(&mut p, b"\x1b[>1u\x1b[=8;2u\x1b[?u");
+        assert_eq!(t.keyboard_flags(), KeyboardFlags::DISAMBIGUATE | KeyboardFlags::REPORT_ALL_KEYS);
+        assert_eq!(t.write_back, b"\x1b[?9u");
+        // The alternate screen has its own stack
+        t.feed_bytes(&mut p, b"\x1b[?1049h");
+        assert_eq!(t.keyboard_flags(), KeyboardFlags::empty());
+        t.feed_bytes(&mut p, b"\x1b[>2u");
+        assert_eq!(t.keyboard_flags(), KeyboardFlags::REPORT_EVENT_TYPES);
+        t.feed_bytes(&mut p, b"\x1b[?1049l");
+        assert_eq!(t.keyboard_flags(), KeyboardFlags::DISAMBIGUATE | KeyboardFlags::REPORT_ALL_KEYS);
+        t.feed_bytes(&mut p, b"\x1b[<u");
+        assert_eq!(t.keyboard_flags(), KeyboardFlags::empty());
+    }
+
+    #[test]
+    fn test_csi_u_still_restores_cursor() {
+        let (mut t, mut p) = make_term();
+        t.feed_bytes(&mut p, b"\x1b[5;10H\x1b[s\x1b[H\x1b[>1u\x1b[u");
+        assert_eq!((t.grid.cursor_row, t.grid.cursor_col), (4, 9));
+        assert_eq!(t.keyboard_flags(), KeyboardFlags::DISAMBIGUATE);
+    }
+
+    #[test]
+    fn test_modify_other_keys() {
+        let (mut t, mut p) = make_term();
+        t.feed_bytes(&mut p, b"\x1b[>4;2m");
+        assert_eq!(t.modify_other_keys, 2);
+        // Not mistaken for SGR 4 (underline)
+        assert_eq!(t.attr, CellAttr::empty());
+        t.feed_bytes(&mut p, b"\x1b[?1h\x1b=");
+        let modes = t.input_modes();
+        assert!(modes.cursor_keys_app && modes.keypad_app);
+        assert_eq!(modes.modify_other_keys, 2);
+        t.feed_bytes(&mut p, b"\x1b[>4n");
+        assert_eq!(t.modify_other_keys, 0);
+        t.feed_bytes(&mut p, b"\x1b[>4;1m\x1b[>4m");
+        assert_eq!(t.modify_other_keys, 0);
+        // Other resources are ignored, not taken as SGR or DSR
+        t.feed_bytes(&mut p, b"\x1b[>1;1m\x1b[>5n");
+        assert_eq!(t.attr, CellAttr::empty());
+        assert!(t.write_back.is_empty());
+    }
+
+    #[test]
+    fn test_xtwinops_size_reports() {
+        let (mut t, mut p) = make_term();
+        t.feed_bytes(&mut p, b"\x1b[18t\x1b[14t\x1b[16t");
+        // No cell metrics yet: pixel sizes are still answered, as zero
+        assert_eq!(t.write_back, b"\x1b[8;24;80t\x1b[4;0;0t\x1b[6;0;0t");
+        t.write_back.clear();
+        t.set_cell_size(9, 18);
+        t.feed_bytes(&mut p, b"\x1b[14t\x1b[16t\x1b[11t");
+        assert_eq!(t.write_back, b"\x1b[4;432;720t\x1b[6;18;9t\x1b[1t");
+    }
+
+    #[test]
+    fn test_xtwinops_title_stack() {
+        let (mut t, mut p) = make_term();
+        t.feed_bytes(&mut p, b"\x1b]2;shell\x07\x1b[22;0t\x1b]2;vim\x07");
+        assert_eq!(t.title, "vim");
+        t.feed_bytes(&mut p, b"\x1b[23;0t");
+        assert_eq!(t.title, "shell");
+        // Popping an empty stack keeps the title
+        t.feed_bytes(&mut p, b"\x1b[23t");
+        assert_eq!(t.title, "shell");
+    }
+
+    #[test]
+    fn test_xtwinops_policy() {
+        let (mut t, mut p) = make_term();
+        t.feed_bytes(&mut p, b"\x1b]2;secret\x07");
+        t.drain_events().for_each(drop);
+        t.feed_bytes(&mut p, b"\x1b[2t\x1b[8;50;100t\x1b[21t");
+        assert_eq!(t.drain_events().count(), 0);
+        assert!(t.write_back.is_empty());
+
+        t.window_op_policy = |op| !matches!(op, WindowOp::Iconify);
+        t.feed_bytes(&mut p, b"\x1b[2t\x1b[8;50;100t\x1b[3;10;20t\x1b[30t\x1b[21t");
+        assert_eq!(t.drain_events().collect::<Vec<_>>(), [
+            TerminalEvent::WindowOp(WindowOp::ResizeCells { cols: 100, rows: 50 }),
+            TerminalEvent::WindowOp(WindowOp::Move { x: 10, y: 20 }),
+            TerminalEvent::WindowOp(WindowOp::ResizeCells { cols: 0, rows: 30 }),
+        ]);
+        assert_eq!(t.write_back, b"\x1b]lsecret\x1b\\");
+    }
+
+    #[test]
+    fn test_events_bell_title_modes() {
+        let (mut t, mut p) = make_term();
+        t.feed_bytes(&mut p, b"\x07\x1b]0;hi\x07\x1b[?2004h\x1b[?2004h\x1b[4h\x1b[?9999h");
+        assert_eq!(t.drain_events().collect::<Vec<_>>(), [
+            TerminalEvent::Bell,
+            TerminalEvent::TitleChanged("hi".into()),
+            TerminalEvent::ModeChanged { mode: 2004, private: true, enabled: true },
+            TerminalEvent::ModeChanged { mode: 4, private: false, enabled: true },
+        ]);
+        // RIS clears the title and keeps undrained events
+        t.feed_bytes(&mut p, b"\x07\x1bc");
+        assert_eq!(t.drain_events().collect::<Vec<_>>(), [
+            TerminalEvent::Bell,
+            TerminalEvent::TitleChanged(String::new()),
+        ]);
+    }
+
+    #[test]
+    fn test_events_notifications_and_color_queries() {
+        let (mut t, mut p) = make_term();
+        t.feed_bytes(&mut p, b"\x1b]9;done\x07\x1b]9;4;1;50\x07\x1b]777;notify;Build;ok\x1b\\");
+        t.feed_bytes(&mut p, b"\x1b]4;1;?\x07\x1b]11;?\x07");
+        assert_eq!(t.drain_events().collect::<Vec<_>>(), [
+            TerminalEvent::Notification { title: String::new(), body: "done".into() },
+            TerminalEvent::Notification { title: "Build".into(), body: "ok".into() },
+            TerminalEvent::ColorQuery { osc: 4, index: Some(1) },
+            TerminalEvent::ColorQuery { osc: 11, index: None },
+        ]);
+    }
+
+    #[test]
+    fn test_events_queue_is_bounded() {
+        let (mut t, mut p) = make_term();
+        t.feed_bytes(&mut p, &[0x07; MAX_EVENTS + 10]);
+        assert_eq!(t.drain_events().count(), MAX_EVENTS);
+
+        // Large payloads are bounded by bytes, not just count
+        let body = "x".repeat(500_000);
+        for _ in 0..20 {
+            t.feed_bytes(&mut p, format!("\x1b]9;{}\x07", body).as_bytes());
+        }
+        let held: usize = t.drain_events().map(|e| e.payload_len()).sum();
+        assert!(held <= MAX_EVENT_BYTES);
+        assert_eq!(t.event_bytes, 0);
+    }
+
+    #[test]
+    fn test_events_coalesce_titles_and_color_queries() {
+        let (mut t, mut p) = make_term();
+        t.feed_bytes(&mut p, b"\x1b]2;a\x07\x1b]2;b\x07\x1b]11;?\x07\x1b]11;?\x07\x1b]10;?\x07\x1b]2;c\x07");
+        assert_eq!(t.drain_events().collect::<Vec<_>>(), [
+            TerminalEvent::TitleChanged("b".into()),
+            TerminalEvent::ColorQuery { osc: 11, index: None },
+            TerminalEvent::ColorQuery { osc: 10, index: None },
+            TerminalEvent::TitleChanged("c".into()),
+        ]);
     }
 }
@@ -0,0 +1,159 @@
+//! OSC 8 hyperlink table. Cells store a `u16` link id; the URI and
+//! parameters live here, shared by every cell of the link.
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hyperlink {
+    /// `id=` parameter, if the application gave one
+    pub id: Option<String>,
+    /// Raw `key=value:key=value` parameter string
+    pub params: String,
+    pub uri: String,
+}
+
+/// Links referenced by cells. Id 0 means "no link".
+#[derive(Debug, Default)]
+pub struct HyperlinkTable {
+    slots: Vec<Option<Hyperlink>>,
+    free: Vec<u16>,
+    /// Links with an explicit `id=`, so cells printed by separate OSC 8
+    /// sequences with the same id and URI resolve to one link.
+    by_id: HashMap<(String, String), u16>,
+    /// URI and parameter bytes held by live links
+    bytes: usize,
+}
+
+impl HyperlinkTable {
+    pub const MAX_LINKS: usize = u16::MAX as usize;
+    /// Longest URI accepted, as in VTE
+    pub const MAX_URI_LEN: usize = 2083;
+    /// Longest parameter string accepted
+    pub const MAX_PARAMS_LEN: usize = 256;
+    /// URI and parameter bytes the table may hold
+    pub const MAX_BYTES: usize = 4 << 20;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, link: u16) -> Option<&Hyperlink> {
+        self.slots.get((link as usize).checked_sub(1)?)?.as_ref()
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// No slot is free, or the byte budget can't take a link of the
+    /// maximum size.
+    pub fn is_full(&self) -> bool {
+        let no_slot = self.free.is_empty() && self.slots.len() >= Self::MAX_LINKS;
+        no_slot || self.bytes + Self::MAX_URI_LEN + Self::MAX_PARAMS_LEN > Self::MAX_BYTES
+    }
+
+    /// Add a link parsed from the OSC 8 `params` field. Returns its id, or
+    /// 0 when the table is full or the URI or parameters are too long.
+    pub fn insert(&mut self, params: &str, uri: &str) -> u16 {
+        let size = params.len() + uri.len();
+        let oversized = uri.len() > Self::MAX_URI_LEN || params.len() > Self::MAX_PARAMS_LEN;
+        if oversized || self.bytes + size > Self::MAX_BYTES {
+            return 0;
+        }
+        let id = params.split(':')
+            .find_map(|kv| kv.strip_prefix("id="))
+            .filter(|id| !id.is_empty())
+            .map(str::to_string);
+        if let Some(id) = &id {
+            if let Some(&link) = self.by_id.get(&(id.clone(), uri.to_string())) {
+                return link;
+            }
+        }
+
+        let entry = Hyperlink { id: id.clone(), params: params.to_string(), uri: uri.to_string() };
+        let link = if let Some(link) = self.free.pop() {
+            self.slots[link as usize - 1] = Some(entry);
+            link
+        } else if self.slots.len() < Self::MAX_LINKS {
+            self.slots.push(Some(entry));
+            self.slots.len() as u16
+        } else {
+            return 0;
+        };
+        self.bytes += size;
+        if let Some(id) = id {
+            self.by_id.insert((id, uri.to_string()), link);
+        }
+        link
+    }
+
+    /// Free every link not in `live`.
+    pub fn retain(&mut self, live: &[bool]) {
+        for (i, slot) in self.slots.iter_mut().enumerate() {
+            let link = i as u16 + 1;
+            if slot.is_some() && !live.get(link as usize).copied().unwrap_or(false) {
+                if let Some(old) = slot.take() {
+                    self.bytes -= old.params.len() + old.uri.len();
+                }
+                self.free.push(link);
+            }
+        }
+        let slots = &self.slots;
+        self.by_id.retain(|_, link| slots[*link as usize - 1].is_some());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_and_reuse_by_id() {
+        let mut t = HyperlinkTable::new();
+        let a = t.insert("id=x", "https://a.com");
+        let b = t.insert("", "https://a.com");
+        let c = t.insert("foo=1:id=x", "https://a.com");
+        assert_ne!(a, 0);
+        assert_ne!(a, b);
+        assert_eq!(a, c);
+        assert_eq!(t.get(a).unwrap().id.as_deref(), Some("x"));
+        assert_eq!(t.get(b).unwrap().uri, "https://a.com");
+        assert!(t.get(0).is_none());
+    }
+
+    #[test]
+    fn test_retain_frees_unused() {
+        let mut t = HyperlinkTable::new();
+        let a = t.insert("id=x", "https://a.com");
+        let b = t.insert("", "https://b.com");
+        let mut live = vec![false; 3];
+        live[b as usize] = true;
+        t.retain(&live);
+        assert!(t.get(a).is_none());
+        assert_eq!(t.len(), 1);
+        // Freed slot is reused, and the stale id mapping is gone
+        let c = t.insert("id=x", "https://c.com");
+        assert_eq!(c, a);
+        assert_eq!(t.get(c).unwrap().uri, "https://c.com");
+    }
+
+    #[test]
+    fn test_size_limits() {
+        let mut t = HyperlinkTable::new();
+        let long = format!("https://a.com/{}", "x".repeat(HyperlinkTable::MAX_URI_LEN));
+        assert_eq!(t.insert("", &long), 0);
+        assert_eq!(t.insert(&"k=v:".repeat(100), "https://a.com"), 0);
+        let uri = format!("https://a.com/{}", "x".repeat(2000));
+        while !t.is_full() {
+            assert_ne!(t.insert("", &uri), 0);
+        }
+        assert!(t.len() < HyperlinkTable::MAX_BYTES / 2000);
+        // Freeing links returns their bytes to the budget
+        t.retain(&[]);
+        assert!(t.is_empty() && !t.is_full());
+    }
+}
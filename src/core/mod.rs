@@ -2,8 +2,18 @@ mod parser;
 mod grid;
 mod utf8;
 mod handler;
+mod event;
+mod width;
+mod hyperlink;
+mod palette;
 
-pub use parser::{VtParser, Action};
-pub use grid::{Grid, Cell, CellAttr, Color};
+pub use parser::{VtParser, Action, Params, Perform, State, ParserLimits, ParserStats};
+pub use grid::{Grid, Cell, CellAttr, CellColor, Color, RowText, UnderlineStyle};
+pub use palette::{Palette, ANSI_COLORS};
 pub use utf8::{Utf8Decoder, char_width};
-pub use handler::{Terminal, MouseMode, MouseEncoding};
+pub use width::AmbiguousWidth;
+pub use hyperlink::{Hyperlink, HyperlinkTable};
+pub use event::TerminalEvent;
+pub use handler::{
+    Terminal, MouseMode, MouseEncoding, WindowOp, WindowOpPolicy, deny_window_ops, SYNC_UPDATE_TIMEOUT,
+};
@@ -0,0 +1,190 @@
+//! Color palette that cell colors resolve against at render and
+//! extraction time. Cells keep `CellColor::Default`/`Indexed` as written
+//! by SGR, so replacing the palette recolors the screen and scrollback.
+
+use crate::core::grid::{CellColor, Color};
+
+/// Standard 8 ANSI colors + bright variants
+pub const ANSI_COLORS: [Color; 16] = [
+    Color { r: 0,   g: 0,   b: 0   }, // 0 black
+    Color { r: 205, g: 49,  b: 49  }, // 1 red
+    Color { r: 13,  g: 188, b: 121 }, // 2 green
+    Color { r: 229, g: 229, b: 16  }, // 3 yellow
+    Color { r: 36,  g: 114, b: 200 }, // 4 blue
+    Color { r: 188, g: 63,  b: 188 }, // 5 magenta
+    Color { r: 17,  g: 168, b: 205 }, // 6 cyan
+    Color { r: 204, g: 204, b: 204 }, // 7 white
+    Color { r: 102, g: 102, b: 102 }, // 8 bright black
+    Color { r: 241, g: 76,  b: 76  }, // 9 bright red
+    Color { r: 35,  g: 209, b: 139 }, // 10 bright green
+    Color { r: 245, g: 245, b: 67  }, // 11 bright yellow
+    Color { r: 59,  g: 142, b: 234 }, // 12 bright blue
+    Color { r: 214, g: 112, b: 214 }, // 13 bright magenta
+    Color { r: 41,  g: 184, b: 219 }, // 14 bright cyan
+    Color { r: 242, g: 242, b: 242 }, // 15 bright white
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    /// Default foreground (SGR 39)
+    pub fg: Color,
+    /// Default background (SGR 49)
+    pub bg: Color,
+    pub cursor: Color,
+    /// 256-color table: 16 ANSI colors, 6x6x6 cube, grayscale ramp
+    pub colors: [Color; 256],
+}
+
+impl Palette {
+    pub fn new(fg: Color, bg: Color, cursor: Color, ansi: [Color; 16]) -> Self {
+        let mut colors = [Color::DEFAULT_FG; 256];
+        for (i, c) in colors.iter_mut().enumerate() {
+            *c = color_from_256(&ansi, i);
+        }
+        Self { fg, bg, cursor, colors }
+    }
+
+    pub fn resolve_fg(&self, color: CellColor) -> Color {
+        self.resolve(color, self.fg)
+    }
+
+    pub fn resolve_bg(&self, color: CellColor) -> Color {
+        self.resolve(color, self.bg)
+    }
+
+    /// Underline color; `CellColor::Default` follows the cell's foreground.
+    pub fn resolve_underline(&self, color: CellColor, fg: CellColor) -> Color {
+        match color {
+            CellColor::Default => self.resolve_fg(fg),
+            _ => self.resolve_fg(color),
+        }
+    }
+
+    fn resolve(&self, color: CellColor, default: Color) -> Color {
+        match color {
+            CellColor::Default => default,
+            CellColor::Indexed(idx) => self.colors[idx as usize],
+            CellColor::Rgb(c) => c,
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::new(Color::DEFAULT_FG, Color::DEFAULT_BG, Color::DEFAULT_FG, ANSI_COLORS)
+    }
+}
+
+/// Parse an X11 color spec as used by OSC 4/10/11/12: `rgb:R/G/B` with
+/// 1–4 hex digits per component, or `#RGB` .. `#RRRRGGGGBBBB`.
+pub fn parse_color_spec(spec: &str) -> Option<Color> {
+    if let Some(rgb) = spec.strip_prefix("rgb:") {
+        let mut parts = rgb.split('/').map(|h| {
+            let v = u32::from_str_radix(h, 16).ok().filter(|_| (1..=4).contains(&h.len()))?;
+            // Scale n hex digits to 8 bits
+            Some((v * 255 / ((1 << (4 * h.len())) - 1)) as u8)
+        });
+        let (r, g, b) = (parts.next()??, parts.next()??, parts.next()??);
+        return parts.next().is_none().then_some(Color { r, g, b });
+    }
+    let hex = spec.strip_prefix('#')?;
+    let n = hex.len() / 3;
+    if hex.len() % 3 != 0 || !(1..=4).contains(&n) || !hex.is_ascii() {
+        return None;
+    }
+    // `#` forms keep the most significant bits
+    let component = |i: usize| {
+        let v = u32::from_str_radix(&hex[i * n..(i + 1) * n], 16).ok()?;
+        Some(if n == 1 { (v << 4) as u8 } else { (v >> (4 * n - 8)) as u8 })
+    };
+    Some(Color { r: component(0)?, g: component(1)?, b: component(2)? })
+}
+
+/// Format a color for OSC query replies: `rgb:rrrr/gggg/bbbb`.
+pub fn format_color_spec(c: Color) -> String {
+    let wide = |v: u8| v as u16 * 257;
+    format!("rgb:{:04x}/{:04x}/{:04x}", wide(c.r), wide(c.g), wide(c.b))
+}
+
+/// xterm 256-color value for `idx`, with the first 16 taken from `ansi`.
+fn color_from_256(ansi: &[Color; 16], idx: usize) -> Color {
+    match idx {
+        0..=15 => ansi[idx],
+        16..=231 => {
+            // 6x6x6 color cube
+            let idx = idx - 16;
+            let r = (idx / 36) % 6;
+            let g = (idx / 6) % 6;
+            let b = idx % 6;
+            let to_val = |v: usize| if v == 0 { 0u8 } else { (55 + 40 * v) as u8 };
+            Color { r: to_val(r), g: to_val(g), b: to_val(b) }
+        }
+        232..=255 => {
+            // Grayscale ramp
+            let v = (8 + 10 * (idx - 232)) as u8;
+            Color { r: v, g: v, b: v }
+        }
+        _ => Color::DEFAULT_FG,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_from_256_ansi() {
+        assert_eq!(color_from_256(&ANSI_COLORS, 0), ANSI_COLORS[0]);
+        assert_eq!(color_from_256(&ANSI_COLORS, 15), ANSI_COLORS[15]);
+    }
+
+    #[test]
+    fn test_color_from_256_cube() {
+        // Index 16 = (0,0,0) = black
+        assert_eq!(color_from_256(&ANSI_COLORS, 16), Color { r: 0, g: 0, b: 0 });
+        // Index 231 = (5,5,5) = white-ish
+        assert_eq!(color_from_256(&ANSI_COLORS, 231), Color { r: 255, g: 255, b: 255 });
+    }
+
+    #[test]
+    fn test_color_from_256_grayscale() {
+        assert_eq!(color_from_256(&ANSI_COLORS, 232), Color { r: 8, g: 8, b: 8 });
+        assert_eq!(color_from_256(&ANSI_COLORS, 255), Color { r: 238, g: 238, b: 238 });
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut ansi = ANSI_COLORS;
+        ansi[1] = Color { r: 1, g: 2, b: 3 };
+        let bg = Color { r: 9, g: 9, b: 9 };
+        let p = Palette::new(Color::DEFAULT_FG, bg, Color::DEFAULT_FG, ansi);
+        assert_eq!(p.resolve_fg(CellColor::Indexed(1)), Color { r: 1, g: 2, b: 3 });
+        assert_eq!(p.resolve_fg(CellColor::Default), Color::DEFAULT_FG);
+        assert_eq!(p.resolve_bg(CellColor::Default), bg);
+        assert_eq!(p.resolve_bg(CellColor::Rgb(Color { r: 7, g: 7, b: 7 })), Color { r: 7, g: 7, b: 7 });
+        assert_eq!(p.resolve_underline(CellColor::Default, CellColor::Indexed(1)), Color { r: 1, g: 2, b: 3 });
+    }
+
+    #[test]
+    fn test_parse_color_spec() {
+        let c = Color { r: 0x12, g: 0xab, b: 0xff };
+        assert_eq!(parse_color_spec("rgb:12/ab/ff"), Some(c));
+        assert_eq!(parse_color_spec("rgb:1212/abab/ffff"), Some(c));
+        assert_eq!(parse_color_spec("rgb:f/0/8"), Some(Color { r: 255, g: 0, b: 136 }));
+        assert_eq!(parse_color_spec("#12abff"), Some(c));
+        assert_eq!(parse_color_spec("#123abcfff"), Some(c));
+        assert_eq!(parse_color_spec("#f08"), Some(Color { r: 0xf0, g: 0, b: 0x80 }));
+        assert_eq!(parse_color_spec("rgb:12/ab"), None);
+        assert_eq!(parse_color_spec("rgb:12/ab/ff/00"), None);
+        assert_eq!(parse_color_spec("rgb:12345/0/0"), None);
+        assert_eq!(parse_color_spec("#12345"), None);
+        assert_eq!(parse_color_spec("red"), None);
+    }
+
+    #[test]
+    fn test_format_color_spec_roundtrip() {
+        let c = Color { r: 0x12, g: 0xab, b: 0xff };
+        assert_eq!(format_color_spec(c), "rgb:1212/abab/ffff");
+        assert_eq!(parse_color_spec(&format_color_spec(c)), Some(c));
+    }
+}
@@ -1,3 +1,5 @@
+use crate::core::utf8::Utf8Decoder;
+
 /// Table-driven VT parser based on Paul Williams' state diagram.
 /// Reference: https://vt100.net/emu/dec_ansi_parser
 
@@ -19,6 +21,67 @@ pub enum State {
     SosPmApcString,
 }
 
+/// CSI/DCS parameters, grouped by `;` with `:` sub-parameters kept together.
+/// `38:2::10:20:30;1` holds two groups: `[38, 2, 0, 10, 20, 30]` and `[1]`,
+/// while `38;2;10;20;30` holds five single-value groups. Values saturate at
+/// `u16::MAX`, so `99999` reads as 65535.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Params {
+    values: Vec<u16>,
+    /// Start index in `values` of each group
+    groups: Vec<usize>,
+}
+
+impl Params {
+    pub fn new() -> Self {
+        Self { values: Vec::with_capacity(16), groups: Vec::with_capacity(16) }
+    }
+
+    /// Number of `;`-separated groups.
+    pub fn len(&self) -> usize { self.groups.len() }
+
+    pub fn is_empty(&self) -> bool { self.groups.is_empty() }
+
+    /// Group at `idx`: the parameter followed by its sub-parameters.
+    pub fn get(&self, idx: usize) -> Option<&[u16]> {
+        let start = *self.groups.get(idx)?;
+        let end = self.groups.get(idx + 1).copied().unwrap_or(self.values.len());
+        Some(&self.values[start..end])
+    }
+
+    /// Iterate over groups.
+    pub fn iter(&self) -> impl Iterator<Item = &[u16]> + '_ {
+        (0..self.len()).filter_map(move |i| self.get(i))
+    }
+
+    /// Start a new group.
+    fn push(&mut self, value: u16) {
+        self.groups.push(self.values.len());
+        self.values.push(value);
+    }
+
+    /// Append a sub-parameter to the last group.
+    fn extend(&mut self, value: u16) {
+        if self.groups.is_empty() {
+            self.groups.push(0);
+        }
+        self.values.push(value);
+    }
+
+    fn clear(&mut self) {
+        self.values.clear();
+        self.groups.clear();
+    }
+}
+
+impl From<Vec<u16>> for Params {
+    /// One group per value, as if separated by `;`.
+    fn from(values: Vec<u16>) -> Self {
+        let groups = (0..values.len()).collect();
+        Self { values, groups }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Action {
     /// Printable character
@@ -28,7 +91,7 @@ pub enum Action {
     /// CSI dispatch: final byte, params, intermediates
     CsiDispatch {
         final_byte: u8,
-        params: Vec<u16>,
+        params: Params,
         intermediates: Vec<u8>,
     },
     /// ESC dispatch
@@ -38,247 +101,737 @@ pub enum Action {
     },
     /// OSC string complete
     OscDispatch(Vec<u8>),
-    /// No action
-    None,
+    /// DCS introducer complete: params, intermediates, final byte.
+    /// Followed by zero or more `DcsPut` and exactly one `DcsUnhook`.
+    DcsHook {
+        params: Params,
+        intermediates: Vec<u8>,
+        final_byte: u8,
+    },
+    /// One byte of DCS payload
+    DcsPut(u8),
+    /// DCS string terminated (ST, ESC, CAN or SUB)
+    DcsUnhook,
+    /// APC string complete (e.g. Kitty graphics `G...`). SOS and PM are discarded.
+    ApcDispatch(Vec<u8>),
+}
+
+/// Receiver for parsed actions. `VtParser::parse` calls these with slices
+/// borrowed from the parser's buffers, so dispatch does not allocate.
+pub trait Perform {
+    /// Printable character
+    fn print(&mut self, ch: char);
+
+    /// Run of printable characters decoded from the ground state.
+    /// Override to write the whole run at once.
+    fn print_str(&mut self, s: &str) {
+        for ch in s.chars() {
+            self.print(ch);
+        }
+    }
+
+    /// C0 control (BEL, BS, HT, LF, CR, etc.)
+    fn execute(&mut self, byte: u8);
+
+    /// CSI sequence complete
+    fn csi_dispatch(&mut self, final_byte: u8, params: &Params, intermediates: &[u8]);
+
+    /// ESC sequence complete
+    fn esc_dispatch(&mut self, final_byte: u8, intermediates: &[u8]);
+
+    /// OSC string complete. `bell_terminated` is true when it ended with
+    /// BEL rather than ST, so replies can use the same terminator.
+    fn osc_dispatch(&mut self, data: &[u8], bell_terminated: bool);
+
+    /// DCS introducer complete. Followed by `put` bytes and one `unhook`.
+    fn hook(&mut self, _params: &Params, _intermediates: &[u8], _final_byte: u8) {}
+
+    /// One byte of DCS payload
+    fn put(&mut self, _byte: u8) {}
+
+    /// DCS string terminated
+    fn unhook(&mut self) {}
+
+    /// APC string complete
+    fn apc_dispatch(&mut self, _data: &[u8]) {}
+}
+
+/// Maximum APC payload kept by the parser. Longer strings are discarded.
+pub const MAX_APC_LEN: usize = 1 << 20;
+
+/// Bounds on what a single sequence may make the parser buffer.
+/// Numeric parameters never need a limit: each value saturates at `u16::MAX`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParserLimits {
+    /// Parameter values per CSI/DCS sequence, sub-parameters included.
+    /// Extra values are dropped.
+    pub max_params: usize,
+    /// Intermediate bytes (and private markers) per sequence. Extra bytes are dropped.
+    pub max_intermediates: usize,
+    /// OSC string bytes. Longer strings are discarded, not dispatched.
+    pub max_osc_len: usize,
+    /// DCS payload bytes passed to `Perform::put`. The rest is dropped.
+    pub max_dcs_len: usize,
+    /// APC string bytes. Longer strings are discarded, not dispatched.
+    pub max_apc_len: usize,
+}
+
+impl Default for ParserLimits {
+    fn default() -> Self {
+        Self {
+            max_params: 32,
+            max_intermediates: 4,
+            max_osc_len: 1 << 20,
+            max_dcs_len: 1 << 24,
+            max_apc_len: MAX_APC_LEN,
+        }
+    }
+}
+
+/// Counts of input the parser dropped because it exceeded `ParserLimits`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParserStats {
+    /// Parameter values dropped
+    pub params_dropped: u64,
+    /// Intermediate bytes dropped
+    pub intermediates_dropped: u64,
+    /// OSC strings discarded
+    pub osc_discarded: u64,
+    /// DCS strings whose payload was cut short
+    pub dcs_truncated: u64,
+    /// APC strings discarded
+    pub apc_discarded: u64,
 }
 
 pub struct VtParser {
     state: State,
-    params: Vec<u16>,
+    params: Params,
     current_param: u16,
+    /// Next finished value continues the last group (after `:`)
+    subparam: bool,
     intermediates: Vec<u8>,
     osc_data: Vec<u8>,
+    /// OSC string exceeded `max_osc_len`; discard it on termination
+    osc_overflow: bool,
+    /// DCS payload bytes passed on so far
+    dcs_len: usize,
+    /// APC payload; `None` while inside SOS/PM or after exceeding `max_apc_len`
+    apc_data: Option<Vec<u8>>,
+    /// UTF-8 sequence split across `parse` calls
+    utf8: Utf8Decoder,
+    /// Treat 0x80–0x9F as C1 controls (S8C1T / `terminal.c1_controls`)
+    c1_controls: bool,
+    /// What RIS puts `c1_controls` back to: the configured setting
+    c1_default: bool,
+    /// Reused by `advance`, so feeding a byte doesn't allocate
+    advanced: Vec<Action>,
+    limits: ParserLimits,
+    stats: ParserStats,
 }
 
 impl VtParser {
     pub fn new() -> Self {
+        Self::with_limits(ParserLimits::default())
+    }
+
+    pub fn with_limits(limits: ParserLimits) -> Self {
         Self {
             state: State::Ground,
-            params: Vec::with_capacity(16),
+            params: Params::new(),
             current_param: 0,
+            subparam: false,
             intermediates: Vec::with_capacity(4),
             osc_data: Vec::with_capacity(256),
+            osc_overflow: false,
+            dcs_len: 0,
+            apc_data: None,
+            utf8: Utf8Decoder::new(),
+            c1_controls: false,
+            c1_default: false,
+            advanced: Vec::with_capacity(2),
+            limits,
+            stats: ParserStats::default(),
+        }
+    }
+
+    pub fn limits(&self) -> &ParserLimits {
+        &self.limits
+    }
+
+    pub fn set_limits(&mut self, limits: ParserLimits) {
+        self.limits = limits;
+    }
+
+    /// Input dropped so far because it exceeded the limits.
+    pub fn stats(&self) -> &ParserStats {
+        &self.stats
+    }
+
+    /// Bytes currently held for an unfinished sequence.
+    pub fn buffered_len(&self) -> usize {
+        self.params.values.len() * std::mem::size_of::<u16>()
+            + self.params.groups.len() * std::mem::size_of::<usize>()
+            + self.intermediates.len()
+            + self.osc_data.len()
+            + self.apc_data.as_ref().map_or(0, Vec::len)
+    }
+
+    /// Whether 8-bit C1 controls are recognized.
+    pub fn c1_controls(&self) -> bool {
+        self.c1_controls
+    }
+
+    /// Recognize 0x80–0x9F as C1 controls equivalent to `ESC 0x40–0x5F`.
+    /// A byte that continues a UTF-8 sequence, or sits inside an OSC/DCS/APC
+    /// payload, is never taken as C1, so UTF-8 text still decodes.
+    /// This is also the setting RIS (`ESC c`) returns to after S8C1T/S7C1T.
+    pub fn set_c1_controls(&mut self, enabled: bool) {
+        self.c1_controls = enabled;
+        self.c1_default = enabled;
+    }
+
+    /// Current state of the state machine.
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Feed a single byte, return the actions it completes: usually none
+    /// or one, but a byte that interrupts a UTF-8 sequence also yields the
+    /// U+FFFD print before its own action. The slice is only valid until
+    /// the next call.
+    pub fn advance(&mut self, byte: u8) -> &[Action] {
+        let mut collector = ActionCollector(std::mem::take(&mut self.advanced));
+        collector.0.clear();
+        self.advance_with(&mut collector, byte);
+        self.advanced = collector.0;
+        &self.advanced
+    }
+
+    /// Feed a slice of bytes, collecting all actions.
+    pub fn feed(&mut self, data: &[u8]) -> Vec<Action> {
+        let mut collector = ActionCollector(Vec::new());
+        self.parse(&mut collector, data);
+        collector.0
+    }
+
+    /// Feed a slice of bytes, calling `performer` for each completed action.
+    /// In the ground state, runs of printable bytes are decoded as UTF-8 and
+    /// passed to `print_str` in one call.
+    pub fn parse<P: Perform>(&mut self, performer: &mut P, data: &[u8]) {
+        let mut i = 0;
+        while i < data.len() {
+            if self.state == State::Ground && !self.utf8.is_pending() {
+                let run = data[i..].iter()
+                    .position(|&b| b < 0x20 || b == 0x7f)
+                    .unwrap_or(data.len() - i);
+                if run > 0 {
+                    let consumed = self.print_run(performer, &data[i..i + run]);
+                    i += consumed;
+                    if consumed == run {
+                        continue;
+                    }
+                }
+            }
+            self.advance_with(performer, data[i]);
+            i += 1;
         }
     }
 
-    /// Feed a single byte, return the resulting action.
-    pub fn advance(&mut self, byte: u8) -> Action {
+    /// Decode and print a run of printable bytes. An incomplete UTF-8
+    /// sequence at the end is left for `advance_with`; returns bytes consumed.
+    fn print_run<P: Perform>(&mut self, performer: &mut P, run: &[u8]) -> usize {
+        let mut rest = run;
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(s) => {
+                    performer.print_str(s);
+                    return run.len();
+                }
+                Err(e) => {
+                    let (valid, tail) = rest.split_at(e.valid_up_to());
+                    if let Ok(s) = std::str::from_utf8(valid) {
+                        if !s.is_empty() {
+                            performer.print_str(s);
+                        }
+                    }
+                    match e.error_len() {
+                        // A stray 0x80–0x9F is a C1 control; leave it for `advance_with`
+                        Some(_) if self.c1_controls && (0x80..=0x9f).contains(&tail[0]) => {
+                            return run.len() - tail.len();
+                        }
+                        Some(n) => {
+                            performer.print(char::REPLACEMENT_CHARACTER);
+                            rest = &tail[n..];
+                        }
+                        None => return run.len() - tail.len(),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Feed a single byte, calling `performer` if it completes an action.
+    pub fn advance_with<P: Perform>(&mut self, performer: &mut P, byte: u8) {
+        // A byte that can't continue a pending UTF-8 sequence ends it
+        if self.utf8.is_pending() && byte & 0xc0 != 0x80 {
+            self.utf8.reset();
+            performer.print(char::REPLACEMENT_CHARACTER);
+        }
+
         // Anywhere transitions (highest priority)
         match byte {
             0x18 | 0x1a => {
+                if self.state == State::DcsPassthrough {
+                    performer.unhook();
+                } else {
+                    performer.execute(byte);
+                }
+                self.apc_data = None;
                 self.state = State::Ground;
-                return Action::Execute(byte);
+                return;
             }
             0x1b => {
+                // ESC terminates OSC/DCS strings (ESC \ is the 7-bit ST)
+                match self.state {
+                    State::OscString => self.osc_end(performer, false),
+                    State::SosPmApcString => {
+                        if let Some(data) = &self.apc_data {
+                            performer.apc_dispatch(data);
+                        }
+                    }
+                    State::DcsPassthrough => performer.unhook(),
+                    _ => {}
+                }
                 self.clear();
                 self.state = State::Escape;
-                return Action::None;
+                return;
+            }
+            0x80..=0x9f if self.c1_controls && !self.utf8.is_pending() && !self.in_string() => {
+                self.c1(performer, byte);
+                return;
             }
             _ => {}
         }
 
         match self.state {
-            State::Ground => self.ground(byte),
-            State::Escape => self.escape(byte),
-            State::EscapeIntermediate => self.escape_intermediate(byte),
-            State::CsiEntry => self.csi_entry(byte),
-            State::CsiParam => self.csi_param(byte),
-            State::CsiIntermediate => self.csi_intermediate(byte),
+            State::Ground => self.ground(performer, byte),
+            State::Escape => self.escape(performer, byte),
+            State::EscapeIntermediate => self.escape_intermediate(performer, byte),
+            State::CsiEntry => self.csi_entry(performer, byte),
+            State::CsiParam => self.csi_param(performer, byte),
+            State::CsiIntermediate => self.csi_intermediate(performer, byte),
             State::CsiIgnore => self.csi_ignore(byte),
-            State::OscString => self.osc_string(byte),
-            _ => Action::None, // TODO: DCS, SOS/PM/APC
+            State::OscString => self.osc_string(performer, byte),
+            State::DcsEntry => self.dcs_entry(performer, byte),
+            State::DcsParam => self.dcs_param(performer, byte),
+            State::DcsIntermediate => self.dcs_intermediate(performer, byte),
+            State::DcsPassthrough => self.dcs_passthrough(performer, byte),
+            State::DcsIgnore => self.dcs_ignore(byte),
+            State::SosPmApcString => self.sos_pm_apc_string(performer, byte),
         }
     }
 
-    /// Feed a slice of bytes, collecting all actions.
-    pub fn feed(&mut self, data: &[u8]) -> Vec<Action> {
-        data.iter()
-            .map(|&b| self.advance(b))
-            .filter(|a| *a != Action::None)
-            .collect()
+    /// Inside an OSC/DCS/SOS/PM/APC payload, where only ST (0x9C) is
+    /// special, and only when C1 controls are recognized.
+    fn in_string(&self) -> bool {
+        matches!(self.state,
+            State::OscString | State::DcsPassthrough | State::DcsIgnore | State::SosPmApcString)
+    }
+
+    /// 8-bit C1 control: same transition as `ESC` followed by `byte - 0x40`.
+    fn c1<P: Perform>(&mut self, performer: &mut P, byte: u8) {
+        self.clear();
+        match byte {
+            0x90 => self.state = State::DcsEntry,
+            0x9b => self.state = State::CsiEntry,
+            0x9d => self.state = State::OscString,
+            0x98 | 0x9e => self.state = State::SosPmApcString,
+            0x9f => {
+                self.apc_data = Some(Vec::new());
+                self.state = State::SosPmApcString;
+            }
+            0x9c => self.state = State::Ground, // ST with nothing to terminate
+            _ => {
+                // IND, NEL, HTS, RI, ...
+                self.state = State::Ground;
+                performer.execute(byte);
+            }
+        }
     }
 
     fn clear(&mut self) {
         self.params.clear();
         self.current_param = 0;
+        self.subparam = false;
         self.intermediates.clear();
         self.osc_data.clear();
+        self.osc_overflow = false;
+        self.apc_data = None;
+    }
+
+    fn push_intermediate(&mut self, byte: u8) {
+        if self.intermediates.len() < self.limits.max_intermediates {
+            self.intermediates.push(byte);
+        } else {
+            self.stats.intermediates_dropped += 1;
+        }
     }
 
-    fn ground(&mut self, byte: u8) -> Action {
+    /// Store the value being accumulated as a new group or a sub-parameter.
+    fn finish_param(&mut self) {
+        if self.params.values.len() >= self.limits.max_params {
+            self.stats.params_dropped += 1;
+            self.current_param = 0;
+            return;
+        }
+        if self.subparam {
+            self.params.extend(self.current_param);
+        } else {
+            self.params.push(self.current_param);
+        }
+        self.current_param = 0;
+    }
+
+    fn ground<P: Perform>(&mut self, performer: &mut P, byte: u8) {
         match byte {
-            0x00..=0x1f => Action::Execute(byte),
-            0x20..=0x7e => Action::Print(byte as char),
-            0x7f => Action::None, // DEL
+            0x00..=0x1f => performer.execute(byte),
+            0x20..=0x7e => performer.print(byte as char),
+            0x7f => {} // DEL
             0x80..=0xff => {
-                // UTF-8 lead/continuation — simplified: treat as printable
-                Action::Print(char::REPLACEMENT_CHARACTER)
+                if let Some(ch) = self.utf8.feed(byte) {
+                    performer.print(ch);
+                }
             }
         }
     }
 
-    fn escape(&mut self, byte: u8) -> Action {
+    fn escape<P: Perform>(&mut self, performer: &mut P, byte: u8) {
         match byte {
             0x20..=0x2f => {
-                self.intermediates.push(byte);
+                self.push_intermediate(byte);
                 self.state = State::EscapeIntermediate;
-                Action::None
             }
             0x5b => {
                 // '[' → CSI
                 self.clear();
                 self.state = State::CsiEntry;
-                Action::None
             }
             0x5d => {
                 // ']' → OSC
-                self.osc_data.clear();
+                self.clear();
                 self.state = State::OscString;
-                Action::None
+            }
+            0x50 => {
+                // 'P' → DCS
+                self.clear();
+                self.state = State::DcsEntry;
+            }
+            0x58 | 0x5e => {
+                // 'X' → SOS, '^' → PM (payload discarded)
+                self.apc_data = None;
+                self.state = State::SosPmApcString;
+            }
+            0x5f => {
+                // '_' → APC
+                self.apc_data = Some(Vec::new());
+                self.state = State::SosPmApcString;
             }
             0x30..=0x7e => {
                 self.state = State::Ground;
-                Action::EscDispatch {
-                    final_byte: byte,
-                    intermediates: self.intermediates.clone(),
+                // RIS undoes S8C1T, as the terminal drops 8-bit replies
+                if byte == b'c' {
+                    self.c1_controls = self.c1_default;
                 }
+                performer.esc_dispatch(byte, &self.intermediates);
             }
-            _ => Action::None,
+            _ => {}
         }
     }
 
-    fn escape_intermediate(&mut self, byte: u8) -> Action {
+    fn escape_intermediate<P: Perform>(&mut self, performer: &mut P, byte: u8) {
         match byte {
-            0x20..=0x2f => {
-                self.intermediates.push(byte);
-                Action::None
-            }
+            0x20..=0x2f => self.push_intermediate(byte),
             0x30..=0x7e => {
                 self.state = State::Ground;
-                Action::EscDispatch {
-                    final_byte: byte,
-                    intermediates: self.intermediates.clone(),
+                // S7C1T (ESC SP F) / S8C1T (ESC SP G)
+                if self.intermediates == b" " {
+                    match byte {
+                        b'F' => self.c1_controls = false,
+                        b'G' => self.c1_controls = true,
+                        _ => {}
+                    }
                 }
+                performer.esc_dispatch(byte, &self.intermediates);
             }
-            _ => Action::None,
+            _ => {}
         }
     }
 
-    fn csi_entry(&mut self, byte: u8) -> Action {
+    fn csi_entry<P: Perform>(&mut self, performer: &mut P, byte: u8) {
         match byte {
             0x30..=0x39 => {
                 self.current_param = (byte - b'0') as u16;
                 self.state = State::CsiParam;
-                Action::None
             }
             0x3b => {
-                self.params.push(0);
+                self.finish_param();
+                self.state = State::CsiParam;
+            }
+            0x3a => {
+                self.finish_param();
+                self.subparam = true;
                 self.state = State::CsiParam;
-                Action::None
             }
             0x3c..=0x3f => {
                 // Private marker (e.g., '?')
-                self.intermediates.push(byte);
+                self.push_intermediate(byte);
                 self.state = State::CsiParam;
-                Action::None
             }
             0x20..=0x2f => {
-                self.intermediates.push(byte);
+                self.push_intermediate(byte);
                 self.state = State::CsiIntermediate;
-                Action::None
             }
             0x40..=0x7e => {
                 self.state = State::Ground;
-                Action::CsiDispatch {
-                    final_byte: byte,
-                    params: self.params.clone(),
-                    intermediates: self.intermediates.clone(),
-                }
+                performer.csi_dispatch(byte, &self.params, &self.intermediates);
             }
-            _ => Action::None,
+            _ => {}
         }
     }
 
-    fn csi_param(&mut self, byte: u8) -> Action {
+    fn csi_param<P: Perform>(&mut self, performer: &mut P, byte: u8) {
         match byte {
             0x30..=0x39 => {
                 self.current_param = self.current_param.saturating_mul(10)
                     .saturating_add((byte - b'0') as u16);
-                Action::None
             }
             0x3b => {
-                self.params.push(self.current_param);
-                self.current_param = 0;
-                Action::None
+                self.finish_param();
+                self.subparam = false;
+            }
+            0x3a => {
+                self.finish_param();
+                self.subparam = true;
             }
             0x20..=0x2f => {
-                self.params.push(self.current_param);
-                self.intermediates.push(byte);
+                self.finish_param();
+                self.push_intermediate(byte);
                 self.state = State::CsiIntermediate;
-                Action::None
             }
             0x40..=0x7e => {
-                self.params.push(self.current_param);
+                self.finish_param();
                 self.state = State::Ground;
-                Action::CsiDispatch {
-                    final_byte: byte,
-                    params: self.params.clone(),
-                    intermediates: self.intermediates.clone(),
-                }
-            }
-            _ => {
-                self.state = State::CsiIgnore;
-                Action::None
+                performer.csi_dispatch(byte, &self.params, &self.intermediates);
             }
+            _ => self.state = State::CsiIgnore,
         }
     }
 
-    fn csi_intermediate(&mut self, byte: u8) -> Action {
+    fn csi_intermediate<P: Perform>(&mut self, performer: &mut P, byte: u8) {
         match byte {
-            0x20..=0x2f => {
-                self.intermediates.push(byte);
-                Action::None
-            }
+            0x20..=0x2f => self.push_intermediate(byte),
             0x40..=0x7e => {
                 self.state = State::Ground;
-                Action::CsiDispatch {
-                    final_byte: byte,
-                    params: self.params.clone(),
-                    intermediates: self.intermediates.clone(),
-                }
-            }
-            _ => {
-                self.state = State::CsiIgnore;
-                Action::None
+                performer.csi_dispatch(byte, &self.params, &self.intermediates);
             }
+            _ => self.state = State::CsiIgnore,
         }
     }
 
-    fn csi_ignore(&mut self, byte: u8) -> Action {
+    fn csi_ignore(&mut self, byte: u8) {
         if (0x40..=0x7e).contains(&byte) {
             self.state = State::Ground;
         }
-        Action::None
     }
 
-    fn osc_string(&mut self, byte: u8) -> Action {
+    fn osc_string<P: Perform>(&mut self, performer: &mut P, byte: u8) {
         match byte {
+            // BEL or ST terminates OSC
             0x07 => {
-                // BEL terminates OSC
                 self.state = State::Ground;
-                Action::OscDispatch(self.osc_data.clone())
+                self.osc_end(performer, true);
+            }
+            0x9c if self.c1_controls => {
+                self.state = State::Ground;
+                self.osc_end(performer, false);
             }
-            0x9c => {
-                // ST terminates OSC
+            _ if self.osc_overflow => {}
+            _ if self.osc_data.len() >= self.limits.max_osc_len => {
+                // Oversized OSC: drop it rather than dispatch a truncated payload
+                self.osc_overflow = true;
+                self.osc_data = Vec::with_capacity(256);
+                self.stats.osc_discarded += 1;
+            }
+            _ => self.osc_data.push(byte),
+        }
+    }
+
+    fn osc_end<P: Perform>(&mut self, performer: &mut P, bell_terminated: bool) {
+        if !self.osc_overflow {
+            performer.osc_dispatch(&self.osc_data, bell_terminated);
+        }
+    }
+
+    fn sos_pm_apc_string<P: Perform>(&mut self, performer: &mut P, byte: u8) {
+        match byte {
+            0x9c if self.c1_controls => {
+                // ST terminates the string
+                self.state = State::Ground;
+                if let Some(data) = self.apc_data.take() {
+                    performer.apc_dispatch(&data);
+                }
+            }
+            _ => {
+                if let Some(data) = &mut self.apc_data {
+                    if data.len() < self.limits.max_apc_len {
+                        data.push(byte);
+                    } else {
+                        // Oversized APC: drop it rather than dispatch a truncated payload
+                        self.apc_data = None;
+                        self.stats.apc_discarded += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    fn dcs_entry<P: Perform>(&mut self, performer: &mut P, byte: u8) {
+        match byte {
+            0x30..=0x39 => {
+                self.current_param = (byte - b'0') as u16;
+                self.state = State::DcsParam;
+            }
+            0x3b => {
+                self.finish_param();
+                self.state = State::DcsParam;
+            }
+            0x3a => self.state = State::DcsIgnore,
+            0x3c..=0x3f => {
+                // Private marker
+                self.push_intermediate(byte);
+                self.state = State::DcsParam;
+            }
+            0x20..=0x2f => {
+                self.push_intermediate(byte);
+                self.state = State::DcsIntermediate;
+            }
+            0x40..=0x7e => self.dcs_hook(performer, byte),
+            _ => {} // C0 and DEL ignored
+        }
+    }
+
+    fn dcs_param<P: Perform>(&mut self, performer: &mut P, byte: u8) {
+        match byte {
+            0x30..=0x39 => {
+                self.current_param = self.current_param.saturating_mul(10)
+                    .saturating_add((byte - b'0') as u16);
+            }
+            0x3b => self.finish_param(),
+            0x3a | 0x3c..=0x3f => self.state = State::DcsIgnore,
+            0x20..=0x2f => {
+                self.finish_param();
+                self.push_intermediate(byte);
+                self.state = State::DcsIntermediate;
+            }
+            0x40..=0x7e => {
+                self.finish_param();
+                self.dcs_hook(performer, byte);
+            }
+            _ => {}
+        }
+    }
+
+    fn dcs_intermediate<P: Perform>(&mut self, performer: &mut P, byte: u8) {
+        match byte {
+            0x20..=0x2f => self.push_intermediate(byte),
+            0x30..=0x3f => self.state = State::DcsIgnore,
+            0x40..=0x7e => self.dcs_hook(performer, byte),
+            _ => {}
+        }
+    }
+
+    fn dcs_hook<P: Perform>(&mut self, performer: &mut P, final_byte: u8) {
+        self.state = State::DcsPassthrough;
+        self.dcs_len = 0;
+        performer.hook(&self.params, &self.intermediates, final_byte);
+    }
+
+    fn dcs_passthrough<P: Perform>(&mut self, performer: &mut P, byte: u8) {
+        match byte {
+            0x9c if self.c1_controls => {
+                // ST terminates DCS
                 self.state = State::Ground;
-                Action::OscDispatch(self.osc_data.clone())
+                performer.unhook();
+            }
+            0x7f => {}
+            _ if self.dcs_len < self.limits.max_dcs_len => {
+                self.dcs_len += 1;
+                performer.put(byte);
             }
             _ => {
-                self.osc_data.push(byte);
-                Action::None
+                if self.dcs_len == self.limits.max_dcs_len {
+                    // Count the string once, then stop counting bytes
+                    self.dcs_len += 1;
+                    self.stats.dcs_truncated += 1;
+                }
             }
         }
     }
+
+    fn dcs_ignore(&mut self, byte: u8) {
+        if byte == 0x9c && self.c1_controls {
+            self.state = State::Ground;
+        }
+    }
+}
+
+/// Adapter behind `advance`/`feed`: records actions in order.
+struct ActionCollector(Vec<Action>);
+
+impl Perform for ActionCollector {
+    fn print(&mut self, ch: char) {
+        self.0.push(Action::Print(ch));
+    }
+
+    fn execute(&mut self, byte: u8) {
+        self.0.push(Action::Execute(byte));
+    }
+
+    fn csi_dispatch(&mut self, final_byte: u8, params: &Params, intermediates: &[u8]) {
+        self.0.push(Action::CsiDispatch {
+            final_byte,
+            params: params.clone(),
+            intermediates: intermediates.to_vec(),
+        });
+    }
+
+    fn esc_dispatch(&mut self, final_byte: u8, intermediates: &[u8]) {
+        self.0.push(Action::EscDispatch { final_byte, intermediates: intermediates.to_vec() });
+    }
+
+    fn osc_dispatch(&mut self, data: &[u8], _bell_terminated: bool) {
+        self.0.push(Action::OscDispatch(data.to_vec()));
+    }
+
+    fn hook(&mut self, params: &Params, intermediates: &[u8], final_byte: u8) {
+        self.0.push(Action::DcsHook {
+            params: params.clone(),
+            intermediates: intermediates.to_vec(),
+            final_byte,
+        });
+    }
+
+    fn put(&mut self, byte: u8) {
+        self.0.push(Action::DcsPut(byte));
+    }
+
+    fn unhook(&mut self) {
+        self.0.push(Action::DcsUnhook);
+    }
+
+    fn apc_dispatch(&mut self, data: &[u8]) {
+        self.0.push(Action::ApcDispatch(data.to_vec()));
+    }
 }
 
 impl Default for VtParser {
@@ -294,7 +847,7 @@ mod tests {
     #[test]
     fn test_printable() {
         let mut p = VtParser::new();
-        assert_eq!(p.advance(b'A'), Action::Print('A'));
+        assert_eq!(p.advance(b'A'), [Action::Print('A')]);
     }
 
     #[test]
@@ -302,7 +855,7 @@ mod tests {
         let mut p = VtParser::new();
         let actions = p.feed(b"\x1b[5A");
         assert_eq!(actions, vec![Action::CsiDispatch {
-            final_byte: b'A', params: vec![5], intermediates: vec![],
+            final_byte: b'A', params: vec![5].into(), intermediates: vec![],
         }]);
     }
 
@@ -311,7 +864,7 @@ mod tests {
         let mut p = VtParser::new();
         let actions = p.feed(b"\x1b[1;31m");
         assert_eq!(actions, vec![Action::CsiDispatch {
-            final_byte: b'm', params: vec![1, 31], intermediates: vec![],
+            final_byte: b'm', params: vec![1, 31].into(), intermediates: vec![],
         }]);
     }
 
@@ -327,7 +880,7 @@ mod tests {
         let mut p = VtParser::new();
         let actions = p.feed(b"\x1b[H"); // CUP with no params
         assert_eq!(actions, vec![Action::CsiDispatch {
-            final_byte: b'H', params: vec![], intermediates: vec![],
+            final_byte: b'H', params: vec![].into(), intermediates: vec![],
         }]);
     }
 
@@ -336,7 +889,7 @@ mod tests {
         let mut p = VtParser::new();
         let actions = p.feed(b"\x1b[?1049h");
         assert_eq!(actions, vec![Action::CsiDispatch {
-            final_byte: b'h', params: vec![1049], intermediates: vec![b'?'],
+            final_byte: b'h', params: vec![1049].into(), intermediates: vec![b'?'],
         }]);
     }
 
@@ -352,11 +905,11 @@ mod tests {
     #[test]
     fn test_c0_controls() {
         let mut p = VtParser::new();
-        assert_eq!(p.advance(0x0a), Action::Execute(0x0a)); // LF
-        assert_eq!(p.advance(0x0d), Action::Execute(0x0d)); // CR
-        assert_eq!(p.advance(0x08), Action::Execute(0x08)); // BS
-        assert_eq!(p.advance(0x09), Action::Execute(0x09)); // HT
-        assert_eq!(p.advance(0x07), Action::Execute(0x07)); // BEL
+        assert_eq!(p.advance(0x0a), [Action::Execute(0x0a)]); // LF
+        assert_eq!(p.advance(0x0d), [Action::Execute(0x0d)]); // CR
+        assert_eq!(p.advance(0x08), [Action::Execute(0x08)]); // BS
+        assert_eq!(p.advance(0x09), [Action::Execute(0x09)]); // HT
+        assert_eq!(p.advance(0x07), [Action::Execute(0x07)]); // BEL
     }
 
     #[test]
@@ -365,7 +918,7 @@ mod tests {
         // Start ESC, then another ESC interrupts
         let actions = p.feed(b"\x1b\x1b[5A");
         assert_eq!(actions, vec![Action::CsiDispatch {
-            final_byte: b'A', params: vec![5], intermediates: vec![],
+            final_byte: b'A', params: vec![5].into(), intermediates: vec![],
         }]);
     }
 
@@ -374,7 +927,7 @@ mod tests {
         let mut p = VtParser::new();
         let actions = p.feed(b"\x1b[1;2;3;4;5m");
         assert_eq!(actions, vec![Action::CsiDispatch {
-            final_byte: b'm', params: vec![1, 2, 3, 4, 5], intermediates: vec![],
+            final_byte: b'm', params: vec![1, 2, 3, 4, 5].into(), intermediates: vec![],
         }]);
     }
 
@@ -384,7 +937,7 @@ mod tests {
         // ESC [ ; H → params should be [0, 0] (both default)
         let actions = p.feed(b"\x1b[;H");
         assert_eq!(actions, vec![Action::CsiDispatch {
-            final_byte: b'H', params: vec![0, 0], intermediates: vec![],
+            final_byte: b'H', params: vec![0, 0].into(), intermediates: vec![],
         }]);
     }
 
@@ -395,7 +948,7 @@ mod tests {
         assert_eq!(actions, vec![
             Action::Print('A'),
             Action::Print('B'),
-            Action::CsiDispatch { final_byte: b'm', params: vec![1], intermediates: vec![] },
+            Action::CsiDispatch { final_byte: b'm', params: vec![1].into(), intermediates: vec![] },
             Action::Print('C'),
             Action::Print('D'),
         ]);
@@ -404,6 +957,7 @@ mod tests {
     #[test]
     fn test_osc_st_terminator() {
         let mut p = VtParser::new();
+        p.set_c1_controls(true);
         let actions = p.feed(b"\x1b]0;title\x9c"); // ST = 0x9C
         assert_eq!(actions, vec![Action::OscDispatch(b"0;title".to_vec())]);
     }
@@ -420,9 +974,375 @@ mod tests {
         ]);
     }
 
+    #[test]
+    fn test_advance_returns_every_action() {
+        let mut p = VtParser::new();
+        assert!(p.advance(0xe4).is_empty());
+        // The control ends the incomplete sequence: U+FFFD, then LF
+        assert_eq!(p.advance(0x0a), [Action::Print(char::REPLACEMENT_CHARACTER), Action::Execute(0x0a)]);
+    }
+
     #[test]
     fn test_del_ignored() {
         let mut p = VtParser::new();
-        assert_eq!(p.advance(0x7f), Action::None);
+        assert!(p.advance(0x7f).is_empty());
+    }
+
+    #[test]
+    fn test_csi_colon_subparams() {
+        let mut p = VtParser::new();
+        let actions = p.feed(b"\x1b[38:2::10:20:30;4:3m");
+        let Action::CsiDispatch { params, .. } = &actions[0] else { panic!("expected CSI") };
+        assert_eq!(params.len(), 2);
+        assert_eq!(params.get(0), Some(&[38, 2, 0, 10, 20, 30][..]));
+        assert_eq!(params.get(1), Some(&[4, 3][..]));
+    }
+
+    #[test]
+    fn test_csi_semicolon_vs_colon() {
+        let mut p = VtParser::new();
+        let semi = p.feed(b"\x1b[4;3m");
+        let colon = p.feed(b"\x1b[4:3m");
+        assert_ne!(semi, colon);
+        assert_eq!(semi, vec![Action::CsiDispatch {
+            final_byte: b'm', params: vec![4, 3].into(), intermediates: vec![],
+        }]);
+    }
+
+    #[test]
+    fn test_csi_leading_colon() {
+        let mut p = VtParser::new();
+        let actions = p.feed(b"\x1b[:5m");
+        let Action::CsiDispatch { params, .. } = &actions[0] else { panic!("expected CSI") };
+        assert_eq!(params.get(0), Some(&[0, 5][..]));
+    }
+
+    #[test]
+    fn test_osc_esc_backslash_terminator() {
+        let mut p = VtParser::new();
+        let actions = p.feed(b"\x1b]2;title\x1b\\");
+        assert_eq!(actions, vec![
+            Action::OscDispatch(b"2;title".to_vec()),
+            Action::EscDispatch { final_byte: b'\\', intermediates: vec![] },
+        ]);
+    }
+
+    #[test]
+    fn test_dcs_hook_put_unhook() {
+        let mut p = VtParser::new();
+        // DECRQSS for SGR: DCS $ q m ST
+        let actions = p.feed(b"\x1bP$qm\x1b\\");
+        assert_eq!(actions, vec![
+            Action::DcsHook { params: vec![].into(), intermediates: vec![b'$'], final_byte: b'q' },
+            Action::DcsPut(b'm'),
+            Action::DcsUnhook,
+            Action::EscDispatch { final_byte: b'\\', intermediates: vec![] },
+        ]);
+    }
+
+    #[test]
+    fn test_dcs_params() {
+        let mut p = VtParser::new();
+        p.set_c1_controls(true);
+        let actions = p.feed(b"\x1bP1;2|ab\x9c");
+        assert_eq!(actions, vec![
+            Action::DcsHook { params: vec![1, 2].into(), intermediates: vec![], final_byte: b'|' },
+            Action::DcsPut(b'a'),
+            Action::DcsPut(b'b'),
+            Action::DcsUnhook,
+        ]);
+    }
+
+    #[test]
+    fn test_dcs_private_marker() {
+        let mut p = VtParser::new();
+        p.set_c1_controls(true);
+        let actions = p.feed(b"\x1bP>|x\x9c");
+        assert_eq!(actions[0], Action::DcsHook {
+            params: vec![0].into(), intermediates: vec![b'>'], final_byte: b'|',
+        });
+    }
+
+    #[test]
+    fn test_dcs_payload_not_printed() {
+        let mut p = VtParser::new();
+        let actions = p.feed(b"\x1bPqSIXEL\x1b\\A");
+        assert!(!actions.iter().any(|a| matches!(a, Action::Print(c) if *c != 'A')));
+        assert_eq!(actions.last(), Some(&Action::Print('A')));
+    }
+
+    #[test]
+    fn test_dcs_cancel_unhooks() {
+        let mut p = VtParser::new();
+        let actions = p.feed(b"\x1bPqab\x18A");
+        assert_eq!(actions, vec![
+            Action::DcsHook { params: vec![].into(), intermediates: vec![], final_byte: b'q' },
+            Action::DcsPut(b'a'),
+            Action::DcsPut(b'b'),
+            Action::DcsUnhook,
+            Action::Print('A'),
+        ]);
+    }
+
+    #[test]
+    fn test_apc_dispatch() {
+        let mut p = VtParser::new();
+        p.set_c1_controls(true);
+        let actions = p.feed(b"\x1b_Ga=q,i=1;AAAA\x1b\\");
+        assert_eq!(actions[0], Action::ApcDispatch(b"Ga=q,i=1;AAAA".to_vec()));
+        let actions = p.feed(b"\x1b_Gi=2\x9c");
+        assert_eq!(actions, vec![Action::ApcDispatch(b"Gi=2".to_vec())]);
+    }
+
+    #[test]
+    fn test_sos_pm_discarded() {
+        let mut p = VtParser::new();
+        p.set_c1_controls(true);
+        let actions = p.feed(b"\x1bXsos text\x1b\\\x1b^pm text\x9cA");
+        assert_eq!(actions, vec![
+            Action::EscDispatch { final_byte: b'\\', intermediates: vec![] },
+            Action::Print('A'),
+        ]);
+    }
+
+    #[test]
+    fn test_apc_size_cap() {
+        let mut p = VtParser::new();
+        p.set_c1_controls(true);
+        let mut data = b"\x1b_G".to_vec();
+        data.extend(std::iter::repeat(b'A').take(MAX_APC_LEN + 10));
+        data.extend_from_slice(b"\x9cB");
+        let actions = p.feed(&data);
+        assert_eq!(actions, vec![Action::Print('B')]);
+        assert_eq!(p.stats().apc_discarded, 1);
+    }
+
+    #[test]
+    fn test_param_saturates() {
+        let mut p = VtParser::new();
+        assert_eq!(p.feed(b"\x1b[99999999999A"), vec![Action::CsiDispatch {
+            final_byte: b'A', params: vec![u16::MAX].into(), intermediates: vec![],
+        }]);
+    }
+
+    #[test]
+    fn test_param_limit() {
+        let mut p = VtParser::new();
+        let mut seq = b"\x1b[".to_vec();
+        for _ in 0..100 {
+            seq.extend_from_slice(b"1;");
+        }
+        seq.push(b'm');
+        let actions = p.feed(&seq);
+        let Action::CsiDispatch { params, .. } = &actions[0] else { panic!("expected CSI") };
+        assert_eq!(params.len(), ParserLimits::default().max_params);
+        assert_eq!(p.stats().params_dropped, 101 - 32);
+    }
+
+    #[test]
+    fn test_intermediate_limit() {
+        let mut p = VtParser::new();
+        let actions = p.feed(b"\x1b[!!!!!!!!p");
+        let Action::CsiDispatch { intermediates, .. } = &actions[0] else { panic!("expected CSI") };
+        assert_eq!(intermediates.len(), 4);
+        assert_eq!(p.stats().intermediates_dropped, 4);
+    }
+
+    #[test]
+    fn test_osc_limit() {
+        let mut p = VtParser::with_limits(ParserLimits { max_osc_len: 8, ..Default::default() });
+        assert_eq!(p.feed(b"\x1b]0;0123456789\x07A"), vec![Action::Print('A')]);
+        assert_eq!(p.stats().osc_discarded, 1);
+        assert_eq!(p.feed(b"\x1b]0;ok\x07"), vec![Action::OscDispatch(b"0;ok".to_vec())]);
+    }
+
+    #[test]
+    fn test_dcs_limit() {
+        let mut p = VtParser::with_limits(ParserLimits { max_dcs_len: 2, ..Default::default() });
+        let actions = p.feed(b"\x1bPqabcdef\x1b\\");
+        assert_eq!(actions.iter().filter(|a| matches!(a, Action::DcsPut(_))).count(), 2);
+        assert!(actions.contains(&Action::DcsUnhook));
+        assert_eq!(p.stats().dcs_truncated, 1);
+    }
+
+    #[test]
+    fn test_apc_cancel() {
+        let mut p = VtParser::new();
+        let actions = p.feed(b"\x1b_Gabc\x18A");
+        assert_eq!(actions, vec![Action::Execute(0x18), Action::Print('A')]);
+    }
+
+    #[test]
+    fn test_dcs_ignore_invalid() {
+        let mut p = VtParser::new();
+        p.set_c1_controls(true);
+        // Private marker after a param is invalid → ignored until ST
+        let actions = p.feed(b"\x1bP1?qdata\x9cA");
+        assert_eq!(actions, vec![Action::Print('A')]);
+    }
+
+    /// Records calls as strings to check `parse` against `feed`.
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    impl Perform for Recorder {
+        fn print(&mut self, ch: char) { self.0.push(format!("print {ch}")); }
+        fn execute(&mut self, byte: u8) { self.0.push(format!("exec {byte}")); }
+        fn csi_dispatch(&mut self, final_byte: u8, params: &Params, intermediates: &[u8]) {
+            let groups: Vec<_> = params.iter().collect();
+            self.0.push(format!("csi {} {:?} {:?}", final_byte as char, groups, intermediates));
+        }
+        fn esc_dispatch(&mut self, final_byte: u8, _intermediates: &[u8]) {
+            self.0.push(format!("esc {}", final_byte as char));
+        }
+        fn osc_dispatch(&mut self, data: &[u8], bell_terminated: bool) {
+            let end = if bell_terminated { "bel" } else { "st" };
+            self.0.push(format!("osc {} {end}", String::from_utf8_lossy(data)));
+        }
+        fn unhook(&mut self) { self.0.push("unhook".into()); }
+    }
+
+    #[test]
+    fn test_perform_dispatch() {
+        let mut p = VtParser::new();
+        let mut r = Recorder::default();
+        p.parse(&mut r, b"a\r\x1b[?1;4:3h\x1b7\x1b]0;t\x07\x1bPq#\x1b\\");
+        assert_eq!(r.0, vec![
+            "print a", "exec 13", "csi h [[1], [4, 3]] [63]", "esc 7", "osc 0;t bel", "unhook", "esc \\",
+        ]);
+    }
+
+    #[test]
+    fn test_osc_terminator_reported() {
+        let mut p = VtParser::new();
+        let mut r = Recorder::default();
+        p.parse(&mut r, b"\x1b]1;a\x1b\\\x1b]2;b\x07");
+        p.set_c1_controls(true);
+        p.parse(&mut r, b"\x9d3;c\x9c");
+        assert_eq!(r.0, vec!["osc 1;a st", "esc \\", "osc 2;b bel", "osc 3;c st"]);
+    }
+
+    #[test]
+    fn test_utf8_decoded() {
+        let mut p = VtParser::new();
+        assert_eq!(p.feed("é中🎉".as_bytes()), vec![
+            Action::Print('é'), Action::Print('中'), Action::Print('🎉'),
+        ]);
+    }
+
+    #[test]
+    fn test_utf8_split_across_reads() {
+        let mut p = VtParser::new();
+        let bytes = "a中b".as_bytes();
+        assert_eq!(p.feed(&bytes[..2]), vec![Action::Print('a')]);
+        assert_eq!(p.feed(&bytes[2..]), vec![Action::Print('中'), Action::Print('b')]);
+    }
+
+    #[test]
+    fn test_utf8_invalid() {
+        let mut p = VtParser::new();
+        // Stray continuation, then a lead byte cut short by a control
+        assert_eq!(p.feed(b"\x80a\xe4\xb8\r"), vec![
+            Action::Print(char::REPLACEMENT_CHARACTER),
+            Action::Print('a'),
+            Action::Print(char::REPLACEMENT_CHARACTER),
+            Action::Execute(b'\r'),
+        ]);
+    }
+
+    #[test]
+    fn test_print_run_single_call() {
+        #[derive(Default)]
+        struct Runs(Vec<String>);
+        impl Perform for Runs {
+            fn print(&mut self, ch: char) { self.0.push(ch.to_string()); }
+            fn print_str(&mut self, s: &str) { self.0.push(s.to_string()); }
+            fn execute(&mut self, _byte: u8) {}
+            fn csi_dispatch(&mut self, _: u8, _: &Params, _: &[u8]) {}
+            fn esc_dispatch(&mut self, _: u8, _: &[u8]) {}
+            fn osc_dispatch(&mut self, _: &[u8], _: bool) {}
+        }
+        let mut p = VtParser::new();
+        let mut r = Runs::default();
+        p.parse(&mut r, "hello wörld\r\n\x1b[1mbold".as_bytes());
+        assert_eq!(r.0, vec!["hello wörld", "bold"]);
+    }
+
+    #[test]
+    fn test_c1_off_by_default() {
+        let mut p = VtParser::new();
+        assert_eq!(p.feed(b"\x9b1m"), vec![
+            Action::Print(char::REPLACEMENT_CHARACTER), Action::Print('1'), Action::Print('m'),
+        ]);
+    }
+
+    #[test]
+    fn test_c1_off_payload_keeps_0x9c() {
+        // 'Ü' is C3 9C: without C1 controls the 9C byte is payload, not ST
+        let mut p = VtParser::new();
+        assert_eq!(p.feed("\x1b]0;Über\x07".as_bytes()), vec![
+            Action::OscDispatch("0;Über".as_bytes().to_vec()),
+        ]);
+        let actions = p.feed("\x1bP+qÜ\x1b\\".as_bytes());
+        assert_eq!(&actions[1..4], [Action::DcsPut(0xc3), Action::DcsPut(0x9c), Action::DcsUnhook]);
+        assert_eq!(p.feed("\x1b_GÜ\x1b\\".as_bytes())[0], Action::ApcDispatch("GÜ".as_bytes().to_vec()));
+    }
+
+    #[test]
+    fn test_c1_csi_osc_dcs() {
+        let mut p = VtParser::new();
+        p.set_c1_controls(true);
+        assert_eq!(p.feed(b"\x9b1;31m"), vec![Action::CsiDispatch {
+            final_byte: b'm', params: vec![1, 31].into(), intermediates: vec![],
+        }]);
+        assert_eq!(p.feed(b"\x9d0;t\x9c"), vec![Action::OscDispatch(b"0;t".to_vec())]);
+        assert_eq!(p.feed(b"\x90q\x9c"), vec![
+            Action::DcsHook { params: vec![].into(), intermediates: vec![], final_byte: b'q' },
+            Action::DcsUnhook,
+        ]);
+        assert_eq!(p.feed(b"\x84\x8d"), vec![Action::Execute(0x84), Action::Execute(0x8d)]);
+    }
+
+    #[test]
+    fn test_c1_keeps_utf8() {
+        let mut p = VtParser::new();
+        p.set_c1_controls(true);
+        // U+0100 is C4 80 and U+4E2D is E4 B8 AD: continuation bytes are not C1
+        assert_eq!(p.feed("Ā中".as_bytes()), vec![Action::Print('Ā'), Action::Print('中')]);
+        assert_eq!(p.feed("\x1b]0;Ā\x07".as_bytes()), vec![
+            Action::OscDispatch("0;Ā".as_bytes().to_vec()),
+        ]);
+    }
+
+    #[test]
+    fn test_s8c1t_toggles_c1() {
+        let mut p = VtParser::new();
+        p.feed(b"\x1b G");
+        assert!(p.c1_controls());
+        p.feed(b"\x1b F");
+        assert!(!p.c1_controls());
+    }
+
+    #[test]
+    fn test_ris_resets_s8c1t() {
+        let mut p = VtParser::new();
+        p.feed(b"\x1b G\x1bc");
+        assert!(!p.c1_controls());
+        assert_eq!(p.feed(b"\x9b"), vec![Action::Print('\u{fffd}')]);
+        // The configured setting survives RIS
+        p.set_c1_controls(true);
+        p.feed(b"\x1b F\x1bc");
+        assert!(p.c1_controls());
+    }
+
+    #[test]
+    fn test_perform_split_input() {
+        // A sequence split across reads dispatches once complete
+        let mut p = VtParser::new();
+        let mut r = Recorder::default();
+        p.parse(&mut r, b"\x1b[3");
+        assert!(r.0.is_empty());
+        p.parse(&mut r, b"1m");
+        assert_eq!(r.0, vec!["csi m [[31]] []"]);
     }
 }
@@ -65,45 +65,23 @@ impl Utf8Decoder {
     pub fn is_pending(&self) -> bool {
         self.expected > 0
     }
+
+    /// Drop any partial sequence.
+    pub fn reset(&mut self) {
+        self.expected = 0;
+        self.len = 0;
+    }
 }
 
 impl Default for Utf8Decoder {
     fn default() -> Self { Self::new() }
 }
 
-/// Returns the display width of a character (0, 1, or 2 for CJK).
+/// Returns the display width of a character (0, 1, or 2 for CJK), with
+/// East Asian Ambiguous characters narrow. See `core::width` for the
+/// configurable and cluster-aware variants.
 pub fn char_width(ch: char) -> usize {
-    let cp = ch as u32;
-    match cp {
-        // C0/C1 controls, DEL
-        0x00..=0x1f | 0x7f..=0x9f => 0,
-        // Combining marks (common ranges)
-        0x0300..=0x036f | 0x0483..=0x0489 | 0x0591..=0x05bd |
-        0x05bf | 0x05c1..=0x05c2 | 0x05c4..=0x05c5 | 0x05c7 |
-        0x0610..=0x061a | 0x064b..=0x065f | 0x0670 |
-        0xfe00..=0xfe0f |  // Variation selectors
-        0x200b..=0x200f |  // Zero-width spaces
-        0x2028..=0x202e |  // Bidi controls
-        0x2060..=0x2064 |  // Word joiner etc
-        0xfeff => 0,       // BOM / ZWNBSP
-        // CJK wide characters
-        0x1100..=0x115f |  // Hangul Jamo
-        0x2e80..=0x303e |  // CJK Radicals, Kangxi, CJK Symbols
-        0x3041..=0x33bf |  // Hiragana, Katakana, Bopomofo, Hangul Compat, Kanbun, CJK
-        0x3400..=0x4dbf |  // CJK Unified Ext A
-        0x4e00..=0xa4cf |  // CJK Unified, Yi
-        0xa960..=0xa97c |  // Hangul Jamo Extended-A
-        0xac00..=0xd7a3 |  // Hangul Syllables
-        0xf900..=0xfaff |  // CJK Compatibility Ideographs
-        0xfe30..=0xfe6f |  // CJK Compatibility Forms, Small Form Variants
-        0xff01..=0xff60 |  // Fullwidth Forms
-        0xffe0..=0xffe6 |  // Fullwidth Signs
-        0x20000..=0x2fffd | // CJK Unified Ext B-F
-        0x30000..=0x3fffd => 2, // CJK Unified Ext G+
-        // Emoji that are typically wide
-        0x1f300..=0x1f9ff | 0x1fa00..=0x1fa6f | 0x1fa70..=0x1faff => 2,
-        _ => 1,
-    }
+    crate::core::width::char_width(ch, crate::core::width::AmbiguousWidth::Narrow)
 }
 
 #[cfg(test)]
@@ -0,0 +1,82 @@
+//! Column widths from Unicode East Asian Width and emoji data (via the
+//! `unicode-width` crate), with configurable ambiguous width.
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// Width of East Asian Ambiguous characters (`§`, `─`, `○`, ...).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AmbiguousWidth {
+    /// One column (Western default)
+    #[default]
+    Narrow,
+    /// Two columns, as CJK legacy fonts draw them
+    Wide,
+}
+
+impl AmbiguousWidth {
+    /// From a column count as written in config (1 or 2).
+    pub fn from_columns(columns: u8) -> Self {
+        if columns == 2 { Self::Wide } else { Self::Narrow }
+    }
+}
+
+/// Width of a single codepoint: 0 for controls and zero-width marks,
+/// 2 for wide/fullwidth characters and default-emoji-presentation
+/// codepoints, and ambiguous characters per `ambiguous`.
+pub fn char_width(ch: char, ambiguous: AmbiguousWidth) -> usize {
+    let width = match ambiguous {
+        AmbiguousWidth::Narrow => ch.width(),
+        AmbiguousWidth::Wide => ch.width_cjk(),
+    };
+    width.unwrap_or(0)
+}
+
+/// Width of a whole grapheme cluster, used in mode 2027. VS16 selects
+/// emoji presentation (2 columns), VS15 text presentation (1), a pair of
+/// regional indicators is one flag (2), and a ZWJ sequence takes the width
+/// of a single emoji.
+pub fn cluster_width(cluster: &str, ambiguous: AmbiguousWidth) -> usize {
+    let width = match ambiguous {
+        AmbiguousWidth::Narrow => cluster.width(),
+        AmbiguousWidth::Wide => cluster.width_cjk(),
+    };
+    width.clamp(1, 2)
+}
+
+/// Regional indicator symbol; two in a row form a flag.
+pub fn is_regional_indicator(ch: char) -> bool {
+    ('\u{1f1e6}'..='\u{1f1ff}').contains(&ch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ambiguous_width() {
+        assert_eq!(char_width('─', AmbiguousWidth::Narrow), 1);
+        assert_eq!(char_width('─', AmbiguousWidth::Wide), 2);
+        assert_eq!(char_width('§', AmbiguousWidth::Wide), 2);
+        // Unambiguous characters are unaffected
+        assert_eq!(char_width('A', AmbiguousWidth::Wide), 1);
+        assert_eq!(char_width('中', AmbiguousWidth::Narrow), 2);
+    }
+
+    #[test]
+    fn test_zero_width() {
+        assert_eq!(char_width('\u{301}', AmbiguousWidth::Narrow), 0);
+        assert_eq!(char_width('\u{fe0f}', AmbiguousWidth::Narrow), 0);
+        assert_eq!(char_width('\u{200d}', AmbiguousWidth::Narrow), 0);
+        assert_eq!(char_width('\x1b', AmbiguousWidth::Narrow), 0);
+    }
+
+    #[test]
+    fn test_cluster_width() {
+        let n = AmbiguousWidth::Narrow;
+        assert_eq!(cluster_width("e\u{301}", n), 1);
+        assert_eq!(cluster_width("\u{2764}\u{fe0f}", n), 2); // ❤️
+        assert_eq!(cluster_width("\u{231a}\u{fe0e}", n), 1); // ⌚︎
+        assert_eq!(cluster_width("\u{1f1fa}\u{1f1f8}", n), 2); // 🇺🇸
+        assert_eq!(cluster_width("\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}", n), 2);
+    }
+}
@@ -1,7 +1,7 @@
 /// C ABI bridge for platform shells (macOS Swift, Linux GTK).
 /// This is the public API that native UIs consume.
 
-use crate::core::{Terminal, VtParser};
+use crate::core::{AmbiguousWidth, Terminal, VtParser};
 use crate::pty::PtyManager;
 use std::ffi::{c_char, c_int, c_uint, CStr};
 use std::ptr;
@@ -31,12 +31,15 @@ pub extern "C" fn term_session_new(cols: c_uint, rows: c_uint) -> *mut TermSessi
     let theme = crate::theme::Theme::by_name(&config.colors.theme)
         .unwrap_or_else(crate::theme::Theme::default_dark);
     let mut terminal = Terminal::new(cols as usize, rows as usize);
-    // Apply theme colors as terminal defaults
-    terminal.set_default_colors(theme.fg, theme.bg);
+    terminal.set_theme(&theme);
     terminal.grid.set_scrollback_max(config.scrollback);
+    terminal.ambiguous_width = AmbiguousWidth::from_columns(config.terminal.ambiguous_width);
+    apply_clipboard_config(&mut terminal, &config.terminal);
+    let mut parser = VtParser::new();
+    parser.set_c1_controls(config.terminal.c1_controls);
     let session = Box::new(TermSession {
         terminal,
-        parser: VtParser::new(),
+        parser,
         pty: None,
         renderer: None,
         config,
@@ -48,6 +51,12 @@ pub extern "C" fn term_session_new(cols: c_uint, rows: c_uint) -> *mut TermSessi
     Box::into_raw(session)
 }
 
+fn apply_clipboard_config(terminal: &mut Terminal, config: &crate::config::TerminalConfig) {
+    terminal.clipboard_write = config.clipboard_write;
+    terminal.clipboard_read = config.clipboard_read;
+    terminal.clipboard_max_bytes = config.clipboard_max_bytes;
+}
+
 #[no_mangle]
 pub extern "C" fn term_session_free(session: *mut TermSession) {
     if !session.is_null() {
@@ -101,14 +110,18 @@ pub extern "C" fn term_session_read_pty(session: *mut TermSession) -> c_int {
             Err(_) => return -1,
         }
     }
-    // Flush write-back (DSR responses) to PTY
+    flush_write_back(session);
+    total
+}
+
+/// Flush write-back (DSR responses, focus reports) to PTY.
+fn flush_write_back(session: &mut TermSession) {
     if !session.terminal.write_back.is_empty() {
         let wb: Vec<u8> = session.terminal.write_back.drain(..).collect();
         if let Some(pty) = &session.pty {
             let _ = pty.write(&wb);
         }
     }
-    total
 }
 
 /// Write user input to PTY.
@@ -127,6 +140,104 @@ pub extern "C" fn term_session_write_pty(
     }
 }
 
+/// Encode a key with the terminal's current modes and write it to the PTY.
+/// `key` is a kitty key number (a codepoint, or 57348.. for functional
+/// keys), `mods` the kitty modifier bits (shift 1, alt 2, ctrl 4, super 8),
+/// `kind` 1 press, 2 repeat, 3 release, and `text` the UTF-8 text the key
+/// produces (may be null). Returns bytes written, 0 if the key sends
+/// nothing, -1 on error.
+#[no_mangle]
+pub extern "C" fn term_session_send_key(
+    session: *mut TermSession,
+    key: u32,
+    mods: u32,
+    kind: c_int,
+    text: *const c_char,
+) -> c_int {
+    let session = unsafe { &*session };
+    let Some(pty) = &session.pty else { return -1 };
+    let Some(key) = crate::input::Key::from_kitty_code(key) else { return 0 };
+    let text = if text.is_null() {
+        ""
+    } else {
+        unsafe { CStr::from_ptr(text).to_str().unwrap_or("") }
+    };
+    let mut event = crate::input::KeyEvent::with_text(
+        key,
+        crate::input::Modifiers::from_bits_truncate(mods as u8),
+        text,
+    );
+    event.kind = match kind {
+        2 => crate::input::KeyEventKind::Repeat,
+        3 => crate::input::KeyEventKind::Release,
+        _ => crate::input::KeyEventKind::Press,
+    };
+    let data = crate::input::encode_key(event, &session.terminal.input_modes());
+    if data.is_empty() {
+        return 0;
+    }
+    match pty.write(&data) {
+        Ok(n) => n as c_int,
+        Err(_) => -1,
+    }
+}
+
+/// Offer a mouse event to the application. `kind` is 1 press, 2 release,
+/// 3 motion; `button` 0 none (motion only), 1 left, 2 middle, 3 right,
+/// 4/5 wheel up/down, 6/7 wheel left/right, 8 back, 9 forward; `mods`
+/// as for `term_session_send_key`; `col`/`row` the 0-based cell and `x`/`y`
+/// the pixel position. Returns 1 if the terminal took the event (reported
+/// or swallowed), 0 if the UI should handle it (selection, scrollback).
+#[no_mangle]
+pub extern "C" fn term_session_send_mouse(
+    session: *mut TermSession,
+    kind: c_int,
+    button: c_int,
+    mods: u32,
+    col: c_uint,
+    row: c_uint,
+    x: c_uint,
+    y: c_uint,
+) -> c_int {
+    use crate::input::{MouseButton, MouseEventKind};
+    let session = unsafe { &*session };
+    let button = match button {
+        1 => Some(MouseButton::Left),
+        2 => Some(MouseButton::Middle),
+        3 => Some(MouseButton::Right),
+        4 => Some(MouseButton::WheelUp),
+        5 => Some(MouseButton::WheelDown),
+        6 => Some(MouseButton::WheelLeft),
+        7 => Some(MouseButton::WheelRight),
+        8 => Some(MouseButton::Back),
+        9 => Some(MouseButton::Forward),
+        _ => None,
+    };
+    let kind = match (kind, button) {
+        (1, Some(b)) => MouseEventKind::Press(b),
+        (2, Some(b)) => MouseEventKind::Release(b),
+        (3, held) => MouseEventKind::Motion(held),
+        _ => return 0,
+    };
+    let grid = &session.terminal.grid;
+    let mut event = crate::input::MouseEvent::new(
+        kind,
+        (col as usize).min(grid.cols() - 1),
+        (row as usize).min(grid.rows() - 1),
+    );
+    event.mods = crate::input::Modifiers::from_bits_truncate(mods as u8);
+    (event.x, event.y) = (x as usize, y as usize);
+    let Some(data) = crate::input::encode_mouse(&event, &session.terminal.input_modes()) else {
+        return 0;
+    };
+    if let Some(pty) = &session.pty {
+        if !data.is_empty() {
+            let _ = pty.write(&data);
+        }
+    }
+    1
+}
+
 /// Get PTY master file descriptor (for polling).
 #[no_mangle]
 pub extern "C" fn term_session_pty_fd(session: *const TermSession) -> c_int {
@@ -145,6 +256,9 @@ pub extern "C" fn term_session_resize(
 ) {
     let session = unsafe { &mut *session };
     session.terminal.resize(cols as usize, rows as usize);
+    if cols > 0 && rows > 0 {
+        session.terminal.set_cell_size((pixel_width / cols) as u16, (pixel_height / rows) as u16);
+    }
     if let Some(pty) = &session.pty {
         let ws = nix::pty::Winsize {
             ws_row: rows as u16,
@@ -175,7 +289,7 @@ pub extern "C" fn term_session_cell_fg(
     col: c_uint,
 ) -> u32 {
     let session = unsafe { &*session };
-    let c = session.terminal.grid.cell(row as usize, col as usize).fg;
+    let c = session.terminal.palette.resolve_fg(session.terminal.grid.cell(row as usize, col as usize).fg);
     (c.r as u32) << 16 | (c.g as u32) << 8 | c.b as u32
 }
 
@@ -187,21 +301,35 @@ pub extern "C" fn term_session_cell_bg(
     col: c_uint,
 ) -> u32 {
     let session = unsafe { &*session };
-    let c = session.terminal.grid.cell(row as usize, col as usize).bg;
+    let c = session.terminal.palette.resolve_bg(session.terminal.grid.cell(row as usize, col as usize).bg);
     (c.r as u32) << 16 | (c.g as u32) << 8 | c.b as u32
 }
 
-/// Get cell attributes (bold, italic, etc). Returns bitfield.
+/// Get cell attributes (bold, italic, underline style, etc). Returns bitfield.
 #[no_mangle]
 pub extern "C" fn term_session_cell_attr(
     session: *const TermSession,
     row: c_uint,
     col: c_uint,
-) -> u8 {
+) -> u16 {
     let session = unsafe { &*session };
     session.terminal.grid.cell(row as usize, col as usize).attr.bits()
 }
 
+/// Get cell underline color (SGR 58), falling back to the foreground.
+/// Returns packed RGB.
+#[no_mangle]
+pub extern "C" fn term_session_cell_underline_color(
+    session: *const TermSession,
+    row: c_uint,
+    col: c_uint,
+) -> u32 {
+    let session = unsafe { &*session };
+    let cell = session.terminal.grid.cell(row as usize, col as usize);
+    let c = session.terminal.palette.resolve_underline(cell.underline_color, cell.fg);
+    (c.r as u32) << 16 | (c.g as u32) << 8 | c.b as u32
+}
+
 /// Get cursor position. Writes to out_row and out_col.
 #[no_mangle]
 pub extern "C" fn term_session_cursor_pos(
@@ -245,6 +373,114 @@ pub extern "C" fn term_string_free(s: *mut c_char) {
     }
 }
 
+/// Pop the oldest terminal event and return its kind, 0 when none is left:
+/// 1 title changed (text = title), 2 bell, 3 clipboard store (text = data,
+/// detail = selection), 4 clipboard load request (detail = selection),
+/// 5 working directory changed (text = path), 6 command started,
+/// 7 command finished (value = exit code, -1 if unknown), 8 notification
+/// (text = body, detail = title), 9 color query (value = OSC number,
+/// text = palette index), 10 mode changed (value = mode, text like
+/// "?1049h"), 11 window operation (value = XTWINOPS number, text = its
+/// parameters). Strings not used by the kind are set to null; free the
+/// others with term_string_free.
+#[no_mangle]
+pub extern "C" fn term_session_next_event(
+    session: *mut TermSession,
+    out_value: *mut i32,
+    out_text: *mut *mut c_char,
+    out_detail: *mut *mut c_char,
+) -> c_int {
+    use crate::core::{TerminalEvent, WindowOp};
+    let session = unsafe { &mut *session };
+    let Some(event) = session.terminal.next_event() else { return 0 };
+    let (kind, value, text, detail): (c_int, i32, Option<String>, Option<String>) = match event {
+        TerminalEvent::TitleChanged(title) => (1, 0, Some(title), None),
+        TerminalEvent::Bell => (2, 0, None, None),
+        TerminalEvent::ClipboardStore { selection, text } => (3, 0, Some(text), Some(selection)),
+        TerminalEvent::ClipboardLoadRequest { selection } => (4, 0, None, Some(selection)),
+        TerminalEvent::CwdChanged(path) => (5, 0, Some(path), None),
+        TerminalEvent::CommandStarted => (6, 0, None, None),
+        TerminalEvent::CommandFinished { exit_code, .. } => (7, exit_code.unwrap_or(-1), None, None),
+        TerminalEvent::Notification { title, body } => (8, 0, Some(body), Some(title)),
+        TerminalEvent::ColorQuery { osc, index } => (9, osc as i32, index.map(|i| i.to_string()), None),
+        TerminalEvent::ModeChanged { mode, private, enabled } => {
+            let text = format!("{}{}{}", if private { "?" } else { "" }, mode, if enabled { 'h' } else { 'l' });
+            (10, mode as i32, Some(text), None)
+        }
+        TerminalEvent::WindowOp(op) => {
+            let (n, params) = match op {
+                WindowOp::Deiconify => (1, String::new()),
+                WindowOp::Iconify => (2, String::new()),
+                WindowOp::Move { x, y } => (3, format!("{};{}", x, y)),
+                WindowOp::ResizePixels { width, height } => (4, format!("{};{}", height, width)),
+                WindowOp::Raise => (5, String::new()),
+                WindowOp::Lower => (6, String::new()),
+                WindowOp::Refresh => (7, String::new()),
+                WindowOp::ResizeCells { cols, rows } => (8, format!("{};{}", rows, cols)),
+                WindowOp::Maximize(how) => (9, how.to_string()),
+                WindowOp::Fullscreen(how) => (10, how.to_string()),
+                WindowOp::ReportTitle => (21, String::new()),
+            };
+            (11, n, Some(params), None)
+        }
+    };
+    let to_c = |s: Option<String>| {
+        s.map_or(ptr::null_mut(), |s| std::ffi::CString::new(s).unwrap_or_default().into_raw())
+    };
+    unsafe {
+        if !out_value.is_null() {
+            *out_value = value;
+        }
+        if !out_text.is_null() {
+            *out_text = to_c(text);
+        }
+        if !out_detail.is_null() {
+            *out_detail = to_c(detail);
+        }
+    }
+    kind
+}
+
+/// OSC 52 policy for writes (`write` = 1) or reads (0): 0 off, 1 ask,
+/// 2 allow. Events only arrive when it is not off; for ask, confirm with
+/// the user before touching the pasteboard.
+#[no_mangle]
+pub extern "C" fn term_session_clipboard_access(session: *const TermSession, write: c_int) -> c_int {
+    use crate::clipboard::ClipboardAccess;
+    let session = unsafe { &*session };
+    let access = if write != 0 { session.terminal.clipboard_write } else { session.terminal.clipboard_read };
+    match access {
+        ClipboardAccess::Off => 0,
+        ClipboardAccess::Ask => 1,
+        ClipboardAccess::Allow => 2,
+    }
+}
+
+/// Answer a clipboard load request (event 4) with the selection's text
+/// and write the reply to the PTY. A null `text` answers with an empty
+/// selection.
+#[no_mangle]
+pub extern "C" fn term_session_clipboard_reply(
+    session: *mut TermSession,
+    selection: *const c_char,
+    text: *const c_char,
+) {
+    let session = unsafe { &mut *session };
+    let to_str = |p: *const c_char| {
+        if p.is_null() { "" } else { unsafe { CStr::from_ptr(p).to_str().unwrap_or("") } }
+    };
+    session.terminal.answer_clipboard_request(to_str(selection), to_str(text));
+    flush_write_back(session);
+}
+
+/// Returns 1 if Option should act as Alt (config `option_as_alt`): the
+/// view sends such keys with term_session_send_key instead of composing.
+#[no_mangle]
+pub extern "C" fn term_session_option_as_alt(session: *const TermSession) -> c_int {
+    let session = unsafe { &*session };
+    session.config.terminal.option_as_alt as c_int
+}
+
 /// Returns 1 if cursor keys are in application mode.
 #[no_mangle]
 pub extern "C" fn term_session_cursor_keys_app(session: *const TermSession) -> c_int {
@@ -252,6 +488,30 @@ pub extern "C" fn term_session_cursor_keys_app(session: *const TermSession) -> c
     session.terminal.cursor_keys_app as c_int
 }
 
+/// Returns 1 if line feed/new line mode (LNM) is set: Enter sends CR LF.
+#[no_mangle]
+pub extern "C" fn term_session_newline_mode(session: *const TermSession) -> c_int {
+    let session = unsafe { &*session };
+    session.terminal.newline_mode as c_int
+}
+
+/// Tell the terminal its view gained (1) or lost (0) focus. Sends
+/// `CSI I`/`CSI O` to the PTY when the application enabled mode 1004.
+#[no_mangle]
+pub extern "C" fn term_session_set_focused(session: *mut TermSession, focused: c_int) {
+    let session = unsafe { &mut *session };
+    session.terminal.set_focused(focused != 0);
+    flush_write_back(session);
+}
+
+/// Returns 1 while a synchronized update (mode 2026) is in progress and
+/// the last complete frame should stay on screen.
+#[no_mangle]
+pub extern "C" fn term_session_sync_pending(session: *const TermSession) -> c_int {
+    let session = unsafe { &*session };
+    session.terminal.sync_update_pending() as c_int
+}
+
 /// Returns 1 if cursor is visible.
 #[no_mangle]
 pub extern "C" fn term_session_cursor_visible(session: *const TermSession) -> c_int {
@@ -293,22 +553,20 @@ pub extern "C" fn term_session_window_height(session: *const TermSession) -> u32
     unsafe { &*session }.config.window.height
 }
 
-/// Get theme background color as packed RGB.
+/// Get the default background color as packed RGB. Reflects OSC 11.
 #[no_mangle]
 pub extern "C" fn term_session_theme_bg(session: *const TermSession) -> u32 {
     let session = unsafe { &*session };
-    let theme = crate::theme::Theme::by_name(&session.config.colors.theme)
-        .unwrap_or_else(crate::theme::Theme::default_dark);
-    (theme.bg.r as u32) << 16 | (theme.bg.g as u32) << 8 | theme.bg.b as u32
+    let c = session.terminal.palette.bg;
+    (c.r as u32) << 16 | (c.g as u32) << 8 | c.b as u32
 }
 
-/// Get theme foreground color as packed RGB.
+/// Get the default foreground color as packed RGB. Reflects OSC 10.
 #[no_mangle]
 pub extern "C" fn term_session_theme_fg(session: *const TermSession) -> u32 {
     let session = unsafe { &*session };
-    let theme = crate::theme::Theme::by_name(&session.config.colors.theme)
-        .unwrap_or_else(crate::theme::Theme::default_dark);
-    (theme.fg.r as u32) << 16 | (theme.fg.g as u32) << 8 | theme.fg.b as u32
+    let c = session.terminal.palette.fg;
+    (c.r as u32) << 16 | (c.g as u32) << 8 | c.b as u32
 }
 
 /// Get scrollback line count.
@@ -341,7 +599,7 @@ pub extern "C" fn term_session_scrollback_cell_fg(
     let sb = session.terminal.grid.scrollback();
     let (row, col) = (sb_row as usize, col as usize);
     if row < sb.len() && col < sb[row].len() {
-        let c = sb[row][col].fg;
+        let c = session.terminal.palette.resolve_fg(sb[row][col].fg);
         (c.r as u32) << 16 | (c.g as u32) << 8 | c.b as u32
     } else { 0 }
 }
@@ -355,7 +613,7 @@ pub extern "C" fn term_session_scrollback_cell_bg(
     let sb = session.terminal.grid.scrollback();
     let (row, col) = (sb_row as usize, col as usize);
     if row < sb.len() && col < sb[row].len() {
-        let c = sb[row][col].bg;
+        let c = session.terminal.palette.resolve_bg(sb[row][col].bg);
         (c.r as u32) << 16 | (c.g as u32) << 8 | c.b as u32
     } else { 0 }
 }
@@ -467,6 +725,35 @@ pub extern "C" fn term_session_url_at(
     }
 }
 
+/// Get the OSC 8 hyperlink at grid position. Returns the URI (caller must
+/// free) and writes the link's column range in that row to out_col_start
+/// and out_col_end (exclusive), either of which may be null. Returns null
+/// if the cell has no link or `session` is null.
+#[no_mangle]
+pub extern "C" fn term_session_hyperlink_at(
+    session: *const TermSession, row: c_uint, col: c_uint,
+    out_col_start: *mut c_uint, out_col_end: *mut c_uint,
+) -> *mut c_char {
+    if session.is_null() {
+        return ptr::null_mut();
+    }
+    let session = unsafe { &*session };
+    match crate::url_detect::hyperlink_at(&session.terminal, row as usize, col as usize) {
+        Some(m) => {
+            unsafe {
+                if !out_col_start.is_null() {
+                    *out_col_start = m.col_start as c_uint;
+                }
+                if !out_col_end.is_null() {
+                    *out_col_end = m.col_end as c_uint;
+                }
+            }
+            std::ffi::CString::new(m.url).unwrap_or_default().into_raw()
+        }
+        None => std::ptr::null_mut(),
+    }
+}
+
 /// Poll for config changes. Returns new generation number if config changed, 0 if not.
 #[no_mangle]
 pub extern "C" fn term_session_poll_config(session: *mut TermSession) -> u64 {
@@ -474,8 +761,13 @@ pub extern "C" fn term_session_poll_config(session: *mut TermSession) -> u64 {
     if let Some(new_config) = session.watcher.poll() {
         let theme = crate::theme::Theme::by_name(&new_config.colors.theme)
             .unwrap_or_else(crate::theme::Theme::default_dark);
-        session.terminal.set_default_colors(theme.fg, theme.bg);
+        session.terminal.set_theme(&theme);
         session.terminal.grid.set_scrollback_max(new_config.scrollback);
+        session.terminal.ambiguous_width = AmbiguousWidth::from_columns(new_config.terminal.ambiguous_width);
+        apply_clipboard_config(&mut session.terminal, &new_config.terminal);
+        if new_config.terminal.c1_controls != session.config.terminal.c1_controls {
+            session.parser.set_c1_controls(new_config.terminal.c1_controls);
+        }
         session.config = new_config;
         session.config_generation += 1;
         session.config_generation
@@ -502,8 +794,7 @@ pub extern "C" fn term_session_extract_text(
         let col_start = if row == sr { sc } else { 0 };
         let col_end = if row == er { ec } else { grid.cols() };
         for col in col_start..col_end.min(grid.cols()) {
-            let ch = grid.cell(row, col).ch;
-            if ch != '\0' { text.push(ch); }
+            grid.push_cell_text(grid.cell(row, col), &mut text);
         }
         if row != er { text = text.trim_end().to_string(); text.push('\n'); }
     }
@@ -596,9 +887,12 @@ pub extern "C" fn term_session_render_gpu(
 ) -> c_int {
     let session = unsafe { &mut *session };
     let Some(renderer) = &mut session.renderer else { return -1 };
+    // Keep presenting the last complete frame during a synchronized update
+    if session.terminal.sync_update_pending() { return 0; }
 
     let (vertices, indices) = renderer.render_state.build_vertices(
         &session.terminal.grid,
+        &session.terminal.palette,
         &mut renderer.atlas,
         width as f32,
         height as f32,
@@ -641,7 +935,7 @@ pub extern "C" fn term_session_render_gpu(
                 view: &view,
                 resolve_target: None,
                 ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    load: wgpu::LoadOp::Clear(crate::renderer::pipeline::clear_color(session.terminal.palette.bg)),
                     store: wgpu::StoreOp::Store,
                 },
             })],
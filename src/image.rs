@@ -6,6 +6,8 @@ use std::collections::HashMap;
 #[derive(Debug, Clone)]
 pub struct ImagePlacement {
     pub id: u32,
+    /// Image id chosen by the client (Kitty `i=`), 0 if none
+    pub image_id: u32,
     pub width: u32,
     pub height: u32,
     pub row: usize,
@@ -25,10 +27,10 @@ impl ImageManager {
     }
 
     /// Place an image at the given grid position.
-    pub fn place(&mut self, width: u32, height: u32, row: usize, col: usize, data: Vec<u8>) -> u32 {
+    pub fn place(&mut self, image_id: u32, width: u32, height: u32, row: usize, col: usize, data: Vec<u8>) -> u32 {
         let id = self.next_id;
         self.next_id += 1;
-        self.images.insert(id, ImagePlacement { id, width, height, row, col, data });
+        self.images.insert(id, ImagePlacement { id, image_id, width, height, row, col, data });
         id
     }
 
@@ -37,6 +39,13 @@ impl ImageManager {
         self.images.remove(&id).is_some()
     }
 
+    /// Remove every placement of a client image id. Returns how many were removed.
+    pub fn remove_image(&mut self, image_id: u32) -> usize {
+        let before = self.images.len();
+        self.images.retain(|_, img| img.image_id != image_id);
+        before - self.images.len()
+    }
+
     /// Get all visible images (for rendering).
     pub fn visible(&self, scroll_top: usize, scroll_bottom: usize) -> Vec<&ImagePlacement> {
         self.images.values()
@@ -80,6 +89,58 @@ pub enum KittyCommand {
     Query,
 }
 
+/// Numeric control keys of a Kitty graphics command.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KittyControl {
+    /// `a=T` — transmit and display in one step
+    pub display: bool,
+    /// `i=` image id (0 = none given)
+    pub id: u32,
+    /// `f=` pixel format: 24 (RGB), 32 (RGBA, default), 100 (PNG)
+    pub format: u32,
+    /// `s=` / `v=` image size in pixels
+    pub width: u32,
+    pub height: u32,
+    /// `q=` response suppression: 1 = no OK replies, 2 = no replies at all
+    pub quiet: u32,
+    /// `d=` what a delete targets: `a`/`A` all (default), `i`/`I` by id, ...
+    pub delete: char,
+}
+
+/// Parse the control keys and base64 payload of a Kitty graphics command.
+pub fn parse_kitty_control(payload: &str) -> (KittyControl, &str) {
+    let (params_str, data) = payload.split_once(';').unwrap_or((payload, ""));
+    let mut ctl = KittyControl { format: 32, delete: 'a', ..Default::default() };
+    for kv in params_str.split(',') {
+        let Some((k, v)) = kv.split_once('=') else { continue };
+        let n = v.parse().unwrap_or(0);
+        match k {
+            "a" => ctl.display = v == "T",
+            "i" => ctl.id = n,
+            "f" => ctl.format = n,
+            "s" => ctl.width = n,
+            "v" => ctl.height = n,
+            "q" => ctl.quiet = n,
+            "d" => ctl.delete = v.chars().next().unwrap_or('a'),
+            _ => {}
+        }
+    }
+    (ctl, data)
+}
+
+/// Convert raw Kitty pixel data (f=24 or f=32) to RGBA.
+/// Returns None for unsupported formats or a size mismatch.
+pub fn kitty_pixels_to_rgba(ctl: &KittyControl, pixels: &[u8]) -> Option<Vec<u8>> {
+    let count = ctl.width as usize * ctl.height as usize;
+    match ctl.format {
+        32 if pixels.len() == count * 4 => Some(pixels.to_vec()),
+        24 if pixels.len() == count * 3 => Some(
+            pixels.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect()
+        ),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -87,7 +148,7 @@ mod tests {
     #[test]
     fn test_image_place_and_remove() {
         let mut mgr = ImageManager::new();
-        let id = mgr.place(100, 50, 5, 0, vec![0u8; 100 * 50 * 4]);
+        let id = mgr.place(0, 100, 50, 5, 0, vec![0u8; 100 * 50 * 4]);
         assert_eq!(mgr.count(), 1);
         assert!(mgr.remove(id));
         assert_eq!(mgr.count(), 0);
@@ -96,8 +157,8 @@ mod tests {
     #[test]
     fn test_image_visible() {
         let mut mgr = ImageManager::new();
-        mgr.place(10, 10, 2, 0, vec![]);
-        mgr.place(10, 10, 50, 0, vec![]);
+        mgr.place(0, 10, 10, 2, 0, vec![]);
+        mgr.place(0, 10, 10, 50, 0, vec![]);
         let visible = mgr.visible(0, 24);
         assert_eq!(visible.len(), 1);
     }
@@ -105,8 +166,8 @@ mod tests {
     #[test]
     fn test_image_clear() {
         let mut mgr = ImageManager::new();
-        mgr.place(10, 10, 0, 0, vec![]);
-        mgr.place(10, 10, 1, 0, vec![]);
+        mgr.place(0, 10, 10, 0, 0, vec![]);
+        mgr.place(0, 10, 10, 1, 0, vec![]);
         mgr.clear();
         assert_eq!(mgr.count(), 0);
     }
@@ -126,6 +187,27 @@ mod tests {
         assert_eq!(parse_kitty_graphics("a=q,i=1"), Some(KittyCommand::Query));
     }
 
+    #[test]
+    fn test_parse_kitty_control() {
+        let (ctl, data) = parse_kitty_control("a=T,i=7,f=24,s=2,v=1,q=1;AAAA");
+        assert!(ctl.display);
+        assert_eq!((ctl.id, ctl.format, ctl.width, ctl.height, ctl.quiet), (7, 24, 2, 1, 1));
+        assert_eq!(data, "AAAA");
+        let (ctl, _) = parse_kitty_control("a=q");
+        assert_eq!(ctl.format, 32); // default RGBA
+        assert_eq!(ctl.delete, 'a');
+        assert_eq!(parse_kitty_control("a=d,d=I,i=3").0.delete, 'I');
+    }
+
+    #[test]
+    fn test_kitty_pixels_rgb_to_rgba() {
+        let (ctl, _) = parse_kitty_control("f=24,s=2,v=1");
+        let rgba = kitty_pixels_to_rgba(&ctl, &[1, 2, 3, 4, 5, 6]).unwrap();
+        assert_eq!(rgba, vec![1, 2, 3, 255, 4, 5, 6, 255]);
+        // Size mismatch is rejected
+        assert!(kitty_pixels_to_rgba(&ctl, &[1, 2, 3]).is_none());
+    }
+
     #[test]
     fn test_parse_kitty_default_transmit() {
         // No 'a' param defaults to transmit
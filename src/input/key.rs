@@ -0,0 +1,127 @@
+use bitflags::bitflags;
+
+/// A key, independent of the windowing toolkit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    /// A key that produces a character, as its unshifted codepoint
+    /// ('a' for Shift+A, '1' for Shift+1 on a US layout)
+    Char(char),
+    Enter,
+    Tab,
+    Backspace,
+    Escape,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Insert,
+    Delete,
+    /// Function key F1–F35
+    F(u8),
+    /// Numeric keypad key: '0'–'9', '.', '/', '*', '-', '+', '=', or '\r'
+    /// for keypad Enter
+    Keypad(char),
+}
+
+/// Keypad keys in the order of their kitty key numbers (57399..=57415).
+pub(super) const KEYPAD_KEYS: [char; 17] = [
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', '.', '/', '*', '-', '+', '\r', '=',
+];
+
+impl Key {
+    /// Key for a kitty keyboard protocol key number: a Unicode codepoint
+    /// or a private-use number for functional keys (57348 Insert ..
+    /// 57357 End, 57364 F1 .. 57398 F35, 57399 keypad 0 .. 57415 keypad =).
+    /// Used by shells that hand keys over FFI.
+    pub fn from_kitty_code(code: u32) -> Option<Key> {
+        Some(match code {
+            13 => Key::Enter,
+            9 => Key::Tab,
+            127 => Key::Backspace,
+            27 => Key::Escape,
+            57348 => Key::Insert,
+            57349 => Key::Delete,
+            57350 => Key::Left,
+            57351 => Key::Right,
+            57352 => Key::Up,
+            57353 => Key::Down,
+            57354 => Key::PageUp,
+            57355 => Key::PageDown,
+            57356 => Key::Home,
+            57357 => Key::End,
+            57364..=57398 => Key::F((code - 57363) as u8),
+            57399..=57415 => Key::Keypad(KEYPAD_KEYS[(code - 57399) as usize]),
+            _ => Key::Char(char::from_u32(code).filter(|c| !c.is_control())?),
+        })
+    }
+}
+
+bitflags! {
+    /// Modifier bits, numbered as in xterm and kitty modifier parameters
+    /// (the parameter is 1 + bits).
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct Modifiers: u8 {
+        const SHIFT     = 0b0000_0001;
+        const ALT       = 0b0000_0010;
+        const CTRL      = 0b0000_0100;
+        const SUPER     = 0b0000_1000;
+        const HYPER     = 0b0001_0000;
+        const META      = 0b0010_0000;
+        const CAPS_LOCK = 0b0100_0000;
+        const NUM_LOCK  = 0b1000_0000;
+        const LOCKS = Self::CAPS_LOCK.bits() | Self::NUM_LOCK.bits();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyEventKind {
+    #[default]
+    Press,
+    Repeat,
+    Release,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub key: Key,
+    pub mods: Modifiers,
+    pub kind: KeyEventKind,
+    /// Text the key produces with the current layout and modifiers
+    /// ("A" for Shift+A), empty if none. When Alt acts as Meta, the text
+    /// without Alt ("!" for Alt+Shift+1); Alt-composed text ("∫") belongs
+    /// in an event without `ALT`.
+    pub text: String,
+}
+
+impl KeyEvent {
+    /// A key press without text.
+    pub fn new(key: Key, mods: Modifiers) -> Self {
+        Self { key, mods, kind: KeyEventKind::Press, text: String::new() }
+    }
+
+    /// A key press producing `text`.
+    pub fn with_text(key: Key, mods: Modifiers, text: &str) -> Self {
+        Self { text: text.to_string(), ..Self::new(key, mods) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_kitty_code() {
+        assert_eq!(Key::from_kitty_code('a' as u32), Some(Key::Char('a')));
+        assert_eq!(Key::from_kitty_code(13), Some(Key::Enter));
+        assert_eq!(Key::from_kitty_code(57352), Some(Key::Up));
+        assert_eq!(Key::from_kitty_code(57364), Some(Key::F(1)));
+        assert_eq!(Key::from_kitty_code(57398), Some(Key::F(35)));
+        assert_eq!(Key::from_kitty_code(57414), Some(Key::Keypad('\r')));
+        assert_eq!(Key::from_kitty_code(1), None);
+        assert_eq!(Key::from_kitty_code(0xd800), None);
+    }
+}
@@ -0,0 +1,388 @@
+//! Key encoding for the PTY: the kitty protocol when the application
+//! asked for it, otherwise the xterm encoding shaped by DECCKM, DECKPAM,
+//! LNM and modifyOtherKeys.
+
+use super::key::{Key, KeyEvent, KeyEventKind, Modifiers};
+use super::kitty::{encode_kitty, KeyboardFlags};
+use crate::core::{MouseEncoding, MouseMode};
+
+/// Terminal state that changes what keys and the mouse send.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TerminalModes {
+    /// DECCKM: unmodified cursor keys and Home/End send `SS3 x`
+    pub cursor_keys_app: bool,
+    /// DECKPAM: keypad keys send `SS3 x`
+    pub keypad_app: bool,
+    /// LNM: Enter sends CR LF
+    pub newline_mode: bool,
+    /// xterm modifyOtherKeys level (`CSI > 4 ; level m`), 0–2
+    pub modify_other_keys: u8,
+    /// Kitty keyboard flags in effect
+    pub keyboard: KeyboardFlags,
+    /// Which mouse events are reported (modes 9, 1000, 1002, 1003)
+    pub mouse_mode: MouseMode,
+    /// How mouse reports are encoded (modes 1005, 1006, 1015, 1016)
+    pub mouse_encoding: MouseEncoding,
+    /// Mode 1007: the wheel sends cursor keys on the alternate screen
+    pub alternate_scroll: bool,
+    /// The alternate screen is active
+    pub alt_screen: bool,
+}
+
+/// Bytes to send for a key event. Empty when the key sends nothing, as
+/// for releases outside the kitty protocol.
+pub fn encode_key(ev: KeyEvent, modes: &TerminalModes) -> Vec<u8> {
+    if let Some(bytes) = encode_kitty(&ev, modes.keyboard) {
+        return bytes;
+    }
+    if ev.kind == KeyEventKind::Release {
+        return Vec::new();
+    }
+    let mods = ev.mods - Modifiers::LOCKS;
+    let alt = mods.contains(Modifiers::ALT);
+    let other_keys = modes.modify_other_keys == 2 && !mods.is_empty();
+    let bytes = match ev.key {
+        Key::Char(c) => return encode_char(c, &ev.text, mods, modes),
+        Key::Enter if other_keys => return modified_other_key(mods, 13),
+        Key::Enter if modes.newline_mode => b"\r\n".to_vec(),
+        Key::Enter => b"\r".to_vec(),
+        Key::Tab if mods == Modifiers::SHIFT => return b"\x1b[Z".to_vec(),
+        Key::Tab if other_keys => return modified_other_key(mods, 9),
+        Key::Tab => b"\t".to_vec(),
+        Key::Backspace if other_keys => return modified_other_key(mods, 127),
+        Key::Backspace if mods.contains(Modifiers::CTRL) => vec![0x08],
+        Key::Backspace => vec![0x7f],
+        Key::Escape if other_keys => return modified_other_key(mods, 27),
+        Key::Escape => vec![0x1b],
+        Key::Up => return cursor_key(b'A', mods, modes),
+        Key::Down => return cursor_key(b'B', mods, modes),
+        Key::Right => return cursor_key(b'C', mods, modes),
+        Key::Left => return cursor_key(b'D', mods, modes),
+        Key::Home => return cursor_key(b'H', mods, modes),
+        Key::End => return cursor_key(b'F', mods, modes),
+        Key::Insert => return tilde_key(2, mods),
+        Key::Delete => return tilde_key(3, mods),
+        Key::PageUp => return tilde_key(5, mods),
+        Key::PageDown => return tilde_key(6, mods),
+        Key::F(n) => return function_key(n, mods),
+        Key::Keypad(c) => return keypad_key(c, ev, modes),
+    };
+    with_meta(bytes, alt)
+}
+
+/// Ctrl applies the C0 mapping, Alt prefixes ESC; modifyOtherKeys sends
+/// `CSI 27 ; mods ; code ~` for combinations (level 2) or only for those
+/// with no legacy byte (level 1).
+fn encode_char(c: char, text: &str, mods: Modifiers, modes: &TerminalModes) -> Vec<u8> {
+    let ctrl = mods.contains(Modifiers::CTRL);
+    let control = if ctrl { control_byte(c) } else { None };
+    let typed = typed_text(c, text, mods);
+    let other_keys = match modes.modify_other_keys {
+        2 => !(mods - Modifiers::SHIFT).is_empty(),
+        1 => ctrl && control.is_none(),
+        _ => false,
+    };
+    if other_keys {
+        let mut chars = typed.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(t), None) => t,
+            _ => c,
+        };
+        return modified_other_key(mods, code as u32);
+    }
+    let bytes = match control {
+        Some(b) => vec![b],
+        None => typed.into_bytes(),
+    };
+    with_meta(bytes, mods.contains(Modifiers::ALT))
+}
+
+/// What the key types without Ctrl/Alt. Under Alt, `text` is the key
+/// with Shift applied (`!` for Alt+Shift+1); toolkits fold Ctrl into
+/// `text` (or drop it), so fall back to the key's char, shifted for
+/// letters.
+fn typed_text(c: char, text: &str, mods: Modifiers) -> String {
+    if !text.is_empty() && !mods.contains(Modifiers::CTRL) {
+        text.to_string()
+    } else if mods.contains(Modifiers::SHIFT) {
+        c.to_ascii_uppercase().to_string()
+    } else {
+        c.to_string()
+    }
+}
+
+/// The C0 byte for Ctrl+`c`, as xterm maps it on a US layout.
+fn control_byte(c: char) -> Option<u8> {
+    Some(match c {
+        'a'..='z' => c as u8 - b'a' + 1,
+        'A'..='Z' => c as u8 - b'A' + 1,
+        '@' | ' ' | '2' => 0x00,
+        '[' | '3' => 0x1b,
+        '\\' | '4' => 0x1c,
+        ']' | '5' => 0x1d,
+        '^' | '~' | '6' => 0x1e,
+        '_' | '/' | '7' => 0x1f,
+        '?' | '8' => 0x7f,
+        _ => return None,
+    })
+}
+
+fn with_meta(mut bytes: Vec<u8>, alt: bool) -> Vec<u8> {
+    if alt {
+        bytes.insert(0, 0x1b);
+    }
+    bytes
+}
+
+/// xterm modifier parameter: 1 + Shift 1, Alt 2, Ctrl 4, Meta 8.
+fn modifier_param(mods: Modifiers) -> u8 {
+    let xterm = Modifiers::SHIFT | Modifiers::ALT | Modifiers::CTRL | Modifiers::SUPER;
+    1 + (mods & xterm).bits()
+}
+
+fn modified_other_key(mods: Modifiers, code: u32) -> Vec<u8> {
+    format!("\x1b[27;{};{}~", modifier_param(mods), code).into_bytes()
+}
+
+/// Arrows and Home/End: `SS3 x` under DECCKM, `CSI x` otherwise,
+/// `CSI 1 ; mods x` when modified.
+fn cursor_key(letter: u8, mods: Modifiers, modes: &TerminalModes) -> Vec<u8> {
+    if !mods.is_empty() {
+        format!("\x1b[1;{}{}", modifier_param(mods), letter as char).into_bytes()
+    } else if modes.cursor_keys_app {
+        vec![0x1b, b'O', letter]
+    } else {
+        vec![0x1b, b'[', letter]
+    }
+}
+
+fn tilde_key(n: u8, mods: Modifiers) -> Vec<u8> {
+    if mods.is_empty() {
+        format!("\x1b[{}~", n).into_bytes()
+    } else {
+        format!("\x1b[{};{}~", n, modifier_param(mods)).into_bytes()
+    }
+}
+
+/// F1–F4 are `SS3 P`..`SS3 S`, F5–F12 `CSI n ~`. F13–F24 are Shift+F1–F12,
+/// as xterm's terminfo has them; higher keys send nothing.
+fn function_key(n: u8, mods: Modifiers) -> Vec<u8> {
+    match n {
+        1..=4 => {
+            let letter = b"PQRS"[n as usize - 1];
+            if mods.is_empty() {
+                vec![0x1b, b'O', letter]
+            } else {
+                format!("\x1b[1;{}{}", modifier_param(mods), letter as char).into_bytes()
+            }
+        }
+        5..=12 => tilde_key([15, 17, 18, 19, 20, 21, 23, 24][n as usize - 5], mods),
+        13..=24 => function_key(n - 12, mods | Modifiers::SHIFT),
+        _ => Vec::new(),
+    }
+}
+
+/// DECKPAM sends `SS3 p`..`SS3 y` for digits and `SS3` + the VT100 final
+/// for the rest; numeric mode sends what the key types.
+fn keypad_key(c: char, ev: KeyEvent, modes: &TerminalModes) -> Vec<u8> {
+    if modes.keypad_app {
+        let letter = match c {
+            '0'..='9' => b'p' + (c as u8 - b'0'),
+            '.' => b'n',
+            '+' => b'k',
+            '-' => b'm',
+            '*' => b'j',
+            '/' => b'o',
+            '=' => b'X',
+            '\r' => b'M',
+            _ => return Vec::new(),
+        };
+        return vec![0x1b, b'O', letter];
+    }
+    let key = if c == '\r' { Key::Enter } else { Key::Char(c) };
+    encode_key(KeyEvent { key, ..ev }, &TerminalModes { keyboard: KeyboardFlags::empty(), ..*modes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enc(ev: KeyEvent, modes: &TerminalModes) -> String {
+        String::from_utf8(encode_key(ev, modes)).unwrap()
+    }
+
+    fn key(key: Key, mods: Modifiers) -> String {
+        enc(KeyEvent::new(key, mods), &TerminalModes::default())
+    }
+
+    const NONE: Modifiers = Modifiers::empty();
+    const SHIFT: Modifiers = Modifiers::SHIFT;
+    const ALT: Modifiers = Modifiers::ALT;
+    const CTRL: Modifiers = Modifiers::CTRL;
+
+    #[test]
+    fn test_text_keys() {
+        let m = TerminalModes::default();
+        assert_eq!(enc(KeyEvent::with_text(Key::Char('a'), NONE, "a"), &m), "a");
+        assert_eq!(enc(KeyEvent::with_text(Key::Char('a'), SHIFT, "A"), &m), "A");
+        assert_eq!(enc(KeyEvent::with_text(Key::Char('1'), SHIFT, "!"), &m), "!");
+        assert_eq!(enc(KeyEvent::with_text(Key::Char('e'), NONE, "é"), &m), "é");
+        // Caps Lock doesn't stop text
+        assert_eq!(enc(KeyEvent::with_text(Key::Char('a'), Modifiers::CAPS_LOCK, "A"), &m), "A");
+        // No text from the toolkit: the key's char
+        assert_eq!(key(Key::Char('x'), NONE), "x");
+    }
+
+    #[test]
+    fn test_ctrl_keys() {
+        assert_eq!(key(Key::Char('a'), CTRL), "\x01");
+        assert_eq!(key(Key::Char('z'), CTRL), "\x1a");
+        assert_eq!(key(Key::Char('c'), CTRL | SHIFT), "\x03");
+        assert_eq!(key(Key::Char(' '), CTRL), "\x00");
+        assert_eq!(key(Key::Char('2'), CTRL), "\x00");
+        assert_eq!(key(Key::Char('['), CTRL), "\x1b");
+        assert_eq!(key(Key::Char('\\'), CTRL), "\x1c");
+        assert_eq!(key(Key::Char(']'), CTRL), "\x1d");
+        assert_eq!(key(Key::Char('6'), CTRL), "\x1e");
+        assert_eq!(key(Key::Char('/'), CTRL), "\x1f");
+        assert_eq!(key(Key::Char('8'), CTRL), "\x7f");
+        // No C0 mapping: Ctrl is dropped
+        assert_eq!(key(Key::Char('.'), CTRL), ".");
+    }
+
+    #[test]
+    fn test_alt_is_meta() {
+        let m = TerminalModes::default();
+        assert_eq!(key(Key::Char('b'), ALT), "\x1bb");
+        assert_eq!(enc(KeyEvent::with_text(Key::Char('b'), ALT, "b"), &m), "\x1bb");
+        assert_eq!(key(Key::Char('b'), ALT | SHIFT), "\x1bB");
+        // Shifted symbols keep their shifted text
+        assert_eq!(enc(KeyEvent::with_text(Key::Char('1'), ALT | SHIFT, "!"), &m), "\x1b!");
+        assert_eq!(enc(KeyEvent::with_text(Key::Char('/'), ALT | SHIFT, "?"), &m), "\x1b?");
+        assert_eq!(enc(KeyEvent::with_text(Key::Char(';'), ALT | SHIFT, ":"), &m), "\x1b:");
+        // Ctrl still maps from the key, not the text
+        assert_eq!(enc(KeyEvent::with_text(Key::Char('x'), ALT | CTRL, "\x18"), &m), "\x1b\x18");
+        assert_eq!(key(Key::Char('x'), ALT | CTRL), "\x1b\x18");
+        assert_eq!(key(Key::Backspace, ALT), "\x1b\x7f");
+        assert_eq!(key(Key::Enter, ALT), "\x1b\r");
+        assert_eq!(key(Key::Escape, ALT), "\x1b\x1b");
+    }
+
+    #[test]
+    fn test_named_keys() {
+        assert_eq!(key(Key::Enter, NONE), "\r");
+        assert_eq!(key(Key::Tab, NONE), "\t");
+        assert_eq!(key(Key::Tab, SHIFT), "\x1b[Z");
+        assert_eq!(key(Key::Backspace, NONE), "\x7f");
+        assert_eq!(key(Key::Backspace, CTRL), "\x08");
+        assert_eq!(key(Key::Escape, NONE), "\x1b");
+        assert_eq!(key(Key::Insert, NONE), "\x1b[2~");
+        assert_eq!(key(Key::Delete, NONE), "\x1b[3~");
+        assert_eq!(key(Key::PageUp, NONE), "\x1b[5~");
+        assert_eq!(key(Key::PageDown, NONE), "\x1b[6~");
+        assert_eq!(key(Key::Delete, CTRL), "\x1b[3;5~");
+        assert_eq!(key(Key::PageUp, SHIFT), "\x1b[5;2~");
+
+        let lnm = TerminalModes { newline_mode: true, ..Default::default() };
+        assert_eq!(enc(KeyEvent::new(Key::Enter, NONE), &lnm), "\r\n");
+    }
+
+    #[test]
+    fn test_cursor_keys() {
+        assert_eq!(key(Key::Up, NONE), "\x1b[A");
+        assert_eq!(key(Key::Down, NONE), "\x1b[B");
+        assert_eq!(key(Key::Right, NONE), "\x1b[C");
+        assert_eq!(key(Key::Left, NONE), "\x1b[D");
+        assert_eq!(key(Key::Home, NONE), "\x1b[H");
+        assert_eq!(key(Key::End, NONE), "\x1b[F");
+        assert_eq!(key(Key::Up, SHIFT), "\x1b[1;2A");
+        assert_eq!(key(Key::Left, ALT), "\x1b[1;3D");
+        assert_eq!(key(Key::Right, CTRL), "\x1b[1;5C");
+        assert_eq!(key(Key::End, CTRL | SHIFT), "\x1b[1;6F");
+        assert_eq!(key(Key::Down, Modifiers::SUPER), "\x1b[1;9B");
+
+        let app = TerminalModes { cursor_keys_app: true, ..Default::default() };
+        assert_eq!(enc(KeyEvent::new(Key::Up, NONE), &app), "\x1bOA");
+        assert_eq!(enc(KeyEvent::new(Key::Home, NONE), &app), "\x1bOH");
+        // Modified keys keep the CSI form under DECCKM
+        assert_eq!(enc(KeyEvent::new(Key::Up, SHIFT), &app), "\x1b[1;2A");
+    }
+
+    #[test]
+    fn test_function_keys() {
+        let expected = [
+            "\x1bOP", "\x1bOQ", "\x1bOR", "\x1bOS", "\x1b[15~", "\x1b[17~",
+            "\x1b[18~", "\x1b[19~", "\x1b[20~", "\x1b[21~", "\x1b[23~", "\x1b[24~",
+            "\x1b[1;2P", "\x1b[1;2Q", "\x1b[1;2R", "\x1b[1;2S", "\x1b[15;2~", "\x1b[17;2~",
+            "\x1b[18;2~", "\x1b[19;2~", "\x1b[20;2~", "\x1b[21;2~", "\x1b[23;2~", "\x1b[24;2~",
+        ];
+        for (n, seq) in (1..=24).zip(expected) {
+            assert_eq!(key(Key::F(n), NONE), seq, "F{}", n);
+        }
+        assert_eq!(key(Key::F(1), CTRL), "\x1b[1;5P");
+        assert_eq!(key(Key::F(5), ALT), "\x1b[15;3~");
+        assert_eq!(key(Key::F(13), CTRL), "\x1b[1;6P");
+        assert_eq!(key(Key::F(25), NONE), "");
+    }
+
+    #[test]
+    fn test_keypad() {
+        let m = TerminalModes::default();
+        assert_eq!(enc(KeyEvent::with_text(Key::Keypad('5'), NONE, "5"), &m), "5");
+        assert_eq!(enc(KeyEvent::new(Key::Keypad('+'), NONE), &m), "+");
+        assert_eq!(enc(KeyEvent::new(Key::Keypad('\r'), NONE), &m), "\r");
+
+        let app = TerminalModes { keypad_app: true, ..Default::default() };
+        assert_eq!(enc(KeyEvent::new(Key::Keypad('0'), NONE), &app), "\x1bOp");
+        assert_eq!(enc(KeyEvent::new(Key::Keypad('9'), NONE), &app), "\x1bOy");
+        assert_eq!(enc(KeyEvent::new(Key::Keypad('.'), NONE), &app), "\x1bOn");
+        assert_eq!(enc(KeyEvent::new(Key::Keypad('-'), NONE), &app), "\x1bOm");
+        assert_eq!(enc(KeyEvent::new(Key::Keypad('*'), NONE), &app), "\x1bOj");
+        assert_eq!(enc(KeyEvent::new(Key::Keypad('/'), NONE), &app), "\x1bOo");
+        assert_eq!(enc(KeyEvent::new(Key::Keypad('\r'), NONE), &app), "\x1bOM");
+    }
+
+    #[test]
+    fn test_modify_other_keys() {
+        let level1 = TerminalModes { modify_other_keys: 1, ..Default::default() };
+        let ev = |c, mods| KeyEvent::new(Key::Char(c), mods);
+        // Level 1: only combinations without a legacy byte
+        assert_eq!(enc(ev('a', CTRL), &level1), "\x01");
+        assert_eq!(enc(ev('.', CTRL), &level1), "\x1b[27;5;46~");
+        assert_eq!(enc(ev('a', ALT), &level1), "\x1ba");
+
+        let level2 = TerminalModes { modify_other_keys: 2, ..Default::default() };
+        assert_eq!(enc(ev('a', CTRL), &level2), "\x1b[27;5;97~");
+        assert_eq!(enc(ev('a', ALT), &level2), "\x1b[27;3;97~");
+        assert_eq!(enc(ev('a', CTRL | SHIFT), &level2), "\x1b[27;6;65~");
+        assert_eq!(enc(KeyEvent::with_text(Key::Char('a'), SHIFT, "A"), &level2), "A");
+        assert_eq!(enc(KeyEvent::new(Key::Enter, CTRL), &level2), "\x1b[27;5;13~");
+        assert_eq!(enc(KeyEvent::new(Key::Tab, CTRL), &level2), "\x1b[27;5;9~");
+        assert_eq!(enc(KeyEvent::new(Key::Tab, SHIFT), &level2), "\x1b[Z");
+        assert_eq!(enc(KeyEvent::new(Key::Enter, NONE), &level2), "\r");
+        // Cursor keys are unaffected
+        assert_eq!(enc(KeyEvent::new(Key::Up, CTRL), &level2), "\x1b[1;5A");
+    }
+
+    #[test]
+    fn test_kitty_takes_precedence() {
+        let modes = TerminalModes {
+            modify_other_keys: 2,
+            keyboard: KeyboardFlags::DISAMBIGUATE,
+            ..Default::default()
+        };
+        assert_eq!(enc(KeyEvent::new(Key::Char('a'), CTRL), &modes), "\x1b[97;5u");
+        assert_eq!(enc(KeyEvent::new(Key::Escape, NONE), &modes), "\x1b[27u");
+        // Left to the legacy encoding
+        assert_eq!(enc(KeyEvent::with_text(Key::Char('a'), NONE, "a"), &modes), "a");
+    }
+
+    #[test]
+    fn test_release_sends_nothing() {
+        let mut ev = KeyEvent::new(Key::Up, NONE);
+        ev.kind = KeyEventKind::Release;
+        assert_eq!(enc(ev.clone(), &TerminalModes::default()), "");
+        ev.kind = KeyEventKind::Repeat;
+        assert_eq!(enc(ev, &TerminalModes::default()), "\x1b[A");
+    }
+}
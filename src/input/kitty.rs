@@ -0,0 +1,276 @@
+//! Kitty keyboard protocol (progressive enhancement): the flag stack
+//! driven by `CSI > u` / `CSI < u` / `CSI = u`, and the
+//! `CSI keycode;modifiers:event;text u` encoder.
+
+use bitflags::bitflags;
+use super::key::{Key, KeyEvent, KeyEventKind, Modifiers, KEYPAD_KEYS};
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+    pub struct KeyboardFlags: u8 {
+        /// Escape and Ctrl/Alt combinations become `CSI u` sequences
+        const DISAMBIGUATE          = 0b0000_0001;
+        /// Report repeat and release events
+        const REPORT_EVENT_TYPES    = 0b0000_0010;
+        /// Add the shifted key to the key code
+        const REPORT_ALTERNATE_KEYS = 0b0000_0100;
+        /// Every key, including plain text, becomes an escape code
+        const REPORT_ALL_KEYS       = 0b0000_1000;
+        /// Add the produced text (with REPORT_ALL_KEYS)
+        const REPORT_TEXT           = 0b0001_0000;
+    }
+}
+
+/// Flags pushed by the application; the top entry is in effect. The
+/// terminal keeps one stack per screen.
+#[derive(Debug, Clone, Default)]
+pub struct KeyboardModeStack {
+    stack: Vec<KeyboardFlags>,
+}
+
+impl KeyboardModeStack {
+    /// Pushing onto a full stack evicts the oldest entry.
+    pub const MAX_DEPTH: usize = 16;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn current(&self) -> KeyboardFlags {
+        self.stack.last().copied().unwrap_or_default()
+    }
+
+    /// `CSI > flags u`
+    pub fn push(&mut self, flags: KeyboardFlags) {
+        if self.stack.len() >= Self::MAX_DEPTH {
+            self.stack.remove(0);
+        }
+        self.stack.push(flags);
+    }
+
+    /// `CSI < n u`: popping every entry resets the flags.
+    pub fn pop(&mut self, n: usize) {
+        self.stack.truncate(self.stack.len().saturating_sub(n));
+    }
+
+    /// `CSI = flags ; mode u`: mode 1 replaces the current flags, 2 adds
+    /// to them and 3 removes from them.
+    pub fn set(&mut self, flags: KeyboardFlags, mode: u16) {
+        let current = self.current();
+        let flags = match mode {
+            1 => flags,
+            2 => current | flags,
+            3 => current - flags,
+            _ => return,
+        };
+        match self.stack.last_mut() {
+            Some(top) => *top = flags,
+            None => self.stack.push(flags),
+        }
+    }
+}
+
+/// Encode a key event under the kitty keyboard protocol. Returns `None`
+/// when `flags` leave the key to the legacy encoding (plain text,
+/// unmodified Enter/Tab/Backspace, unmodified cursor and function keys),
+/// and an empty sequence for events that are not reported at all.
+pub fn encode_kitty(ev: &KeyEvent, flags: KeyboardFlags) -> Option<Vec<u8>> {
+    let report_all = flags.contains(KeyboardFlags::REPORT_ALL_KEYS);
+    if !report_all && !flags.contains(KeyboardFlags::DISAMBIGUATE) {
+        return None;
+    }
+    let event_types = flags.contains(KeyboardFlags::REPORT_EVENT_TYPES);
+    let mut mods = ev.mods;
+    if !report_all {
+        // Lock keys only matter once every key is an escape code
+        mods.remove(Modifiers::LOCKS);
+
+        let legacy = match ev.key {
+            Key::Char(_) => (mods - Modifiers::SHIFT).is_empty(),
+            // Keypad Enter is the one non-text keypad key
+            Key::Keypad(c) => c != '\r' && (mods - Modifiers::SHIFT).is_empty(),
+            Key::Enter | Key::Tab | Key::Backspace => mods.is_empty(),
+            Key::Escape => false,
+            _ => mods.is_empty() && !(event_types && ev.kind != KeyEventKind::Press),
+        };
+        if legacy {
+            // Releases of keys sent as legacy bytes aren't reported
+            return if ev.kind == KeyEventKind::Release { Some(Vec::new()) } else { None };
+        }
+    }
+    if ev.kind == KeyEventKind::Release && !event_types {
+        return Some(Vec::new());
+    }
+
+    let (code, terminator) = key_code(ev.key);
+    let mut params = code.to_string();
+    if let Key::Char(c) = ev.key {
+        let mut text = ev.text.chars();
+        let shifted = text.next().filter(|&s| text.next().is_none() && s != c);
+        if let Some(shifted) = shifted.filter(|_| {
+            flags.contains(KeyboardFlags::REPORT_ALTERNATE_KEYS) && mods.contains(Modifiers::SHIFT)
+        }) {
+            params.push_str(&format!(":{}", shifted as u32));
+        }
+    }
+
+    let event = match ev.kind {
+        KeyEventKind::Press => None,
+        KeyEventKind::Repeat => Some(2),
+        KeyEventKind::Release => Some(3),
+    }.filter(|_| event_types);
+    let text: Vec<String> = if report_all && flags.contains(KeyboardFlags::REPORT_TEXT)
+        && ev.kind != KeyEventKind::Release
+    {
+        ev.text.chars().filter(|c| !c.is_control()).map(|c| (c as u32).to_string()).collect()
+    } else {
+        Vec::new()
+    };
+
+    let modifiers = 1 + mods.bits() as u16;
+    if modifiers != 1 || event.is_some() || !text.is_empty() {
+        params.push_str(&format!(";{}", modifiers));
+        if let Some(event) = event {
+            params.push_str(&format!(":{}", event));
+        }
+    }
+    if !text.is_empty() {
+        params.push(';');
+        params.push_str(&text.join(":"));
+    }
+    // `CSI 1 A` is sent as `CSI A`
+    if terminator != b'u' && terminator != b'~' && params == "1" {
+        params.clear();
+    }
+    Some(format!("\x1b[{}{}", params, terminator as char).into_bytes())
+}
+
+/// Key number and final byte: text keys are `codepoint u`, others keep
+/// their legacy `CSI 1 X` / `CSI n ~` forms, F13–F35 and the keypad use
+/// private-use codes.
+fn key_code(key: Key) -> (u32, u8) {
+    match key {
+        Key::Char(c) => (c as u32, b'u'),
+        Key::Enter => (13, b'u'),
+        Key::Tab => (9, b'u'),
+        Key::Backspace => (127, b'u'),
+        Key::Escape => (27, b'u'),
+        Key::Up => (1, b'A'),
+        Key::Down => (1, b'B'),
+        Key::Right => (1, b'C'),
+        Key::Left => (1, b'D'),
+        Key::Home => (1, b'H'),
+        Key::End => (1, b'F'),
+        Key::Insert => (2, b'~'),
+        Key::Delete => (3, b'~'),
+        Key::PageUp => (5, b'~'),
+        Key::PageDown => (6, b'~'),
+        Key::F(1) => (1, b'P'),
+        Key::F(2) => (1, b'Q'),
+        Key::F(3) => (13, b'~'),
+        Key::F(4) => (1, b'S'),
+        Key::F(n @ 5..=12) => ([15, 17, 18, 19, 20, 21, 23, 24][n as usize - 5], b'~'),
+        Key::F(n) => (57376 + n.saturating_sub(13) as u32, b'u'),
+        Key::Keypad(c) => {
+            let index = KEYPAD_KEYS.iter().position(|&k| k == c).unwrap_or(0);
+            (57399 + index as u32, b'u')
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enc(ev: &KeyEvent, flags: KeyboardFlags) -> Option<String> {
+        encode_kitty(ev, flags).map(|b| String::from_utf8(b).unwrap())
+    }
+
+    fn release(mut ev: KeyEvent) -> KeyEvent {
+        ev.kind = KeyEventKind::Release;
+        ev
+    }
+
+    #[test]
+    fn test_stack_push_pop_set() {
+        let mut s = KeyboardModeStack::new();
+        assert_eq!(s.current(), KeyboardFlags::empty());
+        s.set(KeyboardFlags::REPORT_EVENT_TYPES, 1);
+        s.push(KeyboardFlags::DISAMBIGUATE);
+        s.set(KeyboardFlags::REPORT_ALL_KEYS, 2);
+        assert_eq!(s.current(), KeyboardFlags::DISAMBIGUATE | KeyboardFlags::REPORT_ALL_KEYS);
+        s.set(KeyboardFlags::DISAMBIGUATE, 3);
+        assert_eq!(s.current(), KeyboardFlags::REPORT_ALL_KEYS);
+        s.pop(1);
+        assert_eq!(s.current(), KeyboardFlags::REPORT_EVENT_TYPES);
+        s.pop(5);
+        assert_eq!(s.current(), KeyboardFlags::empty());
+    }
+
+    #[test]
+    fn test_stack_evicts_oldest() {
+        let mut s = KeyboardModeStack::new();
+        s.push(KeyboardFlags::REPORT_TEXT);
+        for _ in 0..KeyboardModeStack::MAX_DEPTH {
+            s.push(KeyboardFlags::DISAMBIGUATE);
+        }
+        s.pop(KeyboardModeStack::MAX_DEPTH - 1);
+        assert_eq!(s.current(), KeyboardFlags::DISAMBIGUATE);
+        s.pop(1);
+        assert_eq!(s.current(), KeyboardFlags::empty());
+    }
+
+    #[test]
+    fn test_disambiguate() {
+        let f = KeyboardFlags::DISAMBIGUATE;
+        assert_eq!(enc(&KeyEvent::with_text(Key::Char('a'), Modifiers::empty(), "a"), f), None);
+        assert_eq!(enc(&KeyEvent::with_text(Key::Char('a'), Modifiers::SHIFT, "A"), f), None);
+        assert_eq!(enc(&KeyEvent::new(Key::Char('a'), Modifiers::CTRL), f).as_deref(), Some("\x1b[97;5u"));
+        assert_eq!(enc(&KeyEvent::new(Key::Char('x'), Modifiers::ALT | Modifiers::SHIFT), f).as_deref(), Some("\x1b[120;4u"));
+        assert_eq!(enc(&KeyEvent::new(Key::Escape, Modifiers::empty()), f).as_deref(), Some("\x1b[27u"));
+        assert_eq!(enc(&KeyEvent::new(Key::Enter, Modifiers::empty()), f), None);
+        assert_eq!(enc(&KeyEvent::new(Key::Enter, Modifiers::CTRL), f).as_deref(), Some("\x1b[13;5u"));
+        assert_eq!(enc(&KeyEvent::new(Key::Up, Modifiers::empty()), f), None);
+        assert_eq!(enc(&KeyEvent::new(Key::Up, Modifiers::SHIFT), f).as_deref(), Some("\x1b[1;2A"));
+        assert_eq!(enc(&KeyEvent::new(Key::Delete, Modifiers::CTRL), f).as_deref(), Some("\x1b[3;5~"));
+        assert_eq!(enc(&KeyEvent::with_text(Key::Keypad('5'), Modifiers::empty(), "5"), f), None);
+        assert_eq!(enc(&KeyEvent::new(Key::Keypad('\r'), Modifiers::empty()), f).as_deref(), Some("\x1b[57414u"));
+        // Lock keys are ignored below REPORT_ALL_KEYS
+        assert_eq!(enc(&KeyEvent::new(Key::Char('a'), Modifiers::CTRL | Modifiers::CAPS_LOCK), f).as_deref(), Some("\x1b[97;5u"));
+    }
+
+    #[test]
+    fn test_event_types() {
+        let f = KeyboardFlags::DISAMBIGUATE | KeyboardFlags::REPORT_EVENT_TYPES;
+        let ctrl_a = KeyEvent::new(Key::Char('a'), Modifiers::CTRL);
+        assert_eq!(enc(&release(ctrl_a.clone()), f).as_deref(), Some("\x1b[97;5:3u"));
+        let repeat = KeyEvent { kind: KeyEventKind::Repeat, ..ctrl_a.clone() };
+        assert_eq!(enc(&repeat, f).as_deref(), Some("\x1b[97;5:2u"));
+        // Text keys and Enter stay legacy; their releases are dropped
+        let a = KeyEvent::with_text(Key::Char('a'), Modifiers::empty(), "a");
+        assert_eq!(enc(&release(a), f).as_deref(), Some(""));
+        assert_eq!(enc(&release(KeyEvent::new(Key::Enter, Modifiers::empty())), f).as_deref(), Some(""));
+        assert_eq!(enc(&release(KeyEvent::new(Key::Up, Modifiers::empty())), f).as_deref(), Some("\x1b[1;1:3A"));
+        // Without REPORT_EVENT_TYPES releases are never reported
+        assert_eq!(enc(&release(ctrl_a), KeyboardFlags::DISAMBIGUATE).as_deref(), Some(""));
+    }
+
+    #[test]
+    fn test_report_all_keys_with_text() {
+        let f = KeyboardFlags::REPORT_ALL_KEYS;
+        assert_eq!(enc(&KeyEvent::with_text(Key::Char('a'), Modifiers::empty(), "a"), f).as_deref(), Some("\x1b[97u"));
+        assert_eq!(enc(&KeyEvent::new(Key::Enter, Modifiers::empty()), f).as_deref(), Some("\x1b[13u"));
+        assert_eq!(enc(&KeyEvent::new(Key::Up, Modifiers::empty()), f).as_deref(), Some("\x1b[A"));
+        assert_eq!(enc(&KeyEvent::new(Key::F(3), Modifiers::empty()), f).as_deref(), Some("\x1b[13~"));
+        assert_eq!(enc(&KeyEvent::new(Key::F(5), Modifiers::empty()), f).as_deref(), Some("\x1b[15~"));
+        assert_eq!(enc(&KeyEvent::new(Key::F(13), Modifiers::empty()), f).as_deref(), Some("\x1b[57376u"));
+        assert_eq!(enc(&KeyEvent::new(Key::Char('a'), Modifiers::CAPS_LOCK), f).as_deref(), Some("\x1b[97;65u"));
+
+        let all = KeyboardFlags::all();
+        let shift_a = KeyEvent::with_text(Key::Char('a'), Modifiers::SHIFT, "A");
+        assert_eq!(enc(&shift_a, all).as_deref(), Some("\x1b[97:65;2;65u"));
+        assert_eq!(enc(&release(shift_a), all).as_deref(), Some("\x1b[97:65;2:3u"));
+        let ctrl_enter = KeyEvent::with_text(Key::Enter, Modifiers::CTRL, "\r");
+        assert_eq!(enc(&ctrl_enter, all).as_deref(), Some("\x1b[13;5u"));
+    }
+}
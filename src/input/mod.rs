@@ -0,0 +1,12 @@
+//! Input encoding: turns toolkit-independent key and mouse events into the bytes
+//! the application on the PTY expects.
+
+mod key;
+mod keyboard;
+mod kitty;
+mod mouse;
+
+pub use key::{Key, KeyEvent, KeyEventKind, Modifiers};
+pub use keyboard::{encode_key, TerminalModes};
+pub use kitty::{encode_kitty, KeyboardFlags, KeyboardModeStack};
+pub use mouse::{encode_mouse, MouseButton, MouseEvent, MouseEventKind};
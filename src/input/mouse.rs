@@ -0,0 +1,290 @@
+//! Mouse reports for the PTY: the X10, UTF-8 (1005), SGR (1006), urxvt
+//! (1015) and SGR-pixel (1016) encodings, filtered by the reporting mode,
+//! and alternate scroll (1007).
+
+use super::key::{Key, KeyEvent, Modifiers};
+use super::keyboard::{encode_key, TerminalModes};
+use crate::core::{MouseEncoding, MouseMode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+    WheelUp,
+    WheelDown,
+    WheelLeft,
+    WheelRight,
+    Back,
+    Forward,
+}
+
+impl MouseButton {
+    fn is_wheel(self) -> bool {
+        matches!(self, Self::WheelUp | Self::WheelDown | Self::WheelLeft | Self::WheelRight)
+    }
+
+    /// xterm button number, before modifier and motion bits.
+    fn code(self) -> u16 {
+        match self {
+            Self::Left => 0,
+            Self::Middle => 1,
+            Self::Right => 2,
+            Self::WheelUp => 64,
+            Self::WheelDown => 65,
+            Self::WheelLeft => 66,
+            Self::WheelRight => 67,
+            Self::Back => 128,
+            Self::Forward => 129,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseEventKind {
+    /// A button went down; wheel notches are presses without a release
+    Press(MouseButton),
+    Release(MouseButton),
+    /// The pointer moved with a button held, or with none
+    Motion(Option<MouseButton>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseEvent {
+    pub kind: MouseEventKind,
+    pub mods: Modifiers,
+    /// Cell under the pointer, 0-based
+    pub col: usize,
+    pub row: usize,
+    /// Pointer position in pixels from the grid's top-left corner, for
+    /// SGR-pixel reports
+    pub x: usize,
+    pub y: usize,
+}
+
+impl MouseEvent {
+    /// An unmodified event at a cell, with pixel position (0, 0).
+    pub fn new(kind: MouseEventKind, col: usize, row: usize) -> Self {
+        Self { kind, mods: Modifiers::empty(), col, row, x: 0, y: 0 }
+    }
+}
+
+/// Report for a mouse event. `None` when the application didn't ask for
+/// the event and the UI should handle it (selection, scrollback); an empty
+/// sequence when the application owns the mouse but the event isn't
+/// reported (motion in mode 1000, coordinates the encoding can't express).
+/// Shift always leaves the event to the UI. Callers send motion only when
+/// the cell (or, for SGR-pixel, the pixel) changes.
+pub fn encode_mouse(ev: &MouseEvent, modes: &TerminalModes) -> Option<Vec<u8>> {
+    if ev.mods.contains(Modifiers::SHIFT) {
+        return None;
+    }
+    if modes.mouse_mode == MouseMode::Off {
+        return alternate_scroll(ev, modes);
+    }
+    let reported = match (modes.mouse_mode, ev.kind) {
+        (_, MouseEventKind::Press(_)) => true,
+        (MouseMode::X10, _) => false,
+        (_, MouseEventKind::Release(button)) => !button.is_wheel(),
+        (MouseMode::Button, MouseEventKind::Motion(held)) => held.is_some(),
+        (MouseMode::Any, MouseEventKind::Motion(_)) => true,
+        _ => false,
+    };
+    if !reported {
+        return Some(Vec::new());
+    }
+
+    let (button, motion) = match ev.kind {
+        MouseEventKind::Press(b) | MouseEventKind::Release(b) => (Some(b), false),
+        MouseEventKind::Motion(held) => (held, true),
+    };
+    // 3 is "no button" in motion reports and "released" outside SGR
+    let mut code = button.map_or(3, MouseButton::code);
+    if motion {
+        code += 32;
+    }
+    // X10 compatibility mode reports no modifiers
+    if modes.mouse_mode != MouseMode::X10 {
+        if ev.mods.contains(Modifiers::ALT) {
+            code += 8;
+        }
+        if ev.mods.contains(Modifiers::CTRL) {
+            code += 16;
+        }
+    }
+    let release = matches!(ev.kind, MouseEventKind::Release(_));
+    let legacy_code = if release { (code & !0b11) | 3 } else { code };
+    let (col, row) = (ev.col + 1, ev.row + 1);
+
+    Some(match modes.mouse_encoding {
+        MouseEncoding::Sgr | MouseEncoding::SgrPixels => {
+            let (x, y) = if modes.mouse_encoding == MouseEncoding::SgrPixels {
+                (ev.x + 1, ev.y + 1)
+            } else {
+                (col, row)
+            };
+            let last = if release { 'm' } else { 'M' };
+            format!("\x1b[<{};{};{}{}", code, x, y, last).into_bytes()
+        }
+        MouseEncoding::Urxvt => {
+            format!("\x1b[{};{};{}M", 32 + legacy_code, col, row).into_bytes()
+        }
+        MouseEncoding::X10 => {
+            let values = [32 + legacy_code as usize, 32 + col, 32 + row];
+            if values.iter().any(|&v| v > 0xff) {
+                return Some(Vec::new());
+            }
+            let mut out = b"\x1b[M".to_vec();
+            out.extend(values.iter().map(|&v| v as u8));
+            out
+        }
+        MouseEncoding::Utf8 => {
+            let values = [32 + legacy_code as usize, 32 + col, 32 + row];
+            if values.iter().any(|&v| v > 0x7ff) {
+                return Some(Vec::new());
+            }
+            let mut out = "\x1b[M".to_string();
+            out.extend(values.iter().filter_map(|&v| char::from_u32(v as u32)));
+            out.into_bytes()
+        }
+    })
+}
+
+/// Mode 1007: with reporting off, the wheel scrolls full-screen programs
+/// on the alternate screen by sending Up/Down.
+fn alternate_scroll(ev: &MouseEvent, modes: &TerminalModes) -> Option<Vec<u8>> {
+    if !modes.alternate_scroll || !modes.alt_screen {
+        return None;
+    }
+    let key = match ev.kind {
+        MouseEventKind::Press(MouseButton::WheelUp) => Key::Up,
+        MouseEventKind::Press(MouseButton::WheelDown) => Key::Down,
+        _ => return None,
+    };
+    Some(encode_key(KeyEvent::new(key, Modifiers::empty()), modes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modes(mouse_mode: MouseMode, mouse_encoding: MouseEncoding) -> TerminalModes {
+        TerminalModes { mouse_mode, mouse_encoding, ..Default::default() }
+    }
+
+    fn enc(ev: MouseEvent, modes: &TerminalModes) -> Option<String> {
+        encode_mouse(&ev, modes).map(|b| String::from_utf8_lossy(&b).into_owned())
+    }
+
+    fn press(button: MouseButton, col: usize, row: usize) -> MouseEvent {
+        MouseEvent::new(MouseEventKind::Press(button), col, row)
+    }
+
+    fn release(button: MouseButton, col: usize, row: usize) -> MouseEvent {
+        MouseEvent::new(MouseEventKind::Release(button), col, row)
+    }
+
+    fn motion(held: Option<MouseButton>, col: usize, row: usize) -> MouseEvent {
+        MouseEvent::new(MouseEventKind::Motion(held), col, row)
+    }
+
+    #[test]
+    fn test_reporting_off() {
+        let m = TerminalModes::default();
+        assert_eq!(enc(press(MouseButton::Left, 0, 0), &m), None);
+        assert_eq!(enc(press(MouseButton::WheelUp, 0, 0), &m), None);
+    }
+
+    #[test]
+    fn test_x10_encoding() {
+        let m = modes(MouseMode::Normal, MouseEncoding::X10);
+        assert_eq!(enc(press(MouseButton::Left, 0, 0), &m).unwrap(), "\x1b[M !!");
+        assert_eq!(enc(press(MouseButton::Right, 9, 4), &m).unwrap(), "\x1b[M\"*%");
+        assert_eq!(enc(release(MouseButton::Right, 9, 4), &m).unwrap(), "\x1b[M#*%");
+        assert_eq!(enc(press(MouseButton::WheelDown, 0, 0), &m).unwrap(), "\x1b[Ma!!");
+        // Column 224 and beyond don't fit in a byte
+        assert_eq!(encode_mouse(&press(MouseButton::Left, 222, 0), &m).unwrap(), b"\x1b[M \xff!");
+        assert_eq!(enc(press(MouseButton::Left, 223, 0), &m).unwrap(), "");
+    }
+
+    #[test]
+    fn test_utf8_encoding() {
+        let m = modes(MouseMode::Normal, MouseEncoding::Utf8);
+        assert_eq!(enc(press(MouseButton::Left, 0, 0), &m).unwrap(), "\x1b[M !!");
+        assert_eq!(enc(press(MouseButton::Left, 299, 0), &m).unwrap(), "\x1b[M \u{14c}!");
+        assert_eq!(enc(press(MouseButton::Left, 2015, 0), &m).unwrap(), "");
+    }
+
+    #[test]
+    fn test_sgr_encoding() {
+        let m = modes(MouseMode::Normal, MouseEncoding::Sgr);
+        assert_eq!(enc(press(MouseButton::Left, 0, 0), &m).unwrap(), "\x1b[<0;1;1M");
+        assert_eq!(enc(release(MouseButton::Middle, 299, 99), &m).unwrap(), "\x1b[<1;300;100m");
+        assert_eq!(enc(press(MouseButton::WheelUp, 4, 2), &m).unwrap(), "\x1b[<64;5;3M");
+        assert_eq!(enc(press(MouseButton::Back, 0, 0), &m).unwrap(), "\x1b[<128;1;1M");
+        let mut ev = press(MouseButton::Left, 0, 0);
+        ev.mods = Modifiers::CTRL | Modifiers::ALT;
+        assert_eq!(enc(ev, &m).unwrap(), "\x1b[<24;1;1M");
+    }
+
+    #[test]
+    fn test_urxvt_encoding() {
+        let m = modes(MouseMode::Normal, MouseEncoding::Urxvt);
+        assert_eq!(enc(press(MouseButton::Left, 0, 0), &m).unwrap(), "\x1b[32;1;1M");
+        assert_eq!(enc(release(MouseButton::Left, 299, 0), &m).unwrap(), "\x1b[35;300;1M");
+    }
+
+    #[test]
+    fn test_sgr_pixel_encoding() {
+        let m = modes(MouseMode::Any, MouseEncoding::SgrPixels);
+        let mut ev = motion(None, 3, 1);
+        (ev.x, ev.y) = (37, 20);
+        assert_eq!(enc(ev, &m).unwrap(), "\x1b[<35;38;21M");
+    }
+
+    #[test]
+    fn test_motion_filtering() {
+        let x10 = modes(MouseMode::X10, MouseEncoding::Sgr);
+        let mut ev = press(MouseButton::Left, 0, 0);
+        ev.mods = Modifiers::CTRL;
+        // X10 mode: presses only, without modifiers
+        assert_eq!(enc(ev, &x10).unwrap(), "\x1b[<0;1;1M");
+        assert_eq!(enc(release(MouseButton::Left, 0, 0), &x10).unwrap(), "");
+
+        let normal = modes(MouseMode::Normal, MouseEncoding::Sgr);
+        assert_eq!(enc(motion(Some(MouseButton::Left), 1, 0), &normal).unwrap(), "");
+        // Wheel notches have no release
+        assert_eq!(enc(release(MouseButton::WheelUp, 0, 0), &normal).unwrap(), "");
+
+        let button = modes(MouseMode::Button, MouseEncoding::Sgr);
+        assert_eq!(enc(motion(Some(MouseButton::Left), 1, 0), &button).unwrap(), "\x1b[<32;2;1M");
+        assert_eq!(enc(motion(None, 1, 0), &button).unwrap(), "");
+
+        let any = modes(MouseMode::Any, MouseEncoding::Sgr);
+        assert_eq!(enc(motion(Some(MouseButton::Right), 1, 0), &any).unwrap(), "\x1b[<34;2;1M");
+        assert_eq!(enc(motion(None, 1, 0), &any).unwrap(), "\x1b[<35;2;1M");
+    }
+
+    #[test]
+    fn test_shift_bypasses_reporting() {
+        let m = modes(MouseMode::Any, MouseEncoding::Sgr);
+        let mut ev = press(MouseButton::Left, 0, 0);
+        ev.mods = Modifiers::SHIFT;
+        assert_eq!(enc(ev, &m), None);
+    }
+
+    #[test]
+    fn test_alternate_scroll() {
+        let mut m = TerminalModes { alternate_scroll: true, ..Default::default() };
+        // Main screen: the UI scrolls back
+        assert_eq!(enc(press(MouseButton::WheelUp, 0, 0), &m), None);
+        m.alt_screen = true;
+        assert_eq!(enc(press(MouseButton::WheelUp, 0, 0), &m).unwrap(), "\x1b[A");
+        m.cursor_keys_app = true;
+        assert_eq!(enc(press(MouseButton::WheelDown, 0, 0), &m).unwrap(), "\x1bOB");
+        assert_eq!(enc(press(MouseButton::Left, 0, 0), &m), None);
+        // Mouse reporting wins
+        m.mouse_mode = MouseMode::Normal;
+        assert_eq!(enc(press(MouseButton::WheelUp, 0, 0), &m).unwrap(), "\x1b[M`!!");
+    }
+}
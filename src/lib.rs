@@ -14,6 +14,7 @@ pub mod session;
 pub mod bench;
 pub mod security;
 pub mod image;
+pub mod input;
 pub mod pane;
 pub mod plugin;
 pub mod shell_integration;
@@ -22,6 +23,9 @@ pub mod portable;
 pub mod vttest;
 pub mod shell_scripts;
 
+/// Version reported by XTVERSION.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
 #[no_mangle]
 pub extern "C" fn libterm_version() -> *const std::ffi::c_char {
     c"0.1.0".as_ptr()
@@ -1,21 +1,23 @@
 /// Window application: connects winit window, wgpu renderer, PTY, and terminal.
 
-use crate::core::{Terminal, VtParser};
+use crate::clipboard::{self, ClipboardAccess};
+use crate::core::{Terminal, TerminalEvent, VtParser};
+use crate::input::{self, KeyEventKind, Modifiers};
 use crate::pty::PtyManager;
 use crate::renderer::atlas::GlyphAtlas;
-use crate::renderer::pipeline::RenderState;
+use crate::renderer::pipeline::{clear_color, RenderState};
 use crate::renderer::cursor::Cursor;
 use crate::renderer::selection::{Selection, SelectionMode};
 use crate::renderer::scroll::SmoothScroll;
-use crate::core::Color;
 
 use std::sync::Arc;
 use winit::application::ApplicationHandler;
 use winit::dpi::PhysicalSize;
 use winit::event::{ElementState, WindowEvent};
 use winit::event_loop::ActiveEventLoop;
-use winit::keyboard::{Key, NamedKey};
-use winit::window::{Window, WindowId};
+use winit::keyboard::{Key, KeyLocation, ModifiersState, NamedKey};
+use winit::platform::modifier_supplement::KeyEventExtModifierSupplement;
+use winit::window::{UserAttentionType, Window, WindowId};
 
 const FONT_DATA: &[u8] = include_bytes!("/System/Library/Fonts/Menlo.ttc");
 const FONT_SIZE: f32 = 14.0;
@@ -32,20 +34,38 @@ pub struct App {
     cursor: Cursor,
     selection: Selection,
     scroll: SmoothScroll,
+    modifiers: ModifiersState,
+    /// macOS: Option is Alt (Meta) rather than a compose key
+    option_as_alt: bool,
+    /// Pointer position in pixels, from `CursorMoved`
+    mouse_pos: (f64, f64),
+    /// Button held down, for drag reports
+    mouse_held: Option<input::MouseButton>,
+    /// Cell of the last motion report
+    mouse_cell: (usize, usize),
 }
 
 impl App {
     pub fn new() -> Self {
+        let mut terminal = Terminal::new(DEFAULT_COLS, DEFAULT_ROWS);
+        // No confirmation dialog here: OSC 52 `ask` is refused outright
+        terminal.clipboard_write = terminal.clipboard_write.without_prompt();
+        terminal.clipboard_read = terminal.clipboard_read.without_prompt();
         Self {
             window: None,
             render: None,
             atlas: None,
-            terminal: Terminal::new(DEFAULT_COLS, DEFAULT_ROWS),
+            terminal,
             parser: VtParser::new(),
             pty: None,
             cursor: Cursor::new(),
             selection: Selection::new(),
             scroll: SmoothScroll::new(),
+            modifiers: ModifiersState::empty(),
+            option_as_alt: crate::config::Config::load().terminal.option_as_alt,
+            mouse_pos: (0.0, 0.0),
+            mouse_held: None,
+            mouse_cell: (0, 0),
         }
     }
 
@@ -131,9 +151,59 @@ impl App {
                 Err(_) => break,
             }
         }
+        self.handle_events();
+        self.flush_write_back();
+    }
+
+    /// React to terminal events: window title, bell and OSC 52 clipboard.
+    fn handle_events(&mut self) {
+        let events: Vec<_> = self.terminal.drain_events().collect();
+        for event in events {
+            // `new` turned OSC 52 `ask` into `off`; a policy set to `ask`
+            // later is still refused rather than allowed
+            match event {
+                TerminalEvent::ClipboardStore { selection, text }
+                    if self.terminal.clipboard_write == ClipboardAccess::Allow =>
+                {
+                    for sel in clipboard::osc52_selections(&selection) {
+                        clipboard::copy_to(sel, &text);
+                    }
+                    continue;
+                }
+                TerminalEvent::ClipboardLoadRequest { selection }
+                    if self.terminal.clipboard_read == ClipboardAccess::Allow =>
+                {
+                    let sel = clipboard::osc52_selections(&selection).first().copied();
+                    let text = sel.and_then(clipboard::paste_from).unwrap_or_default();
+                    self.terminal.answer_clipboard_request(&selection, &text);
+                    continue;
+                }
+                _ => {}
+            }
+            let Some(window) = &self.window else { continue };
+            match event {
+                TerminalEvent::TitleChanged(title) if title.is_empty() => window.set_title("Terminal"),
+                TerminalEvent::TitleChanged(title) => window.set_title(&title),
+                TerminalEvent::Bell => window.request_user_attention(Some(UserAttentionType::Informational)),
+                _ => {}
+            }
+        }
+    }
+
+    /// Send queued terminal replies (DSR, DECRQM, focus reports) to the PTY.
+    fn flush_write_back(&mut self) {
+        let Some(pty) = &self.pty else { return };
+        if !self.terminal.write_back.is_empty() {
+            let _ = pty.write(&self.terminal.write_back);
+            self.terminal.write_back.clear();
+        }
     }
 
     fn render_frame(&mut self) {
+        // Keep presenting the last complete frame during a synchronized update
+        if self.terminal.sync_update_pending() {
+            return;
+        }
         let Some(render) = &self.render else { return };
         let Some(atlas) = &mut self.atlas else { return };
         let Some(window) = &self.window else { return };
@@ -147,6 +217,7 @@ impl App {
 
         let (mut vertices, mut indices) = render.build_vertices(
             &self.terminal.grid,
+            &self.terminal.palette,
             atlas,
             size.width as f32,
             size.height as f32,
@@ -168,7 +239,7 @@ impl App {
             self.terminal.grid.cursor_col,
             atlas.cell_width, atlas.cell_height,
             size.width as f32, size.height as f32,
-            Color { r: 200, g: 200, b: 200 },
+            self.terminal.palette.cursor,
         );
         if cursor_verts.len() == 4 {
             let base = vertices.len() as u32;
@@ -215,9 +286,7 @@ impl App {
                     view: &view,
                     resolve_target: None,
                     ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.0, g: 0.0, b: 0.0, a: 1.0,
-                        }),
+                        load: wgpu::LoadOp::Clear(clear_color(self.terminal.palette.bg)),
                         store: wgpu::StoreOp::Store,
                     },
                 })],
@@ -237,38 +306,48 @@ impl App {
     }
 
     fn handle_key_input(&mut self, event: &winit::event::KeyEvent) {
-        if event.state != ElementState::Pressed {
-            return;
+        if event.state == ElementState::Pressed {
+            self.cursor.reset_blink();
+            self.scroll.reset(); // snap to bottom on keypress
         }
-        self.cursor.reset_blink();
-        self.scroll.reset(); // snap to bottom on keypress
         let Some(pty) = &self.pty else { return };
+        let Some(key) = key_event(event, self.modifiers, self.option_as_alt) else { return };
 
-        let bytes: Option<Vec<u8>> = match &event.logical_key {
-            Key::Named(NamedKey::Enter) => Some(vec![0x0d]),
-            Key::Named(NamedKey::Backspace) => Some(vec![0x7f]),
-            Key::Named(NamedKey::Tab) => Some(vec![0x09]),
-            Key::Named(NamedKey::Escape) => Some(vec![0x1b]),
-            Key::Named(NamedKey::ArrowUp) => Some(b"\x1b[A".to_vec()),
-            Key::Named(NamedKey::ArrowDown) => Some(b"\x1b[B".to_vec()),
-            Key::Named(NamedKey::ArrowRight) => Some(b"\x1b[C".to_vec()),
-            Key::Named(NamedKey::ArrowLeft) => Some(b"\x1b[D".to_vec()),
-            Key::Named(NamedKey::Home) => Some(b"\x1b[H".to_vec()),
-            Key::Named(NamedKey::End) => Some(b"\x1b[F".to_vec()),
-            Key::Named(NamedKey::PageUp) => Some(b"\x1b[5~".to_vec()),
-            Key::Named(NamedKey::PageDown) => Some(b"\x1b[6~".to_vec()),
-            Key::Named(NamedKey::Delete) => Some(b"\x1b[3~".to_vec()),
-            Key::Character(s) => {
-                Some(s.as_str().as_bytes().to_vec())
-            }
-            _ => None,
-        };
-
-        if let Some(data) = bytes {
+        let data = input::encode_key(key, &self.terminal.input_modes());
+        if !data.is_empty() {
             let _ = pty.write(&data);
         }
     }
 
+    /// Offer a mouse event to the application. Returns false when it
+    /// didn't ask for the event and the UI should handle it.
+    fn report_mouse(&mut self, kind: input::MouseEventKind) -> bool {
+        let Some(atlas) = &self.atlas else { return false };
+        let (x, y) = (self.mouse_pos.0.max(0.0), self.mouse_pos.1.max(0.0));
+        let col = ((x / atlas.cell_width as f64) as usize).min(self.terminal.grid.cols() - 1);
+        let row = ((y / atlas.cell_height as f64) as usize).min(self.terminal.grid.rows() - 1);
+        let modes = self.terminal.input_modes();
+
+        if let input::MouseEventKind::Motion(_) = kind {
+            let pixels = modes.mouse_encoding == crate::core::MouseEncoding::SgrPixels;
+            if !pixels && (col, row) == self.mouse_cell {
+                return modes.mouse_mode != crate::core::MouseMode::Off;
+            }
+        }
+        self.mouse_cell = (col, row);
+
+        let mut event = input::MouseEvent::new(kind, col, row);
+        event.mods = modifiers(self.modifiers);
+        (event.x, event.y) = (x as usize, y as usize);
+        let Some(data) = input::encode_mouse(&event, &modes) else { return false };
+        if let Some(pty) = &self.pty {
+            if !data.is_empty() {
+                let _ = pty.write(&data);
+            }
+        }
+        true
+    }
+
     fn update_terminal_size(&mut self) {
         let Some(window) = &self.window else { return };
         let Some(atlas) = &self.atlas else { return };
@@ -279,6 +358,7 @@ impl App {
 
         if cols > 0 && rows > 0 {
             self.terminal.resize(cols, rows);
+            self.terminal.set_cell_size(atlas.cell_width as u16, atlas.cell_height as u16);
             // Also resize PTY
             if let Some(pty) = &self.pty {
                 let ws = nix::pty::Winsize {
@@ -304,6 +384,11 @@ impl ApplicationHandler for App {
         let attrs = Window::default_attributes()
             .with_title("Terminal")
             .with_inner_size(PhysicalSize::new(800, 600));
+        #[cfg(target_os = "macos")]
+        let attrs = {
+            use winit::platform::macos::{OptionAsAlt, WindowAttributesExtMacOS};
+            attrs.with_option_as_alt(if self.option_as_alt { OptionAsAlt::Both } else { OptionAsAlt::None })
+        };
 
         let window = Arc::new(event_loop.create_window(attrs).expect("Failed to create window"));
         self.init_renderer(window);
@@ -337,13 +422,40 @@ impl ApplicationHandler for App {
                 self.update_terminal_size();
             }
 
+            WindowEvent::Focused(focused) => {
+                self.terminal.set_focused(focused);
+                self.flush_write_back();
+            }
+
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers.state();
+            }
+
             WindowEvent::KeyboardInput { event, .. } => {
                 self.handle_key_input(&event);
             }
 
-            WindowEvent::MouseInput { state, button: winit::event::MouseButton::Left, .. } => {
-                if let Some(atlas) = &self.atlas {
-                    // TODO: track mouse position via CursorMoved for accurate coords
+            WindowEvent::CursorMoved { position, .. } => {
+                self.mouse_pos = (position.x, position.y);
+                self.report_mouse(input::MouseEventKind::Motion(self.mouse_held));
+            }
+
+            WindowEvent::MouseInput { state, button, .. } => {
+                let Some(button) = mouse_button(button) else { return };
+                let kind = match state {
+                    ElementState::Pressed => {
+                        self.mouse_held = Some(button);
+                        input::MouseEventKind::Press(button)
+                    }
+                    ElementState::Released => {
+                        self.mouse_held = None;
+                        input::MouseEventKind::Release(button)
+                    }
+                };
+                if self.report_mouse(kind) {
+                    return;
+                }
+                if button == input::MouseButton::Left {
                     match state {
                         ElementState::Pressed => {
                             self.selection.clear();
@@ -363,8 +475,17 @@ impl ApplicationHandler for App {
                             pos.y as f32 / atlas.cell_height
                         }
                     };
+                    let cell_height = atlas.cell_height;
+                    let button = if lines > 0.0 { input::MouseButton::WheelUp } else { input::MouseButton::WheelDown };
+                    let notches = lines.abs().round().max(1.0) as usize;
+                    if lines != 0.0 && self.report_mouse(input::MouseEventKind::Press(button)) {
+                        for _ in 1..notches {
+                            self.report_mouse(input::MouseEventKind::Press(button));
+                        }
+                        return;
+                    }
                     let scrollback_len = 0; // TODO: expose scrollback len from grid
-                    self.scroll.scroll(lines, atlas.cell_height, scrollback_len.max(1));
+                    self.scroll.scroll(lines, cell_height, scrollback_len.max(1));
                 }
             }
 
@@ -387,3 +508,89 @@ impl ApplicationHandler for App {
         }
     }
 }
+
+/// Translate a winit key event for `input::encode_key`; `None` for keys
+/// the encoder has no sequence for.
+fn key_event(
+    event: &winit::event::KeyEvent,
+    state: ModifiersState,
+    option_as_alt: bool,
+) -> Option<input::KeyEvent> {
+    let numpad = event.location == KeyLocation::Numpad;
+    // The encoder wants the key without Shift or Option composition
+    // (`1` for Shift+1, `b` for Option+B); the text comes from `event.text`
+    let key = match &event.key_without_modifiers() {
+        Key::Named(NamedKey::Enter) if numpad => input::Key::Keypad('\r'),
+        Key::Named(NamedKey::Enter) => input::Key::Enter,
+        Key::Named(NamedKey::Tab) => input::Key::Tab,
+        Key::Named(NamedKey::Backspace) => input::Key::Backspace,
+        Key::Named(NamedKey::Escape) => input::Key::Escape,
+        Key::Named(NamedKey::Space) => input::Key::Char(' '),
+        Key::Named(NamedKey::ArrowUp) => input::Key::Up,
+        Key::Named(NamedKey::ArrowDown) => input::Key::Down,
+        Key::Named(NamedKey::ArrowRight) => input::Key::Right,
+        Key::Named(NamedKey::ArrowLeft) => input::Key::Left,
+        Key::Named(NamedKey::Home) => input::Key::Home,
+        Key::Named(NamedKey::End) => input::Key::End,
+        Key::Named(NamedKey::PageUp) => input::Key::PageUp,
+        Key::Named(NamedKey::PageDown) => input::Key::PageDown,
+        Key::Named(NamedKey::Insert) => input::Key::Insert,
+        Key::Named(NamedKey::Delete) => input::Key::Delete,
+        Key::Named(named) => input::Key::F(function_key(*named)?),
+        Key::Character(s) => {
+            let c = s.chars().next()?;
+            if numpad && "0123456789./*-+=".contains(c) {
+                input::Key::Keypad(c)
+            } else {
+                input::Key::Char(c.to_lowercase().next().unwrap_or(c))
+            }
+        }
+        _ => return None,
+    };
+
+    let text = event.text.as_deref().unwrap_or("");
+    let mut mods = modifiers(state);
+    // Unless Option is Alt, it composed `text` ("∫" for Option+B): send
+    // that rather than a Meta prefix
+    if cfg!(target_os = "macos") && !option_as_alt && !text.is_empty() {
+        mods.remove(Modifiers::ALT);
+    }
+    let mut key = input::KeyEvent::with_text(key, mods, text);
+    key.kind = match (event.state, event.repeat) {
+        (ElementState::Released, _) => KeyEventKind::Release,
+        (ElementState::Pressed, true) => KeyEventKind::Repeat,
+        (ElementState::Pressed, false) => KeyEventKind::Press,
+    };
+    Some(key)
+}
+
+fn modifiers(state: ModifiersState) -> Modifiers {
+    let mut mods = Modifiers::empty();
+    mods.set(Modifiers::SHIFT, state.shift_key());
+    mods.set(Modifiers::ALT, state.alt_key());
+    mods.set(Modifiers::CTRL, state.control_key());
+    mods.set(Modifiers::SUPER, state.super_key());
+    mods
+}
+
+fn mouse_button(button: winit::event::MouseButton) -> Option<input::MouseButton> {
+    use winit::event::MouseButton;
+    Some(match button {
+        MouseButton::Left => input::MouseButton::Left,
+        MouseButton::Middle => input::MouseButton::Middle,
+        MouseButton::Right => input::MouseButton::Right,
+        MouseButton::Back => input::MouseButton::Back,
+        MouseButton::Forward => input::MouseButton::Forward,
+        MouseButton::Other(_) => return None,
+    })
+}
+
+/// F1–F24 as their number.
+fn function_key(key: NamedKey) -> Option<u8> {
+    use NamedKey::*;
+    const KEYS: [NamedKey; 24] = [
+        F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+        F13, F14, F15, F16, F17, F18, F19, F20, F21, F22, F23, F24,
+    ];
+    KEYS.iter().position(|&k| k == key).map(|i| i as u8 + 1)
+}
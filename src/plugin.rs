@@ -4,6 +4,8 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
 
+use crate::core::TerminalEvent;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum HookEvent {
     SessionStart,
@@ -14,6 +16,30 @@ pub enum HookEvent {
     Bell,
 }
 
+impl HookEvent {
+    /// Name plugins subscribe to the event with.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookEvent::SessionStart => "session_start",
+            HookEvent::SessionEnd => "session_end",
+            HookEvent::LineOutput(_) => "line_output",
+            HookEvent::TitleChange(_) => "title_change",
+            HookEvent::DirectoryChange(_) => "directory_change",
+            HookEvent::Bell => "bell",
+        }
+    }
+
+    /// The hook a terminal event triggers, if any.
+    pub fn from_terminal_event(event: &TerminalEvent) -> Option<HookEvent> {
+        match event {
+            TerminalEvent::TitleChanged(title) => Some(HookEvent::TitleChange(title.clone())),
+            TerminalEvent::CwdChanged(dir) => Some(HookEvent::DirectoryChange(dir.clone())),
+            TerminalEvent::Bell => Some(HookEvent::Bell),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PluginInfo {
     pub name: String,
@@ -55,6 +81,11 @@ impl PluginManager {
             .unwrap_or_default()
     }
 
+    /// Enabled plugins subscribed to `event`.
+    pub fn dispatch(&self, event: &HookEvent) -> Vec<&PluginInfo> {
+        self.subscribers(event.name())
+    }
+
     /// List all registered plugins.
     pub fn list(&self) -> &[PluginInfo] { &self.plugins }
 
@@ -113,6 +144,17 @@ mod tests {
         assert!(mgr.subscribers("bell").is_empty());
     }
 
+    #[test]
+    fn test_terminal_events_reach_subscribers() {
+        let mut mgr = PluginManager::new();
+        let idx = mgr.register(test_plugin());
+        mgr.subscribe(idx, "directory_change");
+        let hook = HookEvent::from_terminal_event(&TerminalEvent::CwdChanged("/tmp".into())).unwrap();
+        assert_eq!(hook, HookEvent::DirectoryChange("/tmp".into()));
+        assert_eq!(mgr.dispatch(&hook).len(), 1);
+        assert_eq!(HookEvent::from_terminal_event(&TerminalEvent::CommandStarted), None);
+    }
+
     #[test]
     fn test_no_subscribers() {
         let mgr = PluginManager::new();
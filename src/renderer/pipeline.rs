@@ -1,9 +1,12 @@
 /// wgpu rendering pipeline for the terminal.
 /// Renders cell grid as textured quads using the glyph atlas.
 
-use crate::core::{Grid, Color};
+use crate::core::{Grid, Color, Palette, UnderlineStyle};
 use crate::renderer::atlas::GlyphAtlas;
 
+/// Buffer capacity per cell: the glyph quad plus up to two underline quads.
+const QUADS_PER_CELL: usize = 3;
+
 /// Per-vertex data for a cell quad.
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
@@ -79,14 +82,14 @@ impl RenderState {
 
         let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("cell-vertices"),
-            size: (max_cells * 6 * std::mem::size_of::<CellVertex>()) as u64,
+            size: (max_cells * QUADS_PER_CELL * 4 * std::mem::size_of::<CellVertex>()) as u64,
             usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
         let index_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("cell-indices"),
-            size: (max_cells * 6 * std::mem::size_of::<u32>()) as u64,
+            size: (max_cells * QUADS_PER_CELL * 6 * std::mem::size_of::<u32>()) as u64,
             usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
@@ -120,14 +123,14 @@ impl RenderState {
 
         let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("cell-vertices"),
-            size: (max_cells * 6 * std::mem::size_of::<CellVertex>()) as u64,
+            size: (max_cells * QUADS_PER_CELL * 4 * std::mem::size_of::<CellVertex>()) as u64,
             usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
 
         let index_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("cell-indices"),
-            size: (max_cells * 6 * std::mem::size_of::<u32>()) as u64,
+            size: (max_cells * QUADS_PER_CELL * 6 * std::mem::size_of::<u32>()) as u64,
             usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
             mapped_at_creation: false,
         });
@@ -276,10 +279,12 @@ impl RenderState {
         (pipeline, atlas_texture, atlas_bind_group)
     }
 
-    /// Build vertex data from the terminal grid.
+    /// Build vertex data from the terminal grid, resolving cell colors
+    /// against `palette`.
     pub fn build_vertices(
         &self,
         grid: &Grid,
+        palette: &Palette,
         atlas: &mut GlyphAtlas,
         screen_width: f32,
         screen_height: f32,
@@ -310,8 +315,8 @@ impl RenderState {
                 let nx1 = (x1 / screen_width) * 2.0 - 1.0;
                 let ny1 = 1.0 - (y1 / screen_height) * 2.0;
 
-                let fg = color_to_f32(cell.fg);
-                let bg = color_to_f32(cell.bg);
+                let fg = color_to_f32(palette.resolve_fg(cell.fg));
+                let bg = color_to_f32(palette.resolve_bg(cell.bg));
 
                 // Get glyph UV from atlas
                 let glyph = atlas.get_glyph(cell.ch);
@@ -328,6 +333,17 @@ impl RenderState {
                     CellVertex { position: [nx0, ny1], uv: [u0, v1], fg_color: fg, bg_color: bg },
                 ]);
                 indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+
+                if let Some(style) = cell.attr.underline() {
+                    let color = color_to_f32(palette.resolve_underline(cell.underline_color, cell.fg));
+                    for [x, y, w, h] in underline_rects(style, x0, y0, cw, ch) {
+                        push_solid_quad(
+                            &mut vertices, &mut indices, color,
+                            [(x / screen_width) * 2.0 - 1.0, 1.0 - (y / screen_height) * 2.0],
+                            [((x + w) / screen_width) * 2.0 - 1.0, 1.0 - ((y + h) / screen_height) * 2.0],
+                        );
+                    }
+                }
             }
         }
 
@@ -361,6 +377,47 @@ impl RenderState {
     }
 }
 
+/// Underline rectangles (x, y, w, h in pixels) for one cell, at most
+/// `QUADS_PER_CELL - 1`. Curly underlines are drawn as a square wave.
+fn underline_rects(style: UnderlineStyle, x0: f32, y0: f32, cw: f32, ch: f32) -> Vec<[f32; 4]> {
+    let t = (ch / 16.0).round().max(1.0);
+    let y = y0 + ch - 2.0 * t;
+    match style {
+        UnderlineStyle::Single => vec![[x0, y, cw, t]],
+        UnderlineStyle::Double => vec![[x0, y - 2.0 * t, cw, t], [x0, y, cw, t]],
+        UnderlineStyle::Curly => vec![
+            [x0, y - t, cw / 2.0, t],
+            [x0 + cw / 2.0, y + t, cw / 2.0, t],
+        ],
+        UnderlineStyle::Dotted => vec![[x0, y, t, t], [x0 + cw / 2.0, y, t, t]],
+        UnderlineStyle::Dashed => vec![[x0, y, cw * 0.75, t]],
+    }
+}
+
+/// Solid-colored quad between two NDC corners (fg = bg, like the cursor).
+fn push_solid_quad(
+    vertices: &mut Vec<CellVertex>,
+    indices: &mut Vec<u32>,
+    color: [f32; 3],
+    [nx0, ny0]: [f32; 2],
+    [nx1, ny1]: [f32; 2],
+) {
+    let base = vertices.len() as u32;
+    let v = CellVertex { position: [0.0; 2], uv: [0.0; 2], fg_color: color, bg_color: color };
+    vertices.extend_from_slice(&[
+        CellVertex { position: [nx0, ny0], ..v },
+        CellVertex { position: [nx1, ny0], ..v },
+        CellVertex { position: [nx1, ny1], ..v },
+        CellVertex { position: [nx0, ny1], ..v },
+    ]);
+    indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+}
+
+/// Render-pass clear color for the palette background.
+pub fn clear_color(c: Color) -> wgpu::Color {
+    wgpu::Color { r: c.r as f64 / 255.0, g: c.g as f64 / 255.0, b: c.b as f64 / 255.0, a: 1.0 }
+}
+
 fn color_to_f32(c: Color) -> [f32; 3] {
     [c.r as f32 / 255.0, c.g as f32 / 255.0, c.b as f32 / 255.0]
 }
@@ -424,6 +481,20 @@ mod tests {
         assert!((f[2] - 0.0).abs() < 0.01);
     }
 
+    #[test]
+    fn test_underline_rects_stay_in_cell() {
+        for style in [UnderlineStyle::Single, UnderlineStyle::Double, UnderlineStyle::Curly,
+                      UnderlineStyle::Dotted, UnderlineStyle::Dashed] {
+            let rects = underline_rects(style, 16.0, 32.0, 8.0, 16.0);
+            assert!(!rects.is_empty() && rects.len() < QUADS_PER_CELL);
+            for [x, y, w, h] in rects {
+                assert!(x >= 16.0 && x + w <= 24.0, "{style:?}");
+                assert!(y >= 32.0 && y + h <= 48.0, "{style:?}");
+            }
+        }
+        assert_eq!(underline_rects(UnderlineStyle::Double, 0.0, 0.0, 8.0, 16.0).len(), 2);
+    }
+
     #[test]
     fn test_build_vertices_empty_grid() {
         // Can't create full RenderState without GPU, but we can test vertex building logic
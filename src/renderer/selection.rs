@@ -87,10 +87,7 @@ impl Selection {
             let col_end = if row == end.row { end.col } else { grid.cols() - 1 };
 
             for col in col_start..=col_end.min(grid.cols() - 1) {
-                let ch = grid.cell(row, col).ch;
-                if ch != '\0' {
-                    text.push(ch);
-                }
+                grid.push_cell_text(grid.cell(row, col), &mut text);
             }
             if row < end.row {
                 // Trim trailing spaces and add newline
@@ -155,7 +152,7 @@ impl Default for Selection {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::core::{Grid, CellAttr, Color};
+    use crate::core::{Grid, CellAttr, CellColor};
 
     #[test]
     fn test_selection_empty() {
@@ -234,12 +231,12 @@ mod tests {
         for (i, ch) in "Hello".chars().enumerate() {
             grid.cursor_col = i;
             grid.cursor_row = 0;
-            grid.put_char(ch, CellAttr::empty(), Color::DEFAULT_FG, Color::DEFAULT_BG);
+            grid.put_char(ch, CellAttr::empty(), CellColor::Default, CellColor::Default);
         }
         for (i, ch) in "World".chars().enumerate() {
             grid.cursor_col = i;
             grid.cursor_row = 1;
-            grid.put_char(ch, CellAttr::empty(), Color::DEFAULT_FG, Color::DEFAULT_BG);
+            grid.put_char(ch, CellAttr::empty(), CellColor::Default, CellColor::Default);
         }
 
         let mut s = Selection::new();
@@ -254,12 +251,12 @@ mod tests {
         for (i, ch) in "Hello".chars().enumerate() {
             grid.cursor_col = i;
             grid.cursor_row = 0;
-            grid.put_char(ch, CellAttr::empty(), Color::DEFAULT_FG, Color::DEFAULT_BG);
+            grid.put_char(ch, CellAttr::empty(), CellColor::Default, CellColor::Default);
         }
         for (i, ch) in "World".chars().enumerate() {
             grid.cursor_col = i;
             grid.cursor_row = 1;
-            grid.put_char(ch, CellAttr::empty(), Color::DEFAULT_FG, Color::DEFAULT_BG);
+            grid.put_char(ch, CellAttr::empty(), CellColor::Default, CellColor::Default);
         }
 
         let mut s = Selection::new();
@@ -268,6 +265,19 @@ mod tests {
         assert_eq!(s.get_text(&grid), "Hello\nWorld");
     }
 
+    #[test]
+    fn test_get_text_grapheme_cluster() {
+        let mut grid = Grid::new(10, 3);
+        grid.put_char('n', CellAttr::empty(), CellColor::Default, CellColor::Default);
+        grid.append_to_cell(0, 0, '\u{303}');
+        grid.put_char('o', CellAttr::empty(), CellColor::Default, CellColor::Default);
+
+        let mut s = Selection::new();
+        s.begin(0, 0, SelectionMode::Normal);
+        s.update(0, 1);
+        assert_eq!(s.get_text(&grid), "n\u{303}o");
+    }
+
     #[test]
     fn test_build_vertices_inactive() {
         let s = Selection::new();
@@ -11,18 +11,6 @@ pub struct SearchMatch {
 }
 
 /// Extract text content from a grid row.
-fn row_text(grid: &Grid, row: usize) -> String {
-    let cols = grid.cols();
-    let mut s = String::with_capacity(cols);
-    for c in 0..cols {
-        let ch = grid.cell(row, c).ch;
-        s.push(if ch == '\0' { ' ' } else { ch });
-    }
-    // Trim trailing spaces
-    s.truncate(s.trim_end().len());
-    s
-}
-
 /// Search visible grid for a pattern. Returns matches sorted top-to-bottom.
 pub fn search_grid(grid: &Grid, pattern: &str, use_regex: bool) -> Vec<SearchMatch> {
     let mut matches = Vec::new();
@@ -34,12 +22,12 @@ pub fn search_grid(grid: &Grid, pattern: &str, use_regex: bool) -> Vec<SearchMat
     let Some(re) = re else { return matches };
 
     for row in 0..grid.rows() {
-        let text = row_text(grid, row);
-        for m in re.find_iter(&text) {
+        let line = grid.row_text(grid.row(row));
+        for m in re.find_iter(&line.text) {
             matches.push(SearchMatch {
                 row: row as i32,
-                col_start: m.start(),
-                col_end: m.end(),
+                col_start: line.col_at(m.start()),
+                col_end: line.col_end(m.end()),
             });
         }
     }
@@ -59,17 +47,12 @@ pub fn search_scrollback(grid: &Grid, pattern: &str, use_regex: bool) -> Vec<Sea
     let scrollback = grid.scrollback();
     let len = scrollback.len();
     for (i, row_cells) in scrollback.iter().enumerate() {
-        let mut text = String::new();
-        for cell in row_cells {
-            let ch = cell.ch;
-            text.push(if ch == '\0' { ' ' } else { ch });
-        }
-        text.truncate(text.trim_end().len());
-        for m in re.find_iter(&text) {
+        let line = grid.row_text(row_cells);
+        for m in re.find_iter(&line.text) {
             matches.push(SearchMatch {
                 row: -(len as i32 - i as i32),
-                col_start: m.start(),
-                col_end: m.end(),
+                col_start: line.col_at(m.start()),
+                col_end: line.col_end(m.end()),
             });
         }
     }
@@ -146,6 +129,17 @@ mod tests {
         assert_eq!(matches.len(), 3);
     }
 
+    #[test]
+    fn test_search_combining_cluster_columns() {
+        let mut t = Terminal::new(20, 2);
+        let mut p = VtParser::new();
+        t.feed_bytes(&mut p, "cafe\u{301} au lait".as_bytes());
+        let matches = search_grid(&t.grid, "cafe\u{301}", false);
+        assert_eq!(matches, vec![SearchMatch { row: 0, col_start: 0, col_end: 4 }]);
+        let matches = search_grid(&t.grid, "lait", false);
+        assert_eq!(matches, vec![SearchMatch { row: 0, col_start: 8, col_end: 12 }]);
+    }
+
     #[test]
     fn test_search_all_includes_scrollback() {
         let mut t = Terminal::new(10, 3);
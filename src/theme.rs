@@ -1,6 +1,6 @@
 /// Theme system: bundled color schemes + custom themes via TOML.
 
-use crate::core::Color;
+use crate::core::{Color, Palette, ANSI_COLORS};
 use serde::Deserialize;
 
 #[derive(Debug, Clone)]
@@ -37,7 +37,7 @@ impl Theme {
             fg: Color { r: 204, g: 204, b: 204 },
             bg: Color { r: 0, g: 0, b: 0 },
             cursor: Color { r: 204, g: 204, b: 204 },
-            ansi: DEFAULT_ANSI,
+            ansi: ANSI_COLORS,
         }
     }
 
@@ -126,31 +126,17 @@ impl Theme {
         })
     }
 
+    /// Palette for resolving cell colors under this theme.
+    pub fn palette(&self) -> Palette {
+        Palette::new(self.fg, self.bg, self.cursor, self.ansi)
+    }
+
     /// List all bundled theme names.
     pub fn bundled_names() -> &'static [&'static str] {
         &["default", "dracula", "solarized-dark"]
     }
 }
 
-const DEFAULT_ANSI: [Color; 16] = [
-    Color { r: 0,   g: 0,   b: 0   },
-    Color { r: 205, g: 49,  b: 49  },
-    Color { r: 13,  g: 188, b: 121 },
-    Color { r: 229, g: 229, b: 16  },
-    Color { r: 36,  g: 114, b: 200 },
-    Color { r: 188, g: 63,  b: 188 },
-    Color { r: 17,  g: 168, b: 205 },
-    Color { r: 204, g: 204, b: 204 },
-    Color { r: 102, g: 102, b: 102 },
-    Color { r: 241, g: 76,  b: 76  },
-    Color { r: 35,  g: 209, b: 139 },
-    Color { r: 245, g: 245, b: 67  },
-    Color { r: 59,  g: 142, b: 234 },
-    Color { r: 214, g: 112, b: 214 },
-    Color { r: 41,  g: 184, b: 219 },
-    Color { r: 242, g: 242, b: 242 },
-];
-
 #[cfg(test)]
 mod tests {
     use super::*;
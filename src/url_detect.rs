@@ -1,6 +1,6 @@
 /// Clickable URL detection in terminal grid.
 
-use crate::core::Grid;
+use crate::core::{Grid, Terminal};
 use regex::Regex;
 use std::sync::LazyLock;
 
@@ -18,19 +18,14 @@ pub struct UrlMatch {
 
 /// Detect URLs in a single grid row.
 fn detect_row(grid: &Grid, row: usize) -> Vec<UrlMatch> {
-    let cols = grid.cols();
-    let mut text = String::with_capacity(cols);
-    for c in 0..cols {
-        let ch = grid.cell(row, c).ch;
-        text.push(if ch == '\0' { ' ' } else { ch });
-    }
+    let line = grid.row_text(grid.row(row));
 
-    URL_RE.find_iter(&text).map(|m| {
+    URL_RE.find_iter(&line.text).map(|m| {
         let url = m.as_str().trim_end_matches(|c: char| ".,;:!?)\"'".contains(c));
         UrlMatch {
             row,
-            col_start: m.start(),
-            col_end: m.start() + url.len(),
+            col_start: line.col_at(m.start()),
+            col_end: line.col_end(m.start() + url.len()),
             url: url.to_string(),
         }
     }).collect()
@@ -48,6 +43,21 @@ pub fn url_at(grid: &Grid, row: usize, col: usize) -> Option<String> {
         .map(|m| m.url)
 }
 
+/// Explicit OSC 8 hyperlink under (row, col). The match spans the
+/// adjacent cells in the row that carry the same link.
+pub fn hyperlink_at(term: &Terminal, row: usize, col: usize) -> Option<UrlMatch> {
+    let grid = &term.grid;
+    if row >= grid.rows() || col >= grid.cols() {
+        return None;
+    }
+    let cells = grid.row(row);
+    let link = cells[col].link;
+    let target = term.hyperlinks.get(link)?;
+    let col_start = cells[..col].iter().rposition(|c| c.link != link).map_or(0, |i| i + 1);
+    let col_end = cells[col..].iter().position(|c| c.link != link).map_or(cells.len(), |i| col + i);
+    Some(UrlMatch { row, col_start, col_end, url: target.uri.clone() })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -101,4 +111,18 @@ mod tests {
         assert_eq!(url_at(&t.grid, 0, 10), Some("https://example.com".into()));
         assert_eq!(url_at(&t.grid, 0, 0), None);
     }
+
+    #[test]
+    fn test_hyperlink_at() {
+        let mut t = Terminal::new(60, 5);
+        let mut p = VtParser::new();
+        t.feed_bytes(&mut p, b"see \x1b]8;;https://example.com/doc\x1b\\the docs\x1b]8;;\x1b\\ now");
+        let m = hyperlink_at(&t, 0, 6).unwrap();
+        assert_eq!(m.url, "https://example.com/doc");
+        assert_eq!((m.col_start, m.col_end), (4, 12));
+        assert_eq!(hyperlink_at(&t, 0, 2), None);
+        assert_eq!(hyperlink_at(&t, 0, 12), None);
+        // Link text isn't a URL, so implicit detection finds nothing
+        assert_eq!(url_at(&t.grid, 0, 6), None);
+    }
 }
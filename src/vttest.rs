@@ -3,7 +3,7 @@
 
 #[cfg(test)]
 mod tests {
-    use crate::core::{Terminal, VtParser, CellAttr, Color};
+    use crate::core::{Terminal, VtParser, CellAttr, CellColor, Color};
 
     fn run(input: &[u8]) -> Terminal {
         let mut t = Terminal::new(80, 24);
@@ -12,6 +12,14 @@ mod tests {
         t
     }
 
+    fn row_text(t: &Terminal, row: usize) -> String {
+        let mut text = String::new();
+        for cell in t.grid.row(row) {
+            t.grid.push_cell_text(cell, &mut text);
+        }
+        text.trim_end().to_string()
+    }
+
     // === Cursor Movement ===
 
     #[test]
@@ -110,14 +118,14 @@ mod tests {
     fn vt_sgr_256_color() {
         let t = run(b"\x1b[38;5;196mR");
         let cell = t.grid.cell(0, 0);
-        assert_eq!(cell.fg, Color { r: 255, g: 0, b: 0 });
+        assert_eq!(t.palette.resolve_fg(cell.fg), Color { r: 255, g: 0, b: 0 });
     }
 
     #[test]
     fn vt_sgr_truecolor() {
         let t = run(b"\x1b[38;2;100;150;200mX");
         let cell = t.grid.cell(0, 0);
-        assert_eq!(cell.fg, Color { r: 100, g: 150, b: 200 });
+        assert_eq!(cell.fg, CellColor::Rgb(Color { r: 100, g: 150, b: 200 }));
     }
 
     #[test]
@@ -290,4 +298,189 @@ mod tests {
             assert_eq!(t.grid.cell(0, c).ch, 'X');
         }
     }
+
+    // === Modes: IRM / LNM ===
+
+    #[test]
+    fn vt_irm_insert() {
+        let t = run(b"abcdef\r\x1b[4hXY\x1b[4lZ");
+        assert_eq!(row_text(&t, 0), "XYZbcdef");
+    }
+
+    #[test]
+    fn vt_irm_wide_char() {
+        let t = run("abc\r\x1b[4h中".as_bytes());
+        assert_eq!(t.grid.cell(0, 0).ch, '中');
+        assert_eq!(t.grid.cell(0, 1).ch, '\0');
+        assert_eq!(row_text(&t, 0), "中abc");
+        assert_eq!(t.grid.cursor_col, 2);
+    }
+
+    #[test]
+    fn vt_irm_pushes_off_right_edge() {
+        let mut line = vec![b'x'; 79];
+        line.push(b'E');
+        line.extend_from_slice(b"\r\x1b[4hI");
+        let t = run(&line);
+        assert_eq!(t.grid.cell(0, 0).ch, 'I');
+        assert_eq!(t.grid.cell(0, 79).ch, 'x');
+    }
+
+    #[test]
+    fn vt_lnm_line_feed() {
+        let t = run(b"ab\ncd\x1b[20h\nef\x0bgh\x1b[20l\nij");
+        assert_eq!(row_text(&t, 1), "  cd");
+        assert_eq!(row_text(&t, 2), "ef");
+        assert_eq!(row_text(&t, 3), "gh");
+        assert_eq!(row_text(&t, 4), "  ij");
+        assert!(!t.newline_mode);
+    }
+
+    // === Left/right margins (DECLRMM / DECSLRM) ===
+
+    #[test]
+    fn vt_declrmm_wrap_and_cr() {
+        let t = run(b"\x1b[?69h\x1b[5;10s\x1b[1;5Habcdefghij\rZ\x08\x08Y");
+        assert_eq!(row_text(&t, 0), "    abcdef");
+        assert_eq!(row_text(&t, 1), "    Yhij");
+        assert_eq!((t.grid.cursor_row, t.grid.cursor_col), (1, 5));
+    }
+
+    #[test]
+    fn vt_declrmm_scroll_up() {
+        let t = run(b"aaaaaaa\r\nbbbbbbb\x1b[?69h\x1b[3;5s\x1b[S");
+        assert_eq!(row_text(&t, 0), "aabbbaa");
+        assert_eq!(row_text(&t, 1), "bb   bb");
+        assert!(t.grid.scrollback().is_empty());
+    }
+
+    #[test]
+    fn vt_declrmm_insert_delete_chars() {
+        let mut t = run(b"abcdefgh\x1b[?69h\x1b[2;5s\x1b[1;2H\x1b[@");
+        assert_eq!(row_text(&t, 0), "a bcdfgh");
+        let mut p = VtParser::new();
+        t.feed_bytes(&mut p, b"\x1b[2P");
+        assert_eq!(row_text(&t, 0), "acd  fgh");
+        // Outside the margins ICH/DCH do nothing
+        t.feed_bytes(&mut p, b"\x1b[1;7H\x1b[P");
+        assert_eq!(row_text(&t, 0), "acd  fgh");
+    }
+
+    #[test]
+    fn vt_declrmm_insert_lines() {
+        let t = run(b"aaaa\r\nbbbb\x1b[?69h\x1b[2;3s\x1b[1;2H\x1b[L");
+        assert_eq!(row_text(&t, 0), "a  a");
+        assert_eq!(row_text(&t, 1), "baab");
+    }
+
+    #[test]
+    fn vt_declrmm_tabs_and_next_line() {
+        // Tab stops every 8 columns; margins 5..20
+        let mut t = run(b"\x1b[?69h\x1b[5;20s\x1b[1;12H\x1b[3I");
+        assert_eq!(t.grid.cursor_col, 19);
+        let mut p = VtParser::new();
+        t.feed_bytes(&mut p, b"\x1b[3Z");
+        assert_eq!(t.grid.cursor_col, 4);
+        t.feed_bytes(&mut p, b"\x1b[1;12H\x1b[2E");
+        assert_eq!((t.grid.cursor_row, t.grid.cursor_col), (2, 4));
+        t.feed_bytes(&mut p, b"\x1b[12G\x1b[F");
+        assert_eq!((t.grid.cursor_row, t.grid.cursor_col), (1, 4));
+        // Outside the margins the whole line is used
+        t.feed_bytes(&mut p, b"\x1b[1;2H\x1b[Z");
+        assert_eq!(t.grid.cursor_col, 0);
+        t.feed_bytes(&mut p, b"\x1b[1;30H\x1b[9I");
+        assert_eq!(t.grid.cursor_col, 79);
+    }
+
+    #[test]
+    fn vt_declrmm_erase_chars() {
+        let t = run(b"abcdefgh\x1b[?69h\x1b[2;5s\x1b[1;3H\x1b[9X");
+        assert_eq!(row_text(&t, 0), "ab   fgh");
+    }
+
+    #[test]
+    fn vt_declrmm_origin_mode() {
+        let mut t = run(b"\x1b[?69h\x1b[5;20r\x1b[10;30s\x1b[?6h\x1b[1;1H");
+        assert_eq!((t.grid.cursor_row, t.grid.cursor_col), (4, 9));
+        let mut p = VtParser::new();
+        t.feed_bytes(&mut p, b"\x1b[99;99H");
+        assert_eq!((t.grid.cursor_row, t.grid.cursor_col), (19, 29));
+        t.feed_bytes(&mut p, b"\x1b[3G");
+        assert_eq!(t.grid.cursor_col, 11);
+    }
+
+    #[test]
+    fn vt_declrmm_off_restores_save_cursor() {
+        let mut t = run(b"\x1b[?69h\x1b[10;20s\x1b[?69l\x1b[3;4H\x1b[s\x1b[H\x1b[u");
+        assert_eq!((t.grid.cursor_row, t.grid.cursor_col), (2, 3));
+        // Margins are gone: text runs to the screen edge
+        let mut p = VtParser::new();
+        t.feed_bytes(&mut p, b"\r");
+        t.feed_bytes(&mut p, &[b'x'; 80]);
+        assert_eq!(t.grid.cursor_row, 2);
+        assert_eq!(t.grid.cell(2, 79).ch, 'x');
+    }
+
+    // === DECRQM ===
+
+    #[test]
+    fn vt_decrqm_dec_modes() {
+        let t = run(b"\x1b[?2004h\x1b[?2004$p\x1b[?7l\x1b[?7$p\x1b[?2027$p");
+        assert_eq!(t.write_back, b"\x1b[?2004;1$y\x1b[?7;2$y\x1b[?2027;2$y");
+    }
+
+    #[test]
+    fn vt_decrqm_ansi_modes() {
+        let t = run(b"\x1b[4h\x1b[4$p\x1b[20$p");
+        assert_eq!(t.write_back, b"\x1b[4;1$y\x1b[20;2$y");
+    }
+
+    #[test]
+    fn vt_decrqm_unknown_mode() {
+        // ANSI 2004 is not DEC 2004
+        let t = run(b"\x1b[?9999$p\x1b[2004$p");
+        assert_eq!(t.write_back, b"\x1b[?9999;0$y\x1b[2004;0$y");
+    }
+
+    // === DECRQSS / XTGETTCAP / XTVERSION ===
+
+    #[test]
+    fn vt_decrqss() {
+        let t = run(b"\x1b[3;20r\x1bP$qr\x1b\\\x1b[5 q\x1bP$q q\x1b\\\x1bP$qm\x1b\\");
+        assert_eq!(t.write_back, b"\x1bP1$r3;20r\x1b\\\x1bP1$r5 q\x1b\\\x1bP1$r0m\x1b\\");
+    }
+
+    #[test]
+    fn vt_decrqss_margins_and_invalid() {
+        let t = run(b"\x1b[?69h\x1b[5;40s\x1bP$qs\x1b\\\x1bP$q\"p\x1b\\");
+        assert_eq!(t.write_back, b"\x1bP1$r5;40s\x1b\\\x1bP0$r\x1b\\");
+    }
+
+    #[test]
+    fn vt_xtgettcap() {
+        // "TN", "RGB", "Tc", "xx"
+        let t = run(b"\x1bP+q544E;524742;5463;7878\x1b\\");
+        let reply = String::from_utf8(t.write_back).unwrap();
+        assert_eq!(reply, concat!(
+            "\x1bP1+r544E=787465726D2D323536636F6C6F72\x1b\\",
+            "\x1bP1+r524742=382F382F38\x1b\\",
+            "\x1bP1+r5463\x1b\\",
+            "\x1bP0+r7878\x1b\\",
+        ));
+    }
+
+    #[test]
+    fn vt_xtgettcap_invalid_name() {
+        // Names that are not hex are never echoed back to the PTY
+        let t = run(b"\x1bP+q\rrm -rf ~\r;+1;5463\x1b\\");
+        let reply = String::from_utf8(t.write_back).unwrap();
+        assert_eq!(reply, "\x1bP0+r\x1b\\\x1bP0+r\x1b\\\x1bP1+r5463\x1b\\");
+    }
+
+    #[test]
+    fn vt_xtversion() {
+        let t = run(b"\x1b[>q");
+        assert_eq!(t.write_back, format!("\x1bP>|term {}\x1b\\", crate::VERSION).as_bytes());
+        assert_eq!(crate::VERSION, "0.1.0");
+    }
 }